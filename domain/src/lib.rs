@@ -1,14 +1,209 @@
+//! Types shared between the tracker server and its clients. With the `schemars` feature they
+//! additionally implement `JsonSchema`.
+
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::time::Duration;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct TrackerInformation {
     pub key: String,
     pub id: String,
     pub description: Option<String>,
     #[serde(with = "humantime_serde")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub duration: Duration,
     pub running: bool,
     pub start_time: DateTime<Local>,
+    /// Local tracker without Jira issue, never submitted to Tempo
+    #[serde(default)]
+    pub external: bool,
+    /// Account id of the colleague the issue was worked on with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paired_with: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<IssueDetails>,
+    /// Custom fields of integrations, e.g. a pull request number
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct IssueDetails {
+    pub summary: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issue_type: Option<IconReference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<IconReference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct IconReference {
+    pub name: String,
+    pub icon_url: Option<String>,
+}
+
+/// Amount of information returned for a tracker, `?detail=` on tracker endpoints
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Detail {
+    #[default]
+    Summary,
+    Full,
+}
+
+/// Body of `PUT /trackers/:key`
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(untagged, deny_unknown_fields)]
+pub enum AdjustTracker {
+    SetDescription {
+        description: Option<String>,
+    },
+    PositiveDuration {
+        #[serde(
+            rename = "plus",
+            alias = "add",
+            alias = "increase",
+            with = "humantime_serde"
+        )]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+        duration: Duration,
+        #[serde(alias = "from")]
+        using: Option<String>,
+    },
+    NegativeDuration {
+        #[serde(
+            rename = "minus",
+            alias = "sub",
+            alias = "subtract",
+            alias = "decrease",
+            with = "humantime_serde"
+        )]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+        duration: Duration,
+        #[serde(alias = "to")]
+        using: Option<String>,
+    },
+}
+
+/// Body of `PATCH /trackers/:key`, a merge patch of the custom fields: `null` removes a field
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct PatchTracker {
+    pub fields: BTreeMap<String, Option<String>>,
+}
+
+/// Body of `POST /trackers/:key/pair`, unpairing without account id
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Pair {
+    pub account_id: Option<String>,
+}
+
+/// Response of `GET /sum`
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Sum {
+    /// Sum of the whole seconds shown per tracker, so that it matches the listed trackers
+    #[serde(with = "humantime_serde")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub duration: Duration,
+    /// Sum with full precision, up to a second per tracker more than `duration`
+    #[serde(default, with = "humantime_serde")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub exact: Duration,
+}
+
+/// Change of a tracker, e.g. caused by editing the state file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TrackerEvent {
+    Created {
+        key: String,
+    },
+    Changed {
+        key: String,
+    },
+    /// Time was added to or removed from the tracker
+    Adjusted {
+        key: String,
+    },
+    Removed {
+        key: String,
+    },
+    Started {
+        key: String,
+    },
+    Paused {
+        key: String,
+    },
+    /// Worklog of the tracker was created in Tempo
+    Submitted {
+        key: String,
+    },
+    /// Tracker was deleted and can be restored until it is purged
+    PurgePending {
+        key: String,
+        purge_at: DateTime<Local>,
+    },
+    Purged {
+        key: String,
+    },
+}
+
+impl TrackerEvent {
+    pub fn key(&self) -> &str {
+        match self {
+            TrackerEvent::Created { key }
+            | TrackerEvent::Changed { key }
+            | TrackerEvent::Adjusted { key }
+            | TrackerEvent::Removed { key }
+            | TrackerEvent::Started { key }
+            | TrackerEvent::Paused { key }
+            | TrackerEvent::Submitted { key }
+            | TrackerEvent::PurgePending { key, .. }
+            | TrackerEvent::Purged { key } => key,
+        }
+    }
+}
+
+/// Output format of reports, `?format=` on report endpoints
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Text,
+    #[serde(alias = "md")]
+    Markdown,
+    Html,
+    Csv,
+    /// The trackers of the report, supports `?fields=`
+    Json,
+}
+
+impl ReportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ReportFormat::Text => "text/plain; charset=utf-8",
+            ReportFormat::Markdown => "text/markdown; charset=utf-8",
+            ReportFormat::Html => "text/html; charset=utf-8",
+            ReportFormat::Csv => "text/csv; charset=utf-8",
+            ReportFormat::Json => "application/json",
+        }
+    }
 }
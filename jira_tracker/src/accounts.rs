@@ -0,0 +1,135 @@
+//! Submitted time by Tempo account and account category, see `GET /report/accounts`
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use chrono::{Datelike, NaiveDate};
+use futures::future::try_join_all;
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::app_data::Submission;
+use crate::report::escape_csv;
+use crate::tempo_api::{TempoAccount, TempoApi, WorklogReceipt};
+use crate::upstream::UpstreamError;
+
+/// Tempo requests at once, so that reports of long periods don't run into its rate limit
+const CONCURRENT_REQUESTS: usize = 8;
+
+#[derive(Debug, Serialize)]
+pub struct AccountTime {
+    /// `None` for worklogs without account
+    account: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+    /// Type of the category, e.g. capitalized or operational
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category_type: Option<String>,
+    #[serde(with = "humantime_serde")]
+    duration: Duration,
+    worklogs: usize,
+}
+
+/// First day of the quarter of `date`
+pub fn quarter_start_of(date: NaiveDate) -> NaiveDate {
+    let month = (date.month0() / 3) * 3 + 1;
+    NaiveDate::from_ymd_opt(date.year(), month, 1).unwrap()
+}
+
+/// Account of the receipt, looked up in Tempo for receipts of older versions without one
+async fn account_of(
+    api: &TempoApi,
+    receipt: &WorklogReceipt,
+) -> Result<Option<String>, UpstreamError> {
+    match &receipt.account {
+        Some(account) => Ok(Some(account.clone())),
+        None => api.get_worklog_account(receipt.tempo_worklog_id).await,
+    }
+}
+
+/// Time of the worklogs starting between `from` and `to`, both inclusive, by account. Worklogs of
+/// older versions without start count on the day they were submitted. The most used accounts come
+/// first.
+pub async fn report(
+    api: &TempoApi,
+    submissions: &[Submission],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<AccountTime>, UpstreamError> {
+    let receipts: Vec<&WorklogReceipt> = submissions
+        .iter()
+        .flat_map(|submission| {
+            let submitted = submission.submitted_at();
+            submission.receipts().iter().filter(move |receipt| {
+                let date = receipt.start_time.unwrap_or(submitted).date_naive();
+                from <= date && date <= to
+            })
+        })
+        .collect();
+    let mut accounts = Vec::with_capacity(receipts.len());
+    for chunk in receipts.chunks(CONCURRENT_REQUESTS) {
+        accounts.extend(try_join_all(chunk.iter().map(|receipt| account_of(api, receipt))).await?);
+    }
+
+    let mut times: IndexMap<Option<String>, (Duration, usize)> = IndexMap::new();
+    for (receipt, account) in receipts.iter().zip(accounts) {
+        let (duration, worklogs) = times.entry(account).or_default();
+        *duration += receipt.duration;
+        *worklogs += 1;
+    }
+    let keys: Vec<&String> = times.keys().flatten().collect();
+    let mut details: HashMap<String, TempoAccount> = HashMap::new();
+    for chunk in keys.chunks(CONCURRENT_REQUESTS) {
+        let accounts = try_join_all(chunk.iter().map(|key| api.get_account(key))).await?;
+        details.extend(
+            accounts
+                .into_iter()
+                .map(|account| (account.key.clone(), account)),
+        );
+    }
+
+    let mut report: Vec<AccountTime> = times
+        .into_iter()
+        .map(|(account, (duration, worklogs))| {
+            let details = account.as_ref().and_then(|key| details.get(key));
+            let category = details.and_then(|details| details.category.as_ref());
+            AccountTime {
+                name: details.map(|details| details.name.clone()),
+                category: category.map(|category| category.name.clone()),
+                category_type: category
+                    .and_then(|category| category.category_type.as_ref())
+                    .map(|category_type| category_type.name.clone()),
+                account,
+                duration,
+                worklogs,
+            }
+        })
+        .collect();
+    report.sort_by_key(|time| Reverse(time.duration));
+    Ok(report)
+}
+
+/// CSV with durations in hours, as finance wants them
+pub fn render_csv(report: &[AccountTime]) -> String {
+    let mut out = String::new();
+    writeln!(out, "account,name,category,category_type,hours,worklogs").unwrap();
+    for time in report {
+        let field = |value: &Option<String>| escape_csv(value.as_deref().unwrap_or_default());
+        writeln!(
+            out,
+            "{},{},{},{},{:.2},{}",
+            field(&time.account),
+            field(&time.name),
+            field(&time.category),
+            field(&time.category_type),
+            time.duration.as_secs_f64() / 3600.0,
+            time.worklogs
+        )
+        .unwrap();
+    }
+    out
+}
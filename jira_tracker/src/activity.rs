@@ -0,0 +1,137 @@
+//! Coarse activity metadata like the focused app, fed by `POST /heartbeat/activity`. Only app names
+//! and categories are accepted, never window titles or content, and samples are kept in memory for
+//! `ACTIVITY_RETENTION` at most.
+
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+
+/// Time attributed to a heartbeat at most, so that a pause between heartbeats is not counted
+const MAX_GAP: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Activity {
+    app: String,
+    #[serde(default)]
+    category: Option<String>,
+}
+
+#[derive(Debug)]
+struct Sample {
+    key: String,
+    app: String,
+    category: Option<String>,
+    at: SystemTime,
+    /// Time until the next heartbeat, at most [`MAX_GAP`]
+    duration: Duration,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppUsage {
+    app: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+    #[serde(with = "humantime_serde")]
+    duration: Duration,
+    heartbeats: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssueActivity {
+    key: String,
+    apps: Vec<AppUsage>,
+}
+
+/// Activity samples of the last `retention`, attributed to the tracker running at the time
+#[derive(Debug)]
+pub struct ActivityLog {
+    retention: Option<Duration>,
+    samples: RwLock<VecDeque<Sample>>,
+}
+
+impl ActivityLog {
+    /// Without `retention` no activity is recorded at all
+    pub fn new(retention: Option<Duration>) -> Self {
+        Self {
+            retention,
+            samples: RwLock::new(VecDeque::new()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.retention.is_some()
+    }
+
+    fn prune(&self, samples: &mut VecDeque<Sample>) {
+        let retention = self.retention.unwrap_or_default();
+        while samples
+            .front()
+            .is_some_and(|sample| clock::elapsed(sample.at) >= retention)
+        {
+            samples.pop_front();
+        }
+    }
+
+    /// Records the activity for the running tracker `key`, returns whether it was recorded
+    pub fn record(&self, key: Option<String>, activity: Activity) -> bool {
+        let Some(key) = key.filter(|_| self.enabled()) else {
+            return false;
+        };
+        let mut samples = self.samples.write().unwrap();
+        self.prune(&mut samples);
+        let now = clock::now();
+        if let Some(previous) = samples.back_mut() {
+            previous.duration = clock::elapsed(previous.at).min(MAX_GAP);
+        }
+        samples.push_back(Sample {
+            key,
+            app: activity.app,
+            category: activity.category.filter(|c| !c.is_empty()),
+            at: now,
+            duration: Duration::ZERO,
+        });
+        true
+    }
+
+    /// Apps used per issue, the most used first
+    pub fn report(&self) -> Vec<IssueActivity> {
+        let mut samples = self.samples.write().unwrap();
+        self.prune(&mut samples);
+        let mut issues: IndexMap<&str, IndexMap<&str, AppUsage>> = IndexMap::new();
+        for sample in samples.iter() {
+            let usage = issues
+                .entry(&sample.key)
+                .or_default()
+                .entry(&sample.app)
+                .or_insert_with(|| AppUsage {
+                    app: sample.app.clone(),
+                    category: sample.category.clone(),
+                    duration: Duration::ZERO,
+                    heartbeats: 0,
+                });
+            usage.duration += sample.duration;
+            usage.heartbeats += 1;
+        }
+        let mut report: Vec<_> = issues
+            .into_iter()
+            .map(|(key, apps)| {
+                let mut apps: Vec<_> = apps.into_values().collect();
+                apps.sort_by_key(|usage| Reverse(usage.duration));
+                IssueActivity {
+                    key: key.to_string(),
+                    apps,
+                }
+            })
+            .collect();
+        report
+            .sort_by_key(|issue| Reverse(issue.apps.iter().map(|a| a.duration).sum::<Duration>()));
+        report
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use serde::Serialize;
+
+use domain::TrackerInformation;
+
+use crate::app_data::TrackerSession;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    pub max_issue_duration: Duration,
+    pub max_switches: usize,
+    pub max_session: Duration,
+    pub late_hour: NaiveTime,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Anomaly {
+    /// Single issue tracked longer than the threshold on one day
+    LongIssue {
+        date: NaiveDate,
+        key: String,
+        #[serde(with = "humantime_serde")]
+        duration: Duration,
+    },
+    /// Single session tracked longer than the threshold without a break
+    LongSession {
+        date: NaiveDate,
+        key: String,
+        start: DateTime<Local>,
+        #[serde(with = "humantime_serde")]
+        duration: Duration,
+    },
+    /// More issues than the threshold on one day, each change of issue being a context switch
+    ManySwitches { date: NaiveDate, switches: usize },
+    /// Tracking ended after the configured hour, assuming trackers ran without interruption
+    LateEnd { date: NaiveDate, end: NaiveTime },
+}
+
+/// Anomalies of all days with trackers, ordered by day. `sessions` are those of the trackers by
+/// their keys.
+pub fn detect(
+    trackers: &[TrackerInformation],
+    sessions: &[(String, Vec<TrackerSession>)],
+    thresholds: Thresholds,
+) -> Vec<Anomaly> {
+    let mut days: BTreeMap<NaiveDate, Vec<&TrackerInformation>> = BTreeMap::new();
+    for tracker in trackers {
+        days.entry(tracker.start_time.date_naive())
+            .or_default()
+            .push(tracker);
+    }
+
+    let mut anomalies = Vec::new();
+    for (date, trackers) in days {
+        for tracker in &trackers {
+            if tracker.duration > thresholds.max_issue_duration {
+                anomalies.push(Anomaly::LongIssue {
+                    date,
+                    key: tracker.key.clone(),
+                    duration: tracker.duration,
+                });
+            }
+        }
+        let long_sessions = sessions
+            .iter()
+            .flat_map(|(key, sessions)| sessions.iter().map(move |session| (key, session)))
+            .filter(|(_, session)| {
+                session.start().date_naive() == date && session.duration() > thresholds.max_session
+            });
+        for (key, session) in long_sessions {
+            anomalies.push(Anomaly::LongSession {
+                date,
+                key: key.clone(),
+                start: session.start(),
+                duration: session.duration(),
+            });
+        }
+        let switches = trackers.len().saturating_sub(1);
+        if switches > thresholds.max_switches {
+            anomalies.push(Anomaly::ManySwitches { date, switches });
+        }
+        let end = trackers
+            .iter()
+            .filter_map(|t| {
+                chrono::Duration::from_std(t.duration)
+                    .ok()
+                    .map(|d| t.start_time + d)
+            })
+            .max();
+        if let Some(end) = end {
+            let end_time = end.time();
+            if end.date_naive() > date || end_time > thresholds.late_hour {
+                anomalies.push(Anomaly::LateEnd {
+                    date,
+                    end: end_time,
+                });
+            }
+        }
+    }
+    anomalies
+}
@@ -1,22 +1,47 @@
 use core::option::Option;
 use core::result::Result;
 use core::result::Result::{Err, Ok};
-use std::ops::{AddAssign, Deref, DerefMut};
-use std::path::PathBuf;
-use std::sync::RwLock;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, SystemTime};
 
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Datelike, IsoWeek, Local, NaiveDate};
 use indexmap::IndexMap;
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+use tokio::sync::{broadcast, Notify};
 
-use domain::TrackerInformation;
+use domain::{AdjustTracker, Detail, IssueDetails, Sum, TrackerEvent, TrackerInformation};
 
-use crate::config::AppConfig;
+use crate::audit::{AuditEntry, AuditLog};
+use crate::clock;
+use crate::config::{AppConfig, ResumePolicy, SubmissionGrouping};
+use crate::day::{DaySummary, DayTotals};
+use crate::events;
 use crate::files;
+use crate::files::{FileKey, Migration};
+use crate::goals::{Goal, GoalProgress};
+use crate::lease::WriterLease;
+use crate::rounding;
+use crate::rounding::RoundingPolicy;
+use crate::scheduler::Scheduler;
+use crate::storage;
+use crate::storage::{
+    Encoded, MigratedStorage, MigrationError, Storage, StorageBackend, StorageError,
+};
+use crate::tempo_api::{FailedWorklog, WorklogReceipt, MINIMUM_DURATION};
+use crate::upstream::UpstreamError;
+use crate::week_archive::{SessionRow, WorklogRow};
+use crate::write_ahead::WriteAheadLog;
 
 #[derive(Debug)]
 pub enum TrackerError {
@@ -24,6 +49,22 @@ pub enum TrackerError {
     OccupiedError,
     NotFoundError,
     DurationAdjustmentError,
+    UnknownProjectError(String),
+    NotExternalError,
+    /// Jira does not know the issue, with the error detail of Jira
+    IssueNotFoundError(String),
+    /// Jira could not be asked for the issue, e.g. with expired credentials
+    UpstreamError(UpstreamError),
+}
+
+impl TrackerError {
+    /// Failed lookup of the issue of a tracker
+    pub fn issue_lookup(error: UpstreamError) -> Self {
+        match error {
+            UpstreamError::JiraNotFound(detail) => TrackerError::IssueNotFoundError(detail),
+            error => TrackerError::UpstreamError(error),
+        }
+    }
 }
 
 impl IntoResponse for TrackerError {
@@ -33,108 +74,910 @@ impl IntoResponse for TrackerError {
             TrackerError::OccupiedError => StatusCode::CONFLICT,
             TrackerError::NotFoundError => StatusCode::NOT_FOUND,
             TrackerError::DurationAdjustmentError => StatusCode::BAD_REQUEST,
+            TrackerError::NotExternalError => StatusCode::BAD_REQUEST,
+            TrackerError::UnknownProjectError(project) => {
+                let message = format!("project {} does not exist", project);
+                return (StatusCode::NOT_FOUND, message).into_response();
+            }
+            TrackerError::IssueNotFoundError(detail) => {
+                let body = serde_json::json!({ "error": "issue_not_found", "detail": detail });
+                return (StatusCode::NOT_FOUND, axum::Json(body)).into_response();
+            }
+            TrackerError::UpstreamError(error) => return error.into_response(),
         };
         status_code.into_response()
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Why a state given to `POST /import` was rejected
+#[derive(Debug)]
+pub enum ImportError {
+    /// Not a state of this or an older version
+    Format(String),
+    /// Inconsistencies of the state
+    Invalid(Vec<String>),
+}
+
+impl IntoResponse for ImportError {
+    fn into_response(self) -> Response {
+        match self {
+            ImportError::Format(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+            ImportError::Invalid(problems) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, axum::Json(problems)).into_response()
+            }
+        }
+    }
+}
+
+/// How `POST /import` applies an exported state
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Adds trackers and archived trackers that do not exist yet, keeping existing ones
+    #[default]
+    Merge,
+    /// Replaces the whole state
+    Replace,
+}
+
+/// Response of `POST /import`
+#[derive(Debug, Serialize)]
+pub struct Imported {
+    /// Keys of the trackers taken from the imported state
+    pub imported: Vec<String>,
+    /// Keys of imported trackers that were kept as they are because they exist already
+    pub skipped: Vec<String>,
+    /// Number of archived trackers taken from the imported state
+    pub archived: usize,
+}
+
+/// Response of `POST /admin/compact`
+#[derive(Debug, Default, Serialize)]
+pub struct Compaction {
+    /// Keys of the trackers whose adjustments were folded into one
+    pub folded: Vec<String>,
+    /// Number of archived trackers submitted before the retention window
+    pub archived: usize,
+    /// Keys of the deleted trackers that were purged
+    pub purged: Vec<String>,
+    /// Number of days dropped from the ledger of `GET /days/...` before the retention window
+    pub ledger_days: usize,
+}
+
+/// Number of changes that can be undone
+const HISTORY_SIZE: usize = 50;
+
+/// How long sessions are kept for `GET /sum/delta`
+const SESSION_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// More reloads of changed state within `RELOAD_WINDOW` are taken for a loop with another writer
+const MAX_RELOADS: usize = 5;
+const RELOAD_WINDOW: Duration = Duration::from_secs(10);
+/// How long changes of the state are ignored after a reload loop was detected
+const RELOAD_PAUSE: Duration = Duration::from_secs(60);
+
+/// How often deleted trackers are checked for purging
+const PURGE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often a standby instance checks whether it may write its unsaved changes
+const STANDBY_FLUSH_RETRY: Duration = Duration::from_secs(1);
+
+/// Key of the external tracker collecting time that is not yet assigned to an issue
+pub const UNKNOWN_KEY: &str = "unknown";
+
+fn is_jira_key(key: &str) -> bool {
+    Regex::new(r"\w+-\d+").unwrap().is_match(key)
+}
+
+/// Inconsistencies that can only be introduced by editing the state file by hand
+#[derive(Debug)]
+enum StateViolation {
+    /// The running tracker refers to a key without a tracker
+    UnknownRunningTracker(String),
+    /// The running tracker started in the future and would never accrue time
+    RunningInFuture(String),
+    /// The tracker to resume after suspension refers to a key without a tracker
+    UnknownSuspendedTracker(String),
+    /// A tracker is to be resumed after suspension although one is running
+    SuspendedWhileRunning(String),
+    /// The session of `key` starting at `start` overlaps time of `overlapped` until `until`, so
+    /// that the time accrued twice. The running tracker counts as a session until now.
+    OverlappingSessions {
+        key: String,
+        start: DateTime<Local>,
+        overlapped: String,
+        until: DateTime<Local>,
+    },
+}
+
+impl Display for StateViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StateViolation::UnknownRunningTracker(key) => {
+                write!(f, "running tracker {} does not exist", key)
+            }
+            StateViolation::RunningInFuture(key) => {
+                write!(f, "running tracker {} started in the future", key)
+            }
+            StateViolation::UnknownSuspendedTracker(key) => {
+                write!(f, "suspended tracker {} does not exist", key)
+            }
+            StateViolation::SuspendedWhileRunning(key) => {
+                write!(
+                    f,
+                    "suspended tracker {} is kept although one is running",
+                    key
+                )
+            }
+            StateViolation::OverlappingSessions {
+                key,
+                start,
+                overlapped,
+                until,
+            } => write!(
+                f,
+                "session of {} at {} overlaps {} until {}",
+                key, start, overlapped, until
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PausedTracker {
     id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
-    duration: Duration,
+    /// Times the tracker ran, appended on each pause
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    sessions: Vec<Interval>,
     #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     positive_adjustments: Vec<Duration>,
     #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     negative_adjustments: Vec<Duration>,
     start_time: DateTime<Local>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    details: Option<IssueDetails>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    external: bool,
+    /// Account id of the colleague the issue was worked on with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    paired_with: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    fields: BTreeMap<String, String>,
+    /// Migrated from the legacy state without issue ids, until its id is resolved via Jira
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    legacy: bool,
+}
+
+/// Time a tracker ran, from start to pause
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Interval {
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+}
+
+impl Interval {
+    fn duration(&self) -> Duration {
+        (self.end - self.start).to_std().unwrap_or_default()
+    }
+}
+
+/// Entries of `side` added since `base`, all of them if `side` does not continue `base`
+fn added_since<'a, T: PartialEq>(base: &[T], side: &'a [T]) -> &'a [T] {
+    side.strip_prefix(base).unwrap_or(side)
 }
 
 impl PausedTracker {
-    fn new<S: Into<String>>(id: S) -> Self {
+    /// Tracker changed both in memory and outside since `base`: the fields of `theirs` with the
+    /// time and adjustments of both sides
+    fn merged(base: Option<&PausedTracker>, mine: &PausedTracker, theirs: &PausedTracker) -> Self {
+        let (base_sessions, base_positive, base_negative) = match base {
+            Some(base) => (
+                &base.sessions[..],
+                &base.positive_adjustments[..],
+                &base.negative_adjustments[..],
+            ),
+            None => (&[][..], &[][..], &[][..]),
+        };
+        let mut merged = theirs.clone();
+        merged
+            .sessions
+            .extend_from_slice(added_since(base_sessions, &mine.sessions));
+        let continued = mine.positive_adjustments.starts_with(base_positive)
+            && mine.negative_adjustments.starts_with(base_negative);
+        if continued {
+            merged
+                .positive_adjustments
+                .extend_from_slice(added_since(base_positive, &mine.positive_adjustments));
+            merged
+                .negative_adjustments
+                .extend_from_slice(added_since(base_negative, &mine.negative_adjustments));
+        } else {
+            // folded by a compaction since the base, only the net change since then is new
+            let base_net = base.map_or(0, PausedTracker::net_adjustment);
+            let added = mine.net_adjustment() - base_net;
+            let added_duration = Duration::from_nanos(added.unsigned_abs() as u64);
+            match added.signum() {
+                1 => merged.positive_adjustments.push(added_duration),
+                -1 => merged.negative_adjustments.push(added_duration),
+                _ => {}
+            }
+        }
+        merged
+    }
+
+    /// Positive minus negative adjustments in nanoseconds
+    fn net_adjustment(&self) -> i128 {
+        let sum =
+            |adjustments: &[Duration]| adjustments.iter().map(Duration::as_nanos).sum::<u128>();
+        sum(&self.positive_adjustments) as i128 - sum(&self.negative_adjustments) as i128
+    }
+
+    /// Whether the trackers only differ in how their adjustments are split up
+    fn same_but_folded(&self, other: &PausedTracker) -> bool {
+        let without_adjustments = |tracker: &PausedTracker| PausedTracker {
+            positive_adjustments: Vec::new(),
+            negative_adjustments: Vec::new(),
+            ..tracker.clone()
+        };
+        self.net_adjustment() == other.net_adjustment()
+            && without_adjustments(self) == without_adjustments(other)
+    }
+
+    fn new<S: Into<String>>(id: S, details: Option<IssueDetails>) -> Self {
         Self {
             id: id.into(),
             description: None,
-            duration: Duration::default(),
+            sessions: Vec::new(),
             positive_adjustments: Vec::new(),
             negative_adjustments: Vec::new(),
-            start_time: Local::now(),
+            start_time: clock::local_now(),
+            details,
+            external: false,
+            paired_with: None,
+            fields: BTreeMap::new(),
+            legacy: false,
         }
     }
-}
 
-impl AddAssign<&RunningTracker> for PausedTracker {
-    fn add_assign(&mut self, rhs: &RunningTracker) {
-        self.duration += rhs.start_time.elapsed().unwrap_or_default();
+    fn external() -> Self {
+        Self {
+            external: true,
+            ..Self::new(String::new(), None)
+        }
+    }
+
+    /// Appends the session, extending the last one if it continues it, see [`AppData::checkpoint`]
+    fn add_session(&mut self, session: Interval) {
+        match self.sessions.last_mut() {
+            Some(last) if last.end == session.start => last.end = session.end,
+            _ => self.sessions.push(session),
+        }
+    }
+
+    /// Replaces the adjustments by a single one with their net time, returning whether there
+    /// was more than one
+    fn fold_adjustments(&mut self) -> bool {
+        if self.positive_adjustments.len() + self.negative_adjustments.len() <= 1 {
+            return false;
+        }
+        let positive: Duration = self.positive_adjustments.iter().sum();
+        let negative: Duration = self.negative_adjustments.iter().sum();
+        self.positive_adjustments = positive
+            .checked_sub(negative)
+            .filter(|net| !net.is_zero())
+            .into_iter()
+            .collect();
+        self.negative_adjustments = negative
+            .checked_sub(positive)
+            .filter(|net| !net.is_zero())
+            .into_iter()
+            .collect();
+        true
+    }
+
+    /// Time of all sessions, adjustments not included
+    fn tracked(&self) -> Duration {
+        self.sessions.iter().map(Interval::duration).sum()
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct RunningTracker {
     key: String,
     start_time: SystemTime,
 }
 
+/// Time a tracker was running, from start to pause
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Session {
+    key: String,
+    start: SystemTime,
+    end: SystemTime,
+}
+
+impl Session {
+    /// Part of the session after `since`
+    fn duration_since(&self, since: SystemTime) -> Duration {
+        let start = self.start.max(since);
+        self.end.duration_since(start).unwrap_or_default()
+    }
+
+    /// Parts of the session on each local day it spans
+    fn per_day(&self) -> Vec<(NaiveDate, Duration)> {
+        let mut parts = Vec::new();
+        let mut from: DateTime<Local> = self.start.into();
+        let end: DateTime<Local> = self.end.into();
+        while from < end {
+            let date = from.date_naive();
+            let midnight = date
+                .succ_opt()
+                .and_then(|next| next.and_hms_opt(0, 0, 0))
+                .and_then(|midnight| midnight.and_local_timezone(Local).earliest());
+            let until = midnight.filter(|m| *m > from).map_or(end, |m| m.min(end));
+            parts.push((date, (until - from).to_std().unwrap_or_default()));
+            from = until;
+        }
+        parts
+    }
+}
+
 impl RunningTracker {
     fn new(key: &str) -> Self {
         Self {
             key: key.to_string(),
-            start_time: SystemTime::now(),
+            start_time: clock::now(),
+        }
+    }
+}
+
+/// Outcome of submitting a tracker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubmissionStatus {
+    /// All of its worklogs were created and it was archived
+    Submitted,
+    /// Kept from being submitted by `SUBMIT_RULES` and archived
+    Skipped,
+    /// Some of its worklogs failed, it is kept with the time that was not submitted
+    Failed,
+}
+
+/// Worklogs created by one call to submit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Submission {
+    submitted_at: DateTime<Local>,
+    receipts: Vec<WorklogReceipt>,
+    /// Keys of the trackers `SUBMIT_RULES` kept from being submitted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skipped: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    failed: Vec<FailedWorklog>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    trackers: IndexMap<String, SubmissionStatus>,
+}
+
+impl Submission {
+    pub fn duration(&self) -> Duration {
+        self.receipts.iter().map(|receipt| receipt.duration).sum()
+    }
+
+    pub fn submitted_at(&self) -> DateTime<Local> {
+        self.submitted_at
+    }
+
+    pub fn receipts(&self) -> &[WorklogReceipt] {
+        &self.receipts
+    }
+}
+
+/// Submitted tracker, kept as record of what was submitted, see `GET /archive`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedTracker {
+    key: String,
+    #[serde(flatten)]
+    tracker: PausedTracker,
+    submitted_at: DateTime<Local>,
+    /// Ids of the worklogs created for the tracker, two for a split pair
+    tempo_worklog_ids: Vec<u64>,
+}
+
+impl ArchivedTracker {
+    /// Whether both record the same submission, by their worklogs or, for trackers submitted
+    /// without worklog, by key and time
+    fn same_submission(&self, other: &ArchivedTracker) -> bool {
+        if self.tempo_worklog_ids.is_empty() || other.tempo_worklog_ids.is_empty() {
+            return self.key == other.key && self.submitted_at == other.submitted_at;
+        }
+        self.tempo_worklog_ids == other.tempo_worklog_ids
+    }
+}
+
+/// Tracker deleted with `PURGE_DELAY`, which can be restored until it is purged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DeletedTracker {
+    tracker: PausedTracker,
+    purge_at: DateTime<Local>,
+}
+
+/// Breaks loops of reloading the state, e.g. with another instance or a sync tool writing it back
+#[derive(Default)]
+struct ReloadGuard {
+    recent: VecDeque<SystemTime>,
+    paused_until: Option<SystemTime>,
+    /// Number of detected loops
+    loops: u64,
+}
+
+impl ReloadGuard {
+    fn allow(&mut self) -> bool {
+        let now = clock::now();
+        if self.paused_until.is_some_and(|until| now < until) {
+            return false;
+        }
+        self.recent.push_back(now);
+        while self
+            .recent
+            .front()
+            .is_some_and(|at| clock::elapsed(*at) > RELOAD_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+        if self.recent.len() <= MAX_RELOADS {
+            return true;
+        }
+        tracing::warn!(
+            "state changed {} times within {:?}, another instance or a sync tool may be writing it, ignoring changes for {:?}",
+            self.recent.len(),
+            RELOAD_WINDOW,
+            RELOAD_PAUSE
+        );
+        self.recent.clear();
+        self.paused_until = Some(now + RELOAD_PAUSE);
+        self.loops += 1;
+        false
+    }
+}
+
+/// Deleted tracker as listed by `GET /trash`
+#[derive(Debug, Serialize)]
+pub struct TrashedTracker {
+    #[serde(flatten)]
+    tracker: TrackerInformation,
+    purge_at: DateTime<Local>,
+}
+
+/// Session of a tracker as listed by `GET /trackers/:key/sessions`
+#[derive(Debug, Serialize)]
+pub struct TrackerSession {
+    /// Index of the session, e.g. for `POST /trackers/:key/sessions/:id/split`
+    id: usize,
+    start: DateTime<Local>,
+    /// Missing while the tracker is running
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<DateTime<Local>>,
+    #[serde(with = "humantime_serde")]
+    duration: Duration,
+}
+
+impl TrackerSession {
+    pub fn start(&self) -> DateTime<Local> {
+        self.start
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// Trackers before a change, to derive the events of the change or to undo it
+struct Snapshot {
+    running: Option<RunningTracker>,
+    suspended: Option<RunningTracker>,
+    trackers: IndexMap<String, PausedTracker>,
+    deleted: IndexMap<String, DeletedTracker>,
+    ledger: BTreeMap<NaiveDate, IndexMap<String, Duration>>,
+    sessions: Vec<Session>,
+}
+
+impl Snapshot {
+    /// Whether the trackers of `data` are the same, compared without copying them
+    fn matches(&self, data: &InnerAppData) -> bool {
+        self.running == data.running
+            && self.suspended == data.suspended
+            && self.trackers == data.trackers
+            && self.deleted == data.deleted
+            && self.ledger == data.ledger
+            && self.sessions == data.sessions
+    }
+}
+
+/// States before the latest changes and after undone ones, see [`AppData::undo`]
+#[derive(Default)]
+struct History {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl History {
+    fn record(&mut self, before: Snapshot) {
+        self.undo.push_back(before);
+        if self.undo.len() > HISTORY_SIZE {
+            self.undo.pop_front();
+        }
+        self.redo.clear();
+    }
+
+    /// Forgets all changes, as they would revert the state to one before it was reloaded
+    fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+/// Keys of a map in their order if any was added, removed or moved, and the changed entries
+#[derive(Serialize, Deserialize)]
+struct MapChange<V> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keys: Option<Vec<String>>,
+    #[serde(default = "IndexMap::new", skip_serializing_if = "IndexMap::is_empty")]
+    changed: IndexMap<String, V>,
+}
+
+impl<V: Clone + PartialEq> MapChange<V> {
+    fn between(before: &IndexMap<String, V>, after: &IndexMap<String, V>) -> Option<Self> {
+        let changed: IndexMap<String, V> = after
+            .iter()
+            .filter(|(key, value)| before.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let moved = !before.keys().eq(after.keys());
+        (moved || !changed.is_empty()).then(|| MapChange {
+            keys: moved.then(|| after.keys().cloned().collect()),
+            changed,
+        })
+    }
+
+    fn apply(self, map: &mut IndexMap<String, V>) {
+        map.extend(self.changed);
+        if let Some(keys) = self.keys {
+            let mut previous = std::mem::take(map);
+            *map = keys
+                .into_iter()
+                .filter_map(|key| Some((key.clone(), previous.swap_remove(&key)?)))
+                .collect();
+        }
+    }
+}
+
+/// Sections of the state besides the trackers, journaled as a whole when they may have changed
+#[derive(Serialize, Deserialize)]
+struct Sections {
+    account_id: Option<String>,
+    submissions: Vec<Submission>,
+    archive: Vec<ArchivedTracker>,
+    days: Vec<DaySummary>,
+    goals: Vec<Goal>,
+}
+
+/// Change of the state in the write-ahead log, see `STATE_JOURNAL`. Applied in order on top of the
+/// state saved before, the changes result in the state of the last one.
+#[derive(Serialize, Deserialize)]
+struct Change {
+    running: Option<RunningTracker>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    suspended: Option<RunningTracker>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    trackers: Option<MapChange<PausedTracker>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deleted: Option<MapChange<DeletedTracker>>,
+    /// Changed days of the ledger, removed ones being `null`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    ledger: BTreeMap<NaiveDate, Option<IndexMap<String, Duration>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sessions: Option<Vec<Session>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sections: Option<Sections>,
+}
+
+impl Change {
+    /// `None` if nothing changed, only the trackers are compared unless `sections`
+    fn between(before: &Snapshot, after: &InnerAppData, sections: bool) -> Option<Self> {
+        let mut ledger: BTreeMap<_, _> = before
+            .ledger
+            .keys()
+            .filter(|date| !after.ledger.contains_key(*date))
+            .map(|date| (*date, None))
+            .collect();
+        for (date, day) in &after.ledger {
+            if before.ledger.get(date) != Some(day) {
+                ledger.insert(*date, Some(day.clone()));
+            }
+        }
+        let change = Change {
+            running: after.running.clone(),
+            suspended: after.suspended.clone(),
+            trackers: MapChange::between(&before.trackers, &after.trackers),
+            deleted: MapChange::between(&before.deleted, &after.deleted),
+            ledger,
+            sessions: (before.sessions != after.sessions).then(|| after.sessions.clone()),
+            sections: sections.then(|| Sections {
+                account_id: after.account_id.clone(),
+                submissions: after.submissions.clone(),
+                archive: after.archive.clone(),
+                days: after.days.clone(),
+                goals: after.goals.clone(),
+            }),
+        };
+        let unchanged = change.running == before.running
+            && change.suspended == before.suspended
+            && change.trackers.is_none()
+            && change.deleted.is_none()
+            && change.ledger.is_empty()
+            && change.sessions.is_none()
+            && change.sections.is_none();
+        (!unchanged).then_some(change)
+    }
+
+    fn apply(self, data: &mut InnerAppData) {
+        data.running = self.running;
+        data.suspended = self.suspended;
+        if let Some(trackers) = self.trackers {
+            trackers.apply(&mut data.trackers);
+        }
+        if let Some(deleted) = self.deleted {
+            deleted.apply(&mut data.deleted);
+        }
+        for (date, day) in self.ledger {
+            match day {
+                Some(day) => data.ledger.insert(date, day),
+                None => data.ledger.remove(&date),
+            };
+        }
+        if let Some(sessions) = self.sessions {
+            data.sessions = sessions;
+        }
+        if let Some(sections) = self.sections {
+            data.account_id = sections.account_id;
+            data.submissions = sections.submissions;
+            data.archive = sections.archive;
+            data.days = sections.days;
+            data.goals = sections.goals;
+        }
+    }
+}
+
+/// Upgrades of older state files, the state version being their count
+pub const MIGRATIONS: &[Migration] = &[migrate_tracker_ids, migrate_tracker_sessions];
+
+/// Trackers of the first format had their key as property and may miss the issue id, which is
+/// required since. They become external until their id is resolved, see
+/// [`AppData::resolve_legacy`].
+fn migrate_tracker_ids(state: &mut Value) {
+    let Some(trackers) = state.get_mut("trackers").and_then(Value::as_object_mut) else {
+        return;
+    };
+    for (key, tracker) in trackers.iter_mut() {
+        let Some(tracker) = tracker.as_object_mut() else {
+            continue;
+        };
+        tracker.remove("key");
+        if !tracker.contains_key("id") {
+            tracing::warn!(
+                "tracker {} has no issue id, it is resolved via Jira on startup",
+                key
+            );
+            tracker.insert("id".to_string(), Value::String(String::new()));
+            tracker.insert("external".to_string(), Value::Bool(true));
+            tracker.insert("legacy".to_string(), Value::Bool(true));
+        }
+        if !tracker.contains_key("start_time") {
+            let now = serde_json::to_value(clock::local_now()).unwrap();
+            tracker.insert("start_time".to_string(), now);
+        }
+    }
+}
+
+/// Trackers kept the sum of their time before their sessions were recorded. The sum becomes a
+/// single session from the creation of the tracker on, in the trash and archive as well.
+fn migrate_tracker_sessions(state: &mut Value) {
+    fn migrate(tracker: &mut Value) {
+        let Some(tracker) = tracker.as_object_mut() else {
+            return;
+        };
+        let duration: Duration = tracker
+            .remove("duration")
+            .and_then(|duration| serde_json::from_value(duration).ok())
+            .unwrap_or_default();
+        let start: Option<DateTime<Local>> = tracker
+            .get("start_time")
+            .and_then(|start| serde_json::from_value(start.clone()).ok());
+        if let (false, Some(start)) = (duration.is_zero(), start) {
+            let end = (SystemTime::from(start) + duration).into();
+            let sessions = serde_json::to_value([Interval { start, end }]).unwrap();
+            tracker.insert("sessions".to_string(), sessions);
         }
     }
+    if let Some(trackers) = state.get_mut("trackers").and_then(Value::as_object_mut) {
+        trackers.values_mut().for_each(migrate);
+    }
+    if let Some(deleted) = state.get_mut("deleted").and_then(Value::as_object_mut) {
+        deleted
+            .values_mut()
+            .filter_map(|deleted| deleted.get_mut("tracker"))
+            .for_each(migrate);
+    }
+    if let Some(archive) = state.get_mut("archive").and_then(Value::as_array_mut) {
+        archive.iter_mut().for_each(migrate);
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct InnerAppData {
+pub(crate) struct InnerAppData {
+    /// Number of [`MIGRATIONS`] applied
+    #[serde(default)]
+    version: u64,
     running: Option<RunningTracker>,
+    /// Tracker that was running when the system was suspended, with the time of suspension
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    suspended: Option<RunningTracker>,
+    /// Last resolved Jira account id, used when Jira is unreachable on startup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    account_id: Option<String>,
     trackers: IndexMap<String, PausedTracker>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    deleted: IndexMap<String, DeletedTracker>,
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    submissions: Vec<Submission>,
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    archive: Vec<ArchivedTracker>,
+    /// Summaries of closed days, see `POST /day/close`
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    days: Vec<DaySummary>,
+    /// Recent sessions of all trackers, see `SESSION_RETENTION`
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    sessions: Vec<Session>,
+    /// Time tracked per day and key, kept after submission, see `GET /days/:date`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    ledger: BTreeMap<NaiveDate, IndexMap<String, Duration>>,
+    /// Weekly goals, see `/goals`
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    goals: Vec<Goal>,
+}
+
+/// Size of the state for `GET /diagnostics`, without any keys or descriptions
+#[derive(Debug, Serialize)]
+pub struct StateSummary {
+    /// Number of [`MIGRATIONS`] applied, of the ones known
+    version: u64,
+    migrations: usize,
+    running: bool,
+    suspended: bool,
+    trackers: usize,
+    external: usize,
+    /// Trackers migrated without issue id, see [`AppData::resolve_legacy`]
+    legacy: usize,
+    deleted: usize,
+    archived: usize,
+    submissions: usize,
+    days: usize,
+    sessions: usize,
+    ledger_days: usize,
+    goals: usize,
 }
 
 impl InnerAppData {
+    /// Sections of the state besides the trackers, and the serialized trackers in their order
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn to_parts(
+        &self,
+    ) -> serde_json::Result<(Vec<(String, Value)>, Vec<(String, String)>)> {
+        let trackers = self
+            .trackers
+            .iter()
+            .map(|(key, tracker)| Ok((key.clone(), serde_json::to_string(tracker)?)))
+            .collect::<serde_json::Result<_>>()?;
+        let mut sections = match serde_json::to_value(self)? {
+            Value::Object(sections) => sections,
+            _ => unreachable!("state is serialized as an object"),
+        };
+        sections.remove("trackers");
+        Ok((sections.into_iter().collect(), trackers))
+    }
+
+    /// Inverse of [`InnerAppData::to_parts`]
+    pub(crate) fn from_parts(
+        mut sections: Map<String, Value>,
+        trackers: Vec<(String, String)>,
+    ) -> serde_json::Result<Self> {
+        sections.insert("trackers".to_string(), Value::Object(Map::new()));
+        let mut data: Self = serde_json::from_value(Value::Object(sections))?;
+        for (key, tracker) in trackers {
+            data.trackers.insert(key, serde_json::from_str(&tracker)?);
+        }
+        Ok(data)
+    }
+
+    /// Time of each tracker as stored, without the running time, e.g. to verify copies
+    pub(crate) fn durations(&self) -> Vec<(String, Duration)> {
+        self.trackers
+            .iter()
+            .map(|(key, tracker)| (key.clone(), Self::elapsed_of(tracker, None)))
+            .collect()
+    }
+
     fn new() -> Self {
         Self {
+            version: MIGRATIONS.len() as u64,
             running: None,
+            suspended: None,
+            account_id: None,
             trackers: IndexMap::new(),
+            deleted: IndexMap::new(),
+            submissions: Vec::new(),
+            archive: Vec::new(),
+            days: Vec::new(),
+            sessions: Vec::new(),
+            ledger: BTreeMap::new(),
+            goals: Vec::new(),
         }
     }
 
-    fn elapsed(&self, key: &str) -> Option<Duration> {
-        self.trackers.get(key).map(|tracker| {
-            let running_duration = self
-                .running
-                .as_ref()
-                .filter(|r| r.key == key)
-                .map_or(Duration::ZERO, |r| {
-                    r.start_time.elapsed().unwrap_or_default()
-                });
-            let positive_adjustments_sum: Duration = tracker.positive_adjustments.iter().sum();
-            let negative_adjustments_sum: Duration = tracker.negative_adjustments.iter().sum();
-            let positive_duration_sum =
-                tracker.duration + running_duration + positive_adjustments_sum;
-            positive_duration_sum.saturating_sub(negative_adjustments_sum)
-        })
+    /// Elapsed duration of `tracker`, including `running` time if it is the running tracker
+    fn elapsed_of(tracker: &PausedTracker, running: Option<&RunningTracker>) -> Duration {
+        let running_duration = running.map_or(Duration::ZERO, |r| clock::elapsed(r.start_time));
+        let positive_adjustments_sum: Duration = tracker.positive_adjustments.iter().sum();
+        let negative_adjustments_sum: Duration = tracker.negative_adjustments.iter().sum();
+        let positive_duration_sum = tracker.tracked() + running_duration + positive_adjustments_sum;
+        positive_duration_sum.saturating_sub(negative_adjustments_sum)
+    }
+
+    fn running_if(&self, key: &str) -> Option<&RunningTracker> {
+        self.running.as_ref().filter(|r| r.key == key)
     }
 
-    fn elapsed_seconds(&self, key: &str) -> Option<Duration> {
-        self.elapsed(key)
-            .map(|elapsed| Duration::from_secs(elapsed.as_secs()))
+    fn elapsed(&self, key: &str) -> Option<Duration> {
+        self.trackers
+            .get(key)
+            .map(|tracker| Self::elapsed_of(tracker, self.running_if(key)))
     }
 
     /// It is assumed that a tracker with the key exists
     fn get_information(&self, key: &str) -> TrackerInformation {
+        self.get_information_with(key, Detail::Summary)
+    }
+
+    /// It is assumed that a tracker with the key exists
+    fn get_information_with(&self, key: &str, detail: Detail) -> TrackerInformation {
         let tracker = self.trackers.get(key).unwrap();
+        Self::information(key, tracker, self.running_if(key), detail)
+    }
+
+    fn information(
+        key: &str,
+        tracker: &PausedTracker,
+        running: Option<&RunningTracker>,
+        detail: Detail,
+    ) -> TrackerInformation {
+        let elapsed = Self::elapsed_of(tracker, running);
         TrackerInformation {
             key: key.to_owned(),
             id: tracker.id.clone(),
             description: tracker.description.clone(),
-            duration: self.elapsed_seconds(key).unwrap(),
-            running: self
-                .running
-                .as_ref()
-                .filter(|running| running.key == key)
-                .is_some(),
+            duration: rounding::seconds(elapsed),
+            running: running.is_some(),
             start_time: tracker.start_time,
+            external: tracker.external,
+            paired_with: tracker.paired_with.clone(),
+            details: match detail {
+                Detail::Summary => None,
+                Detail::Full => tracker.details.clone(),
+            },
+            fields: match detail {
+                Detail::Summary => BTreeMap::new(),
+                Detail::Full => tracker.fields.clone(),
+            },
         }
     }
 
@@ -145,17 +988,142 @@ impl InnerAppData {
             .ok_or(TrackerError::NotFoundError)
     }
 
-    fn get_tracker(&self, key: &str) -> Result<TrackerInformation, TrackerError> {
+    fn get_tracker(&self, key: &str, detail: Detail) -> Result<TrackerInformation, TrackerError> {
         self.trackers
             .get(key)
-            .map(|_| self.get_information(key))
+            .map(|_| self.get_information_with(key, detail))
             .ok_or(TrackerError::NotFoundError)
     }
 
-    fn list_trackers(&self) -> Vec<TrackerInformation> {
+    fn list_trackers(&self, detail: Detail) -> Vec<TrackerInformation> {
+        let running = self.running.as_ref();
         self.trackers
-            .keys()
-            .map(|key| self.get_information(key))
+            .iter()
+            .map(|(key, tracker)| {
+                let running = running.filter(|r| &r.key == key);
+                Self::information(key, tracker, running, detail)
+            })
+            .collect()
+    }
+
+    /// Trackers as worklogs to submit, split per [`SubmissionGrouping`]. Their durations are
+    /// precise, so that they are rounded only once, when submitted.
+    fn worklogs(&self, grouping: SubmissionGrouping) -> Vec<TrackerInformation> {
+        self.list_trackers(Detail::Full)
+            .into_iter()
+            .map(|tracker| TrackerInformation {
+                duration: self.elapsed(&tracker.key).unwrap_or(tracker.duration),
+                ..tracker
+            })
+            .flat_map(|tracker| match grouping {
+                SubmissionGrouping::Tracker => vec![self.started_worklog(tracker)],
+                grouping => self.split_worklog(tracker, grouping),
+            })
+            .collect()
+    }
+
+    /// The whole tracker as one worklog, started when it first ran
+    fn started_worklog(&self, tracker: TrackerInformation) -> TrackerInformation {
+        let started = self.sessions_of(&tracker.key).first().map(|s| s.start);
+        TrackerInformation {
+            start_time: started.unwrap_or(tracker.start_time),
+            ..tracker
+        }
+    }
+
+    /// Sessions of the tracker, the running one up to now
+    fn sessions_of(&self, key: &str) -> Vec<Interval> {
+        let running = self.running_if(key).map(|running| Interval {
+            start: running.start_time.into(),
+            end: clock::local_now(),
+        });
+        self.trackers
+            .get(key)
+            .map(|tracker| tracker.sessions.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .chain(running)
+            .collect()
+    }
+
+    /// Splits the time of the tracker along its sessions, merged per day with
+    /// [`SubmissionGrouping::Day`]. Time not covered by sessions, e.g. adjustments, goes to the
+    /// first part, parts shorter than [`MINIMUM_DURATION`] go to the longest one.
+    fn split_worklog(
+        &self,
+        tracker: TrackerInformation,
+        grouping: SubmissionGrouping,
+    ) -> Vec<TrackerInformation> {
+        let created = tracker.start_time;
+        let mut parts: Vec<(DateTime<Local>, Duration)> = Vec::new();
+        for session in self.sessions_of(&tracker.key) {
+            let duration = session.duration();
+            match parts.last_mut() {
+                Some((start, sum))
+                    if matches!(grouping, SubmissionGrouping::Day)
+                        && start.date_naive() == session.start.date_naive() =>
+                {
+                    *sum += duration
+                }
+                _ => parts.push((session.start, duration)),
+            }
+        }
+
+        let mut excess = parts
+            .iter()
+            .map(|(_, duration)| *duration)
+            .sum::<Duration>()
+            .saturating_sub(tracker.duration);
+        for (_, duration) in parts.iter_mut().rev() {
+            let cut = excess.min(*duration);
+            *duration -= cut;
+            excess -= cut;
+        }
+        parts.retain(|(_, duration)| !duration.is_zero());
+        let covered: Duration = parts.iter().map(|(_, duration)| *duration).sum();
+        match parts.first_mut() {
+            Some((_, duration)) => *duration += tracker.duration - covered,
+            None => parts.push((created, tracker.duration)),
+        }
+        while parts.len() > 1 {
+            let Some(short) = parts.iter().position(|(_, d)| *d < MINIMUM_DURATION) else {
+                break;
+            };
+            let (_, duration) = parts.remove(short);
+            parts.iter_mut().max_by_key(|(_, d)| *d).unwrap().1 += duration;
+        }
+
+        parts
+            .into_iter()
+            .map(|(start, duration)| TrackerInformation {
+                start_time: start,
+                duration,
+                ..tracker.clone()
+            })
+            .collect()
+    }
+
+    /// Position and up-to-date information of the running tracker
+    fn running_information(&self, detail: Detail) -> Option<(usize, TrackerInformation)> {
+        self.running.as_ref().map(|running| {
+            let index = self.trackers.get_index_of(&running.key).unwrap();
+            (index, self.get_information_with(&running.key, detail))
+        })
+    }
+
+    /// Oldest paused trackers that exceed the `limit`, suggested for archiving
+    fn archive_candidates(&self, limit: usize) -> Vec<String> {
+        let excess = self.trackers.len().saturating_sub(limit);
+        let mut paused: Vec<(&String, &PausedTracker)> = self
+            .trackers
+            .iter()
+            .filter(|(key, _)| self.running_if(key).is_none())
+            .collect();
+        paused.sort_by_key(|(_, tracker)| tracker.start_time);
+        paused
+            .into_iter()
+            .take(excess)
+            .map(|(key, _)| key.clone())
             .collect()
     }
 
@@ -172,21 +1140,110 @@ impl InnerAppData {
         Ok(self.get_information(key))
     }
 
-    fn adjust_positive_duration(
+    /// Adds sessions tracked elsewhere, e.g. in Toggl, skipping those overlapping time already
+    /// tracked by any tracker or archived for the key. Sets the description if the tracker has none. The
+    /// sessions appear in the history of sessions too. Returns the added time.
+    fn import_sessions(
         &mut self,
         key: &str,
-        duration: Duration,
-    ) -> Result<TrackerInformation, TrackerError> {
-        self.trackers
+        sessions: &[(DateTime<Local>, DateTime<Local>)],
+        description: Option<String>,
+    ) -> Result<Duration, TrackerError> {
+        let archived: Vec<Interval> = self
+            .archive
+            .iter()
+            .filter(|archived| archived.key == key)
+            .flat_map(|archived| archived.tracker.sessions.iter().copied())
+            .collect();
+        let mut known: Vec<Interval> = self
+            .trackers
+            .keys()
+            .flat_map(|key| self.sessions_of(key))
+            .collect();
+        known.extend(archived);
+        let tracker = self
+            .trackers
             .get_mut(key)
-            .map(|tracker| tracker.positive_adjustments.push(duration))
             .ok_or(TrackerError::NotFoundError)?;
-        Ok(self.get_information(key))
-    }
-
-    fn adjust_negative_duration(
-        &mut self,
-        key: &str,
+        if tracker.description.is_none() {
+            tracker.description = description.filter(|d| !d.is_empty());
+        }
+        let mut added = Vec::new();
+        for &(start, end) in sessions {
+            let session = Interval { start, end };
+            let overlaps =
+                |other: &Interval| other.start < session.end && session.start < other.end;
+            if start < end && !known.iter().any(overlaps) {
+                known.push(session);
+                added.push(session);
+            }
+        }
+        tracker.sessions.extend_from_slice(&added);
+        tracker.sessions.sort_by_key(|session| session.start);
+        for session in &added {
+            let session = Session {
+                key: key.to_string(),
+                start: session.start.into(),
+                end: session.end.into(),
+            };
+            for (date, duration) in session.per_day() {
+                self.add_to_ledger(date, key, duration);
+            }
+            if clock::elapsed(session.end) < SESSION_RETENTION {
+                self.sessions.push(session);
+            }
+        }
+        self.sessions.sort_by_key(|session| session.start);
+        Ok(added.iter().map(Interval::duration).sum())
+    }
+
+    /// Sets the custom fields given with a value and removes those given without
+    fn patch_fields(
+        &mut self,
+        key: &str,
+        fields: BTreeMap<String, Option<String>>,
+    ) -> Result<TrackerInformation, TrackerError> {
+        let tracker = self
+            .trackers
+            .get_mut(key)
+            .ok_or(TrackerError::NotFoundError)?;
+        for (name, value) in fields {
+            match value {
+                Some(value) => tracker.fields.insert(name, value),
+                None => tracker.fields.remove(&name),
+            };
+        }
+        Ok(self.get_information_with(key, Detail::Full))
+    }
+
+    fn set_paired_with(
+        &mut self,
+        key: &str,
+        account_id: Option<String>,
+    ) -> Result<TrackerInformation, TrackerError> {
+        self.trackers
+            .get_mut(key)
+            .map(|tracker| tracker.paired_with = account_id.filter(|a| !a.is_empty()))
+            .ok_or(TrackerError::NotFoundError)?;
+        Ok(self.get_information(key))
+    }
+
+    fn adjust_positive_duration(
+        &mut self,
+        key: &str,
+        duration: Duration,
+    ) -> Result<TrackerInformation, TrackerError> {
+        self.trackers
+            .get_mut(key)
+            .map(|tracker| tracker.positive_adjustments.push(duration))
+            .ok_or(TrackerError::NotFoundError)?;
+        self.add_to_ledger(clock::local_now().date_naive(), key, duration);
+        Ok(self.get_information(key))
+    }
+
+    fn adjust_negative_duration(
+        &mut self,
+        key: &str,
         duration: Duration,
     ) -> Result<TrackerInformation, TrackerError> {
         let elapsed = self.elapsed(key).ok_or(TrackerError::NotFoundError)?;
@@ -199,169 +1256,2483 @@ impl InnerAppData {
             .unwrap()
             .negative_adjustments
             .push(duration);
+        self.subtract_from_ledger(clock::local_now().date_naive(), key, duration);
         Ok(self.get_information(key))
     }
 
+    /// Applies the adjustment of `PUT /trackers/:key`, moving the time from or to `using`
+    fn adjust(
+        &mut self,
+        key: &str,
+        adjustment: AdjustTracker,
+    ) -> Result<TrackerInformation, TrackerError> {
+        match adjustment {
+            AdjustTracker::SetDescription { description } => self.set_description(key, description),
+            AdjustTracker::PositiveDuration { duration, using } => {
+                if let Some(other_key) = using {
+                    self.adjust_negative_duration(&other_key, duration)?;
+                }
+                self.adjust_positive_duration(key, duration)
+            }
+            AdjustTracker::NegativeDuration { duration, using } => {
+                let tracker = self.adjust_negative_duration(key, duration)?;
+                if let Some(other_key) = using {
+                    self.adjust_positive_duration(&other_key, duration)?;
+                }
+                Ok(tracker)
+            }
+        }
+    }
+
+    /// Applies all adjustments or, if one fails, none of them
+    fn adjust_all(
+        &mut self,
+        adjustments: IndexMap<String, AdjustTracker>,
+    ) -> Result<Vec<TrackerInformation>, TrackerError> {
+        let before = self.snapshot();
+        let keys: Vec<_> = adjustments.keys().cloned().collect();
+        for (key, adjustment) in adjustments {
+            if let Err(e) = self.adjust(&key, adjustment) {
+                self.restore(before);
+                return Err(e);
+            }
+        }
+        Ok(keys.iter().map(|key| self.get_information(key)).collect())
+    }
+
+    /// Sets the descriptions and durations of trackers or, if one fails, none of them. Returns the
+    /// keys of the changed trackers, or the key that failed.
+    fn correct_all(
+        &mut self,
+        corrections: Vec<(String, Option<String>, Duration)>,
+    ) -> Result<Vec<String>, (String, TrackerError)> {
+        let before = self.snapshot();
+        let mut corrected = Vec::new();
+        for (key, description, duration) in corrections {
+            match self.correct(&key, description, duration) {
+                Ok(true) => corrected.push(key),
+                Ok(false) => {}
+                Err(e) => {
+                    self.restore(before);
+                    return Err((key, e));
+                }
+            }
+        }
+        Ok(corrected)
+    }
+
+    fn correct(
+        &mut self,
+        key: &str,
+        description: Option<String>,
+        duration: Duration,
+    ) -> Result<bool, TrackerError> {
+        let tracker = self.trackers.get(key).ok_or(TrackerError::NotFoundError)?;
+        let mut changed = false;
+        if tracker.description != description {
+            self.set_description(key, description)?;
+            changed = true;
+        }
+        let elapsed = rounding::seconds(self.elapsed(key).unwrap());
+        if duration > elapsed {
+            self.adjust_positive_duration(key, duration - elapsed)?;
+            changed = true;
+        } else if duration < elapsed {
+            self.adjust_negative_duration(key, elapsed - duration)?;
+            changed = true;
+        }
+        Ok(changed)
+    }
+
+    /// Rounds the durations of all trackers with time, returning the keys of the changed ones
+    fn round_durations(&mut self, step: Duration, policy: RoundingPolicy) -> Vec<String> {
+        let mut rounded = Vec::new();
+        for key in self.trackers.keys().cloned().collect::<Vec<_>>() {
+            let elapsed = self.elapsed(&key).unwrap();
+            let target = policy.round(elapsed, step);
+            if elapsed.is_zero() || target == elapsed {
+                continue;
+            } else if target > elapsed {
+                self.adjust_positive_duration(&key, target - elapsed)
+                    .unwrap();
+            } else {
+                self.adjust_negative_duration(&key, elapsed - target)
+                    .unwrap();
+            }
+            rounded.push(key);
+        }
+        rounded
+    }
+
+    /// Removes paused trackers started before `date` without any time, returning their keys
+    fn prune_empty(&mut self, date: NaiveDate, purge_delay: Option<Duration>) -> Vec<String> {
+        let keys: Vec<String> = self
+            .trackers
+            .iter()
+            .filter(|(key, tracker)| {
+                tracker.start_time.date_naive() < date
+                    && self.running_if(key).is_none()
+                    && Self::elapsed_of(tracker, None).is_zero()
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &keys {
+            self.remove(key, purge_delay).unwrap();
+        }
+        keys
+    }
+
+    /// Moves paused external trackers started before `date` to the archive, without worklogs as
+    /// they are never submitted. Returns their keys.
+    fn archive_external(&mut self, date: NaiveDate) -> Vec<String> {
+        let keys: Vec<String> = self
+            .trackers
+            .iter()
+            .filter(|(key, tracker)| {
+                tracker.external
+                    && tracker.start_time.date_naive() < date
+                    && self.running_if(key).is_none()
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        let archived_at = clock::local_now();
+        for key in &keys {
+            let tracker = self.take_tracker(key).unwrap();
+            self.archive.push(ArchivedTracker {
+                key: key.clone(),
+                tracker,
+                submitted_at: archived_at,
+                tempo_worklog_ids: Vec::new(),
+            });
+        }
+        keys
+    }
+
+    /// Keeps the summary, replacing an earlier one of the same day
+    fn record_day(&mut self, summary: DaySummary) {
+        self.days.retain(|day| day.date() != summary.date());
+        self.days.push(summary);
+    }
+
     fn start(&mut self, key: &str) -> Result<TrackerInformation, TrackerError> {
         if !self.trackers.contains_key(key) {
             return Err(TrackerError::NotFoundError);
         }
         self.pause();
+        // started by hand instead of resuming after a suspension
+        self.suspended = None;
         self.running = Some(RunningTracker::new(key));
         Ok(self.get_information(key))
     }
 
     fn pause(&mut self) {
-        if let Some(running) = &self.running {
-            *self.trackers.get_mut(&running.key).unwrap() += running;
-        }
-        self.running = None;
+        self.pause_at(clock::now());
     }
 
-    fn create_tracker(&mut self, key: &str, id: &str) -> Result<TrackerInformation, TrackerError> {
-        if !Regex::new(r"\w+-\d+").unwrap().is_match(key) {
-            return Err(TrackerError::KeyFormatError);
-        }
-        if self.trackers.contains_key(key) {
-            return Err(TrackerError::OccupiedError);
-        }
-        self.trackers
-            .insert(key.to_string(), PausedTracker::new(id));
-        Ok(self.get_information(key))
+    /// Starts the tracker that ran last before the running one, or before pausing if none runs
+    fn resume_last(&mut self) -> Result<TrackerInformation, TrackerError> {
+        let running = self.running.as_ref().map(|running| &running.key);
+        let key = self
+            .sessions
+            .iter()
+            .rev()
+            .map(|session| &session.key)
+            .find(|key| Some(*key) != running && self.trackers.contains_key(*key))
+            .cloned()
+            .ok_or(TrackerError::NotFoundError)?;
+        self.start(&key)
     }
 
-    fn remove(&mut self, key: &str) -> Result<PausedTracker, TrackerError> {
-        if self.running.as_ref().filter(|t| t.key == key).is_some() {
+    /// Pauses the tracker if it is the running one, starts it otherwise
+    fn toggle(&mut self, key: &str) -> Result<TrackerInformation, TrackerError> {
+        if self
+            .running
+            .as_ref()
+            .is_some_and(|running| running.key == key)
+        {
             self.pause();
+            return Ok(self.get_information(key));
         }
+        self.start(key)
+    }
+
+    fn pause_at(&mut self, end: SystemTime) {
+        if let Some(running) = self.running.take() {
+            self.book_session(Session {
+                key: running.key,
+                start: running.start_time,
+                end,
+            });
+        }
+    }
+
+    /// Adds the session to its tracker, the ledger and the history of sessions
+    fn book_session(&mut self, session: Session) {
         self.trackers
-            .shift_remove(key)
-            .ok_or(TrackerError::NotFoundError)
+            .get_mut(&session.key)
+            .unwrap()
+            .add_session(Interval {
+                start: session.start.into(),
+                end: session.end.into(),
+            });
+        for (date, duration) in session.per_day() {
+            self.add_to_ledger(date, &session.key, duration);
+        }
+        self.sessions
+            .retain(|session| clock::elapsed(session.end) < SESSION_RETENTION);
+        let last = self
+            .sessions
+            .iter_mut()
+            .rev()
+            .find(|s| s.key == session.key);
+        match last {
+            Some(last) if last.end == session.start => last.end = session.end,
+            _ => self.sessions.push(session),
+        }
     }
 
-    fn remove_all(&mut self) -> Vec<PausedTracker> {
-        self.pause();
-        let map: Vec<String> = self.trackers.keys().map(|k| k.to_string()).collect();
-        map.iter()
-            .map(|key| self.trackers.remove(key).unwrap())
-            .collect()
+    /// Records the time of the running tracker so far, its session continues without a gap.
+    /// Returns the key of the running tracker.
+    fn checkpoint(&mut self) -> Option<String> {
+        let key = self.running.as_ref()?.key.clone();
+        let now = clock::now();
+        self.pause_at(now);
+        self.running = Some(RunningTracker {
+            key: key.clone(),
+            start_time: now,
+        });
+        Some(key)
     }
 
-    fn sum(&self) -> Duration {
-        self.list_trackers().into_iter().map(|t| t.duration).sum()
+    fn add_to_ledger(&mut self, date: NaiveDate, key: &str, duration: Duration) {
+        *self
+            .ledger
+            .entry(date)
+            .or_default()
+            .entry(key.to_string())
+            .or_default() += duration;
     }
-}
 
-#[derive(Debug)]
-pub struct AppData {
-    inner: RwLock<InnerAppData>,
-    path: PathBuf,
-}
+    /// Removes time of the day, as far as it was tracked on that day
+    fn subtract_from_ledger(&mut self, date: NaiveDate, key: &str, duration: Duration) {
+        if let Some(tracked) = self.ledger.get_mut(&date).and_then(|day| day.get_mut(key)) {
+            *tracked = tracked.saturating_sub(duration);
+        }
+    }
 
-impl AppData {
-    fn reading<F, T>(&self, f: F) -> T
-    where
-        F: FnOnce(&InnerAppData) -> T,
-    {
-        let AppData { inner, .. } = self;
-        f(inner.read().unwrap().deref())
+    /// Time tracked on `date` per key, including the running tracker
+    fn day_totals(&self, date: NaiveDate) -> Vec<(String, Duration)> {
+        let mut totals = self.ledger.get(&date).cloned().unwrap_or_default();
+        if let Some(running) = &self.running {
+            let session = Session {
+                key: running.key.clone(),
+                start: running.start_time,
+                end: clock::now(),
+            };
+            for (_, duration) in session.per_day().into_iter().filter(|(d, _)| *d == date) {
+                *totals.entry(session.key.clone()).or_default() += duration;
+            }
+        }
+        totals.into_iter().collect()
     }
 
-    fn writing<F, T>(&self, f: F) -> T
-    where
-        F: FnOnce(&mut InnerAppData) -> T,
-    {
-        let result = self.writing_without_flush(f);
-        self.reading(|a| files::write_file(&self.path, a).unwrap());
-        result
+    /// Trackers with time in the `days` days from `first` on, each with its time of these days
+    fn tracked_trackers(&self, first: NaiveDate, days: usize) -> Vec<TrackerInformation> {
+        let mut totals: IndexMap<String, Duration> = IndexMap::new();
+        for date in first.iter_days().take(days) {
+            for (key, duration) in self.day_totals(date) {
+                *totals.entry(key).or_default() += duration;
+            }
+        }
+        totals
+            .into_iter()
+            .filter(|(_, duration)| !duration.is_zero())
+            .map(|(key, duration)| {
+                let known = self.trackers.get(&key).or_else(|| {
+                    let archived = self.archive.iter().rev().find(|a| a.key == key);
+                    archived.map(|archived| &archived.tracker)
+                });
+                let information = match known {
+                    Some(tracker) => Self::information(&key, tracker, None, Detail::Full),
+                    None => TrackerInformation {
+                        key: key.clone(),
+                        id: String::new(),
+                        description: None,
+                        duration,
+                        running: false,
+                        start_time: clock::local_now(),
+                        external: false,
+                        paired_with: None,
+                        details: None,
+                        fields: BTreeMap::new(),
+                    },
+                };
+                TrackerInformation {
+                    duration: rounding::seconds(duration),
+                    ..information
+                }
+            })
+            .collect()
     }
 
-    fn writing_without_flush<F, T>(&self, f: F) -> T
-    where
-        F: FnOnce(&mut InnerAppData) -> T,
-    {
-        let AppData { inner, .. } = self;
-        f(inner.write().unwrap().deref_mut())
+    fn set_goal(&mut self, goal: Goal) {
+        match self.goals.iter_mut().find(|g| g.name == goal.name) {
+            Some(existing) => *existing = goal,
+            None => self.goals.push(goal),
+        }
     }
 
-    pub fn current(&self) -> Result<TrackerInformation, TrackerError> {
-        self.reading(|a| a.current())
+    fn remove_goal(&mut self, name: &str) -> Option<Goal> {
+        let index = self.goals.iter().position(|goal| goal.name == name)?;
+        Some(self.goals.remove(index))
+    }
+
+    /// Time tracked since `since` by sessions, adjustments not included
+    fn sum_since(&self, since: SystemTime) -> Sum {
+        let running = self.running.as_ref().map(|running| Session {
+            key: running.key.clone(),
+            start: running.start_time,
+            end: clock::now(),
+        });
+        let sum: Duration = self
+            .sessions
+            .iter()
+            .chain(running.as_ref())
+            .map(|session| session.duration_since(since))
+            .sum();
+        Sum {
+            duration: rounding::seconds(sum),
+            exact: sum,
+        }
     }
 
-    pub fn get_tracker(&self, key: &str) -> Result<TrackerInformation, TrackerError> {
-        self.reading(|a| a.get_tracker(key))
+    fn suspend(&mut self) {
+        if let Some(running) = &self.running {
+            self.suspended = Some(RunningTracker::new(&running.key));
+        }
+        self.pause();
     }
 
-    pub fn list_trackers(&self) -> Vec<TrackerInformation> {
-        self.reading(|a| a.list_trackers())
+    /// Restarts the tracker that was running on suspension, if the policy allows it
+    fn resume(&mut self, policy: ResumePolicy, within: Duration) -> Option<TrackerInformation> {
+        let suspended = self.suspended.take()?;
+        let suspension = clock::elapsed(suspended.start_time);
+        let resume = match policy {
+            ResumePolicy::Never => false,
+            ResumePolicy::Always => true,
+            ResumePolicy::Recent => suspension <= within,
+        };
+        if resume && self.running.is_none() {
+            self.start(&suspended.key).ok()
+        } else {
+            None
+        }
     }
 
-    pub fn set_description(
-        &self,
+    fn create_tracker(
+        &mut self,
         key: &str,
-        description: Option<String>,
+        id: &str,
+        details: Option<IssueDetails>,
     ) -> Result<TrackerInformation, TrackerError> {
-        self.writing(|a| a.set_description(key, description))
+        if !is_jira_key(key) {
+            return Err(TrackerError::KeyFormatError);
+        }
+        if self.trackers.contains_key(key) {
+            return Err(TrackerError::OccupiedError);
+        }
+        self.trackers
+            .insert(key.to_string(), PausedTracker::new(id, details));
+        Ok(self.get_information(key))
     }
 
-    pub fn adjust_positive_duration(
-        &self,
+    fn create_external_tracker(&mut self, key: &str) -> Result<TrackerInformation, TrackerError> {
+        if key.trim().is_empty() {
+            return Err(TrackerError::KeyFormatError);
+        }
+        if self.trackers.contains_key(key) {
+            return Err(TrackerError::OccupiedError);
+        }
+        self.trackers
+            .insert(key.to_string(), PausedTracker::external());
+        Ok(self.get_information(key))
+    }
+
+    /// Starts the tracker for unassigned time, creating it if necessary
+    fn start_unknown(&mut self) -> Result<TrackerInformation, TrackerError> {
+        if !self.trackers.contains_key(UNKNOWN_KEY) {
+            self.create_external_tracker(UNKNOWN_KEY)?;
+        }
+        self.start(UNKNOWN_KEY)
+    }
+
+    /// Moves the given durations from the unknown tracker onto existing trackers, all or nothing.
+    /// The unknown tracker is removed once it is paused and has no time left.
+    fn triage(
+        &mut self,
+        assignments: &[(String, Duration)],
+    ) -> Result<Vec<TrackerInformation>, TrackerError> {
+        let elapsed = self
+            .elapsed(UNKNOWN_KEY)
+            .ok_or(TrackerError::NotFoundError)?;
+        if assignments
+            .iter()
+            .any(|(key, _)| key == UNKNOWN_KEY || !self.trackers.contains_key(key))
+        {
+            return Err(TrackerError::NotFoundError);
+        }
+        let total: Duration = assignments.iter().map(|(_, duration)| *duration).sum();
+        if total > elapsed {
+            return Err(TrackerError::DurationAdjustmentError);
+        }
+
+        for (key, duration) in assignments {
+            self.adjust_negative_duration(UNKNOWN_KEY, *duration)?;
+            self.adjust_positive_duration(key, *duration)?;
+        }
+        let unknown_running = self.running.as_ref().is_some_and(|r| r.key == UNKNOWN_KEY);
+        if !unknown_running
+            && self.elapsed(UNKNOWN_KEY).map(rounding::seconds) == Some(Duration::ZERO)
+        {
+            self.take_tracker(UNKNOWN_KEY);
+        }
+        Ok(assignments
+            .iter()
+            .map(|(key, _)| self.get_information(key))
+            .collect())
+    }
+
+    /// Turns an external tracker into one backed by the Jira issue `new_key`, keeping its time
+    fn link(
+        &mut self,
         key: &str,
-        duration: Duration,
+        new_key: &str,
+        id: &str,
+        details: Option<IssueDetails>,
     ) -> Result<TrackerInformation, TrackerError> {
-        self.writing(|a| a.adjust_positive_duration(key, duration))
+        let tracker = self.trackers.get(key).ok_or(TrackerError::NotFoundError)?;
+        if !tracker.external {
+            return Err(TrackerError::NotExternalError);
+        }
+        if !is_jira_key(new_key) {
+            return Err(TrackerError::KeyFormatError);
+        }
+        if self.trackers.contains_key(new_key) {
+            return Err(TrackerError::OccupiedError);
+        }
+        self.trackers = self
+            .trackers
+            .drain(..)
+            .map(|(k, mut tracker)| {
+                if k == key {
+                    tracker.id = id.to_string();
+                    tracker.details = details.clone();
+                    tracker.external = false;
+                    tracker.legacy = false;
+                    (new_key.to_string(), tracker)
+                } else {
+                    (k, tracker)
+                }
+            })
+            .collect();
+        if let Some(running) = self.running.as_mut().filter(|r| r.key == key) {
+            running.key = new_key.to_string();
+        }
+        if let Some(suspended) = self.suspended.as_mut().filter(|s| s.key == key) {
+            suspended.key = new_key.to_string();
+        }
+        Ok(self.get_information(new_key))
     }
 
-    pub fn adjust_negative_duration(
-        &self,
+    fn summary(&self) -> StateSummary {
+        let count = |f: fn(&PausedTracker) -> bool| self.trackers.values().filter(|t| f(t)).count();
+        StateSummary {
+            version: self.version,
+            migrations: MIGRATIONS.len(),
+            running: self.running.is_some(),
+            suspended: self.suspended.is_some(),
+            trackers: self.trackers.len(),
+            external: count(|tracker| tracker.external),
+            legacy: count(|tracker| tracker.legacy),
+            deleted: self.deleted.len(),
+            archived: self.archive.len(),
+            submissions: self.submissions.len(),
+            days: self.days.len(),
+            sessions: self.sessions.len(),
+            ledger_days: self.ledger.len(),
+            goals: self.goals.len(),
+        }
+    }
+
+    fn legacy_keys(&self) -> Vec<String> {
+        self.trackers
+            .iter()
+            .filter(|(_, tracker)| tracker.legacy)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Turns the tracker migrated without issue id back into a tracker of its issue
+    fn resolve_legacy(
+        &mut self,
         key: &str,
-        duration: Duration,
+        id: &str,
+        details: Option<IssueDetails>,
     ) -> Result<TrackerInformation, TrackerError> {
-        self.writing(|a| a.adjust_negative_duration(key, duration))
+        let tracker = self
+            .trackers
+            .get_mut(key)
+            .ok_or(TrackerError::NotFoundError)?;
+        if !tracker.legacy {
+            return Err(TrackerError::NotExternalError);
+        }
+        tracker.id = id.to_string();
+        tracker.details = details;
+        tracker.external = false;
+        tracker.legacy = false;
+        Ok(self.get_information(key))
     }
 
-    pub fn start(&self, key: &str) -> Result<TrackerInformation, TrackerError> {
-        self.writing(|a| a.start(key))
+    /// Removes the tracker, which is not resumed after a suspension then
+    fn take_tracker(&mut self, key: &str) -> Option<PausedTracker> {
+        if self.suspended.as_ref().is_some_and(|s| s.key == key) {
+            self.suspended = None;
+        }
+        self.trackers.shift_remove(key)
     }
 
-    pub fn pause(&self) {
-        self.writing(|a| a.pause())
+    /// Keeps the tracker for `purge_delay` if given, see [`InnerAppData::undelete`]
+    fn soft_delete(&mut self, key: &str, tracker: &PausedTracker, purge_delay: Option<Duration>) {
+        let Some(delay) = purge_delay else {
+            return;
+        };
+        let purge_at = (clock::now() + delay).into();
+        let tracker = tracker.clone();
+        self.deleted
+            .insert(key.to_string(), DeletedTracker { tracker, purge_at });
     }
 
-    pub fn create_tracker(&self, key: &str, id: &str) -> Result<TrackerInformation, TrackerError> {
-        self.writing(|a| a.create_tracker(key, id))
+    fn remove(
+        &mut self,
+        key: &str,
+        purge_delay: Option<Duration>,
+    ) -> Result<PausedTracker, TrackerError> {
+        if self.running.as_ref().filter(|t| t.key == key).is_some() {
+            self.pause();
+        }
+        let tracker = self.take_tracker(key).ok_or(TrackerError::NotFoundError)?;
+        self.soft_delete(key, &tracker, purge_delay);
+        Ok(tracker)
     }
 
-    pub fn remove(&self, key: &str) -> Result<PausedTracker, TrackerError> {
-        self.writing(|a| a.remove(key))
+    fn remove_all(&mut self, purge_delay: Option<Duration>) -> Vec<PausedTracker> {
+        self.pause();
+        let map: Vec<String> = self.trackers.keys().map(|k| k.to_string()).collect();
+        map.iter()
+            .map(|key| {
+                let tracker = self.take_tracker(key).unwrap();
+                self.soft_delete(key, &tracker, purge_delay);
+                tracker
+            })
+            .collect()
     }
 
-    pub fn remove_all(&self) -> Vec<PausedTracker> {
-        self.writing(|a| a.remove_all())
+    /// Restores a deleted tracker that was not purged yet
+    fn undelete(&mut self, key: &str) -> Result<TrackerInformation, TrackerError> {
+        if self.trackers.contains_key(key) {
+            return Err(TrackerError::OccupiedError);
+        }
+        let deleted = self
+            .deleted
+            .shift_remove(key)
+            .ok_or(TrackerError::NotFoundError)?;
+        self.trackers.insert(key.to_string(), deleted.tracker);
+        Ok(self.get_information(key))
     }
 
-    pub fn sum(&self) -> Duration {
-        self.reading(|a| a.sum())
+    fn tracker_sessions(&self, key: &str) -> Result<Vec<TrackerSession>, TrackerError> {
+        if !self.trackers.contains_key(key) {
+            return Err(TrackerError::NotFoundError);
+        }
+        let running = self.running_if(key).is_some();
+        let sessions = self.sessions_of(key);
+        let last = sessions.len().saturating_sub(1);
+        Ok(sessions
+            .into_iter()
+            .enumerate()
+            .map(|(index, session)| TrackerSession {
+                id: index,
+                start: session.start,
+                end: (!running || index < last).then_some(session.end),
+                duration: rounding::seconds(session.duration()),
+            })
+            .collect())
     }
 
-    pub fn reload_state(&self) {
-        self.writing_without_flush(|a| *a = files::read_file(&self.path).unwrap())
+    /// Splits a finished session of the tracker at `at`, moving the second part to the tracker
+    /// `to` if given. Returns the changed trackers.
+    fn split_session(
+        &mut self,
+        key: &str,
+        id: usize,
+        at: DateTime<Local>,
+        to: Option<&str>,
+    ) -> Result<Vec<TrackerInformation>, TrackerError> {
+        let session = self
+            .trackers
+            .get(key)
+            .and_then(|tracker| tracker.sessions.get(id).copied())
+            .ok_or(TrackerError::NotFoundError)?;
+        if at <= session.start || at >= session.end {
+            return Err(TrackerError::DurationAdjustmentError);
+        }
+        let to = to.filter(|to| *to != key);
+        if to.is_some_and(|to| !self.trackers.contains_key(to)) {
+            return Err(TrackerError::NotFoundError);
+        }
+        let second = Interval {
+            start: at,
+            end: session.end,
+        };
+        let tracker = self.trackers.get_mut(key).unwrap();
+        tracker.sessions[id].end = at;
+        let Some(to) = to else {
+            tracker.sessions.insert(id + 1, second);
+            return Ok(vec![self.get_information(key)]);
+        };
+        let other = self.trackers.get_mut(to).unwrap();
+        other.sessions.push(second);
+        other.sessions.sort_by_key(|session| session.start);
+        let moved = Session {
+            key: to.to_string(),
+            start: second.start.into(),
+            end: second.end.into(),
+        };
+        for (date, duration) in moved.per_day() {
+            self.subtract_from_ledger(date, key, duration);
+            self.add_to_ledger(date, to, duration);
+        }
+        self.reassign_history(key, &moved);
+        Ok(vec![self.get_information(key), self.get_information(to)])
     }
-}
 
-impl From<&AppConfig> for AppData {
-    fn from(config: &AppConfig) -> Self {
-        let path = &config.json_file;
-        let inner = files::read_file(path).unwrap_or_else(|e| {
-            if e.is_not_found() {
-                InnerAppData::new()
-            } else {
-                Err(e).unwrap()
-            }
-        });
+    /// Moves the time of `moved` in the session history from `from` to the key of `moved`
+    fn reassign_history(&mut self, from: &str, moved: &Session) {
+        let Some(index) = self
+            .sessions
+            .iter()
+            .position(|s| s.key == from && s.start < moved.end && moved.start < s.end)
+        else {
+            return;
+        };
+        let original = self.sessions.remove(index);
+        let parts = [
+            (from, original.start, moved.start),
+            (
+                moved.key.as_str(),
+                moved.start.max(original.start),
+                moved.end.min(original.end),
+            ),
+            (from, moved.end, original.end),
+        ];
+        let parts: Vec<_> = parts
+            .into_iter()
+            .filter(|(_, start, end)| start < end)
+            .map(|(key, start, end)| Session {
+                key: key.to_string(),
+                start,
+                end,
+            })
+            .collect();
+        self.sessions.splice(index..index, parts);
+    }
+
+    /// Sessions started in `week` of the trackers and the archive, and the worklogs submitted in it
+    fn week_archive(&self, week: IsoWeek) -> (Vec<SessionRow>, Vec<WorklogRow>) {
+        let in_week = |time: DateTime<Local>| time.date_naive().iso_week() == week;
+        let open = self
+            .trackers
+            .iter()
+            .map(|(key, tracker)| (key, tracker, None));
+        let archived = self.archive.iter().map(|archived| {
+            (
+                &archived.key,
+                &archived.tracker,
+                Some(archived.submitted_at),
+            )
+        });
+        let mut sessions: Vec<SessionRow> = open
+            .chain(archived)
+            .flat_map(|(key, tracker, submitted_at)| {
+                tracker
+                    .sessions
+                    .iter()
+                    .filter(|session| in_week(session.start))
+                    .map(move |session| SessionRow {
+                        key: key.clone(),
+                        start: session.start,
+                        end: session.end,
+                        seconds: session.duration().as_secs(),
+                        submitted_at,
+                    })
+            })
+            .collect();
+        sessions.sort_by_key(|session| session.start);
+        let worklogs = self
+            .submissions
+            .iter()
+            .filter(|submission| in_week(submission.submitted_at))
+            .flat_map(|submission| {
+                submission.receipts.iter().map(|receipt| WorklogRow {
+                    submitted_at: submission.submitted_at,
+                    key: receipt.key.clone(),
+                    seconds: receipt.duration.as_secs(),
+                    tempo_worklog_id: receipt.tempo_worklog_id,
+                    account: receipt.account.clone(),
+                })
+            })
+            .collect();
+        (sessions, worklogs)
+    }
+
+    /// Deleted trackers that can still be restored, the next to be purged first
+    fn trash(&self) -> Vec<TrashedTracker> {
+        let mut trash: Vec<TrashedTracker> = self
+            .deleted
+            .iter()
+            .map(|(key, deleted)| TrashedTracker {
+                tracker: Self::information(key, &deleted.tracker, None, Detail::Summary),
+                purge_at: deleted.purge_at,
+            })
+            .collect();
+        trash.sort_by_key(|trashed| trashed.purge_at);
+        trash
+    }
+
+    /// Removes deleted trackers whose delay passed, returning their keys
+    fn purge_deleted(&mut self) -> Vec<String> {
+        let now = clock::local_now();
+        let keys: Vec<String> = self
+            .deleted
+            .iter()
+            .filter(|(_, deleted)| deleted.purge_at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &keys {
+            self.deleted.shift_remove(key);
+        }
+        keys
+    }
+
+    /// Folds the adjustments of all trackers, also of deleted ones, and with `retention` drops
+    /// archived trackers submitted and deleted trackers deleted before it
+    fn compact(
+        &mut self,
+        retention: Option<Duration>,
+        purge_delay: Option<Duration>,
+    ) -> Compaction {
+        let mut compaction = Compaction {
+            purged: self.purge_deleted(),
+            ..Compaction::default()
+        };
+        for (key, tracker) in &mut self.trackers {
+            if tracker.fold_adjustments() {
+                compaction.folded.push(key.clone());
+            }
+        }
+        for (key, deleted) in &mut self.deleted {
+            if deleted.tracker.fold_adjustments() {
+                compaction.folded.push(key.clone());
+            }
+        }
+        self.sessions
+            .retain(|session| clock::elapsed(session.end) < SESSION_RETENTION);
+        let Some(retention) = retention else {
+            return compaction;
+        };
+        let cutoff: DateTime<Local> = (clock::now() - retention).into();
+        let days = self.ledger.len();
+        self.ledger.retain(|date, _| *date >= cutoff.date_naive());
+        compaction.ledger_days = days - self.ledger.len();
+        let before = self.archive.len();
+        self.archive
+            .retain(|archived| archived.submitted_at >= cutoff);
+        compaction.archived = before - self.archive.len();
+        let deleted_before = |deleted: &DeletedTracker| {
+            let deleted_at = deleted.purge_at - purge_delay.unwrap_or_default();
+            deleted_at < cutoff
+        };
+        let expired: Vec<String> = self
+            .deleted
+            .iter()
+            .filter(|(_, deleted)| deleted_before(deleted))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            self.deleted.shift_remove(&key);
+            compaction.purged.push(key);
+        }
+        compaction
+    }
+
+    /// Removes the trackers of `submitted` backed by a Jira issue, keeping external ones
+    fn remove_submitted(
+        &mut self,
+        submitted: &[String],
+        kept: &[&str],
+    ) -> Vec<(String, PausedTracker)> {
+        let removed = |key: &str, tracker: &PausedTracker| {
+            !tracker.external && submitted.iter().any(|k| k == key) && !kept.contains(&key)
+        };
+        if let Some(running) = &self.running {
+            if removed(&running.key, &self.trackers[&running.key]) {
+                self.pause();
+            }
+        }
+        let keys: Vec<String> = self
+            .trackers
+            .iter()
+            .filter(|(key, tracker)| removed(key, tracker))
+            .map(|(key, _)| key.to_string())
+            .collect();
+        keys.into_iter()
+            .map(|key| {
+                let tracker = self.take_tracker(&key).unwrap();
+                (key, tracker)
+            })
+            .collect()
+    }
+
+    /// Moves the trackers of `submitted` whose worklogs did not fail into the archive and records
+    /// their worklogs. Trackers created or failed meanwhile stay.
+    fn record_submission(
+        &mut self,
+        submitted: &[String],
+        receipts: Vec<WorklogReceipt>,
+        failed: Vec<FailedWorklog>,
+        skipped: Vec<String>,
+    ) -> Submission {
+        let mut submission = Submission {
+            submitted_at: clock::local_now(),
+            receipts,
+            skipped,
+            failed,
+            trackers: IndexMap::new(),
+        };
+        let kept: Vec<&str> = submission.failed.iter().map(|f| f.key.as_str()).collect();
+        for key in &kept {
+            // worklogs that were created must not be submitted again with the rest
+            let submitted: Duration = submission
+                .receipts
+                .iter()
+                .filter(|receipt| receipt.key == *key)
+                .map(|receipt| receipt.duration)
+                .sum();
+            let elapsed = self.elapsed(key).unwrap_or_default();
+            if let Some(tracker) = self.trackers.get_mut(*key) {
+                if !submitted.is_zero() {
+                    tracker.negative_adjustments.push(submitted.min(elapsed));
+                }
+            }
+            submission
+                .trackers
+                .insert(key.to_string(), SubmissionStatus::Failed);
+        }
+        for (key, tracker) in self.remove_submitted(submitted, &kept) {
+            let status = if submission.skipped.contains(&key) {
+                SubmissionStatus::Skipped
+            } else {
+                SubmissionStatus::Submitted
+            };
+            submission.trackers.insert(key.clone(), status);
+            let tempo_worklog_ids = submission
+                .receipts
+                .iter()
+                .filter(|receipt| receipt.key == key)
+                .map(|receipt| receipt.tempo_worklog_id)
+                .collect();
+            self.archive.push(ArchivedTracker {
+                key,
+                tracker,
+                submitted_at: submission.submitted_at,
+                tempo_worklog_ids,
+            });
+        }
+        self.submissions.push(submission.clone());
+        submission
+    }
+
+    /// Archived trackers submitted between `from` and `to`, both inclusive
+    fn archived(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Vec<ArchivedTracker> {
+        self.archive
+            .iter()
+            .filter(|archived| {
+                let date = archived.submitted_at.date_naive();
+                from.is_none_or(|from| from <= date) && to.is_none_or(|to| date <= to)
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn sum(&self) -> Sum {
+        let elapsed: Vec<Duration> = self
+            .trackers
+            .iter()
+            .map(|(key, tracker)| Self::elapsed_of(tracker, self.running_if(key)))
+            .collect();
+        Sum {
+            duration: elapsed.iter().copied().map(rounding::seconds).sum(),
+            exact: elapsed.iter().sum(),
+        }
+    }
+
+    /// Applies only the differences between `self` and `other`, leaving unchanged trackers and
+    /// an unchanged running tracker untouched
+    fn merge_from(&mut self, other: InnerAppData) {
+        let InnerAppData {
+            running,
+            suspended,
+            account_id,
+            trackers,
+            deleted,
+            submissions,
+            archive,
+            days,
+            sessions,
+            ledger,
+            goals,
+            ..
+        } = other;
+        self.suspended = suspended;
+        self.deleted = deleted;
+        self.account_id = account_id;
+        self.submissions = submissions;
+        self.archive = archive;
+        self.days = days;
+        self.sessions = sessions;
+        self.ledger = ledger;
+        self.goals = goals;
+
+        self.trackers.retain(|key, _| trackers.contains_key(key));
+        for (key, tracker) in trackers {
+            match self.trackers.get_mut(&key) {
+                Some(existing) if *existing == tracker => {}
+                Some(existing) => *existing = tracker,
+                None => {
+                    self.trackers.insert(key, tracker);
+                }
+            }
+        }
+        self.running = running;
+    }
+
+    /// Merges `theirs`, the state changed outside since `base`, into `self`, which may have changed
+    /// since `base` as well. Trackers of both are kept and a tracker changed on both sides gets the
+    /// fields of `theirs` with the time and adjustments added on both sides. Otherwise the
+    /// in-memory state wins on conflicting changes, keeping the running tracker. Returns the
+    /// trackers and sections changed on both sides.
+    fn merge_three_way(&mut self, base: InnerAppData, theirs: InnerAppData) -> Vec<String> {
+        fn sections(data: &InnerAppData) -> Map<String, Value> {
+            match serde_json::to_value(data).expect("state is serializable") {
+                Value::Object(mut sections) => {
+                    sections.remove("trackers");
+                    sections.remove("running");
+                    sections
+                }
+                _ => unreachable!("state is serialized as an object"),
+            }
+        }
+        fn pick<T: PartialEq + Clone>(
+            base: &T,
+            mine: &T,
+            theirs: &T,
+            conflict: impl FnOnce(),
+        ) -> T {
+            if theirs == base || mine == theirs {
+                mine.clone()
+            } else if mine == base {
+                theirs.clone()
+            } else {
+                conflict();
+                mine.clone()
+            }
+        }
+
+        let mut conflicts = Vec::new();
+        let (base_sections, their_sections) = (sections(&base), sections(&theirs));
+        let mut merged_sections = sections(self);
+        let removed = base_sections
+            .keys()
+            .filter(|name| !their_sections.contains_key(*name));
+        for name in their_sections.keys().chain(removed) {
+            let mine = merged_sections.get(name).cloned();
+            let (base, their) = (base_sections.get(name), their_sections.get(name));
+            let section = pick(&base.cloned(), &mine, &their.cloned(), || {
+                conflicts.push(name.clone())
+            });
+            match section {
+                Some(section) => merged_sections.insert(name.clone(), section),
+                None => merged_sections.remove(name),
+            };
+        }
+
+        let mut trackers = IndexMap::new();
+        let keys: Vec<String> = self
+            .trackers
+            .keys()
+            .chain(theirs.trackers.keys())
+            .chain(base.trackers.keys())
+            .cloned()
+            .collect();
+        for key in keys {
+            if trackers.contains_key(&key) {
+                continue;
+            }
+            let base = base.trackers.get(&key).cloned();
+            let (mine, their) = (self.trackers.get(&key).cloned(), theirs.trackers.get(&key));
+            let merged = match (&base, &mine, their) {
+                (base, Some(mine), Some(their)) if base.as_ref() != Some(mine) => {
+                    if base.as_ref() == Some(their) || mine == their {
+                        Some(mine.clone())
+                    } else {
+                        conflicts.push(key.clone());
+                        Some(PausedTracker::merged(base.as_ref(), mine, their))
+                    }
+                }
+                // deleted on one side and changed on the other
+                (Some(base), None, Some(their)) if base != their => {
+                    conflicts.push(key.clone());
+                    Some(their.clone())
+                }
+                (Some(base), Some(mine), None) if base != mine => {
+                    conflicts.push(key.clone());
+                    Some(mine.clone())
+                }
+                _ => pick(&base, &mine, &their.cloned(), || {}),
+            };
+            if let Some(tracker) = merged {
+                trackers.insert(key, tracker);
+            }
+        }
+
+        let running = pick(&base.running, &self.running, &theirs.running, || {
+            conflicts.push("running".to_string())
+        })
+        .filter(|running| trackers.contains_key(&running.key));
+
+        let mut merged = InnerAppData::from_parts(merged_sections, Vec::new())
+            .expect("merged sections of valid states are valid");
+        merged.trackers = trackers;
+        merged.running = running;
+        self.merge_from(merged);
+        // e.g. trackers running on both sides
+        self.log_repairs();
+        conflicts
+    }
+
+    /// Reads an exported state, migrating it from older versions
+    fn parse(mut value: Value) -> Result<Self, ImportError> {
+        files::migrate(&mut value, MIGRATIONS)
+            .map_err(|e| ImportError::Format(format!("{:?}", e)))?;
+        let data: Self =
+            serde_json::from_value(value).map_err(|e| ImportError::Format(e.to_string()))?;
+        let violations = data.validate();
+        if !violations.is_empty() {
+            return Err(ImportError::Invalid(
+                violations.iter().map(ToString::to_string).collect(),
+            ));
+        }
+        Ok(data)
+    }
+
+    fn import(&mut self, other: InnerAppData, mode: ImportMode) -> Imported {
+        if let ImportMode::Replace = mode {
+            let imported = other.trackers.keys().cloned().collect();
+            let archived = other.archive.len();
+            self.merge_from(other);
+            return Imported {
+                imported,
+                skipped: Vec::new(),
+                archived,
+            };
+        }
+        let InnerAppData {
+            running,
+            trackers,
+            archive,
+            ..
+        } = other;
+        let (mut imported, mut skipped) = (Vec::new(), Vec::new());
+        for (key, tracker) in trackers {
+            if self.trackers.contains_key(&key) {
+                skipped.push(key);
+            } else {
+                self.trackers.insert(key.clone(), tracker);
+                imported.push(key);
+            }
+        }
+        // the imported running tracker only continues if nothing runs here
+        if let Some(running) = running.filter(|r| imported.contains(&r.key)) {
+            if self.running.is_none() {
+                self.running = Some(running);
+            } else {
+                self.book_session(Session {
+                    key: running.key,
+                    start: running.start_time,
+                    end: clock::now(),
+                });
+            }
+        }
+        let before = self.archive.len();
+        for archived in archive {
+            if !self.archive.iter().any(|a| a.same_submission(&archived)) {
+                self.archive.push(archived);
+            }
+        }
+        Imported {
+            imported,
+            skipped,
+            archived: self.archive.len() - before,
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            running: self.running.clone(),
+            suspended: self.suspended.clone(),
+            trackers: self.trackers.clone(),
+            deleted: self.deleted.clone(),
+            ledger: self.ledger.clone(),
+            sessions: self.sessions.clone(),
+        }
+    }
+
+    /// Resets the trackers with their booked time to the snapshot, returning the replaced ones.
+    /// Submissions are kept.
+    fn restore(&mut self, snapshot: Snapshot) -> Snapshot {
+        let current = self.snapshot();
+        self.running = snapshot.running;
+        self.suspended = snapshot.suspended;
+        self.trackers = snapshot.trackers;
+        self.deleted = snapshot.deleted;
+        self.ledger = snapshot.ledger;
+        self.sessions = snapshot.sessions;
+        current
+    }
+
+    fn events_since(&self, before: &Snapshot) -> Vec<TrackerEvent> {
+        let mut events: Vec<TrackerEvent> = before
+            .trackers
+            .keys()
+            .filter(|key| !self.trackers.contains_key(*key))
+            .map(|key| TrackerEvent::Removed { key: key.clone() })
+            .collect();
+        for (key, tracker) in &self.trackers {
+            match before.trackers.get(key) {
+                Some(previous) if previous == tracker || previous.same_but_folded(tracker) => {}
+                Some(previous) if previous.net_adjustment() != tracker.net_adjustment() => {
+                    events.push(TrackerEvent::Adjusted { key: key.clone() })
+                }
+                Some(_) => events.push(TrackerEvent::Changed { key: key.clone() }),
+                None => events.push(TrackerEvent::Created { key: key.clone() }),
+            }
+        }
+
+        for (key, deleted) in &self.deleted {
+            if before.deleted.get(key) != Some(deleted) {
+                events.push(TrackerEvent::PurgePending {
+                    key: key.clone(),
+                    purge_at: deleted.purge_at,
+                });
+            }
+        }
+        events.extend(
+            before
+                .deleted
+                .keys()
+                .filter(|key| !self.deleted.contains_key(*key) && !self.trackers.contains_key(*key))
+                .map(|key| TrackerEvent::Purged { key: key.clone() }),
+        );
+
+        if before.running != self.running {
+            match (&before.running, &self.running) {
+                (Some(old), Some(new)) if old.key == new.key => {
+                    events.push(TrackerEvent::Changed {
+                        key: new.key.clone(),
+                    })
+                }
+                (old, new) => {
+                    if let Some(old) = old {
+                        events.push(TrackerEvent::Paused {
+                            key: old.key.clone(),
+                        });
+                    }
+                    if let Some(new) = new {
+                        events.push(TrackerEvent::Started {
+                            key: new.key.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    /// Entry of `event` with the tracker's state after the change, or before it for removals
+    fn audit_entry(&self, before: &Snapshot, event: TrackerEvent) -> AuditEntry {
+        let key = event.key();
+        let tracker = match self.trackers.get(key) {
+            Some(tracker) => Some((tracker, self.running_if(key))),
+            None => before.trackers.get(key).map(|tracker| {
+                let running = before.running.as_ref().filter(|r| r.key == key);
+                (tracker, running)
+            }),
+        };
+        let duration = tracker.map(|(tracker, running)| Self::elapsed_of(tracker, running));
+        let description = tracker.and_then(|(tracker, _)| tracker.description.clone());
+        AuditEntry::new(event, duration, description)
+    }
+
+    /// Checks the invariant that at most one existing tracker accrues wall-clock time
+    fn validate(&self) -> Vec<StateViolation> {
+        let mut violations = Vec::new();
+        if let Some(running) = &self.running {
+            if !self.trackers.contains_key(&running.key) {
+                violations.push(StateViolation::UnknownRunningTracker(running.key.clone()));
+            }
+            if running.start_time > clock::now() {
+                violations.push(StateViolation::RunningInFuture(running.key.clone()));
+            }
+        }
+        if let Some(suspended) = &self.suspended {
+            if !self.trackers.contains_key(&suspended.key) {
+                violations.push(StateViolation::UnknownSuspendedTracker(
+                    suspended.key.clone(),
+                ));
+            } else if self.running.is_some() {
+                violations.push(StateViolation::SuspendedWhileRunning(suspended.key.clone()));
+            }
+        }
+        violations.extend(self.overlapping_sessions());
+        violations
+    }
+
+    /// Sessions starting before those of other trackers ended, compared with the one of the
+    /// trackers before ending last
+    fn overlapping_sessions(&self) -> Vec<StateViolation> {
+        let mut sessions: Vec<(&str, DateTime<Local>, DateTime<Local>)> = self
+            .trackers
+            .iter()
+            .flat_map(|(key, tracker)| {
+                tracker
+                    .sessions
+                    .iter()
+                    .map(move |session| (key.as_str(), session.start, session.end))
+            })
+            .collect();
+        let running = self.running.as_ref().filter(|running| {
+            self.trackers.contains_key(&running.key) && running.start_time <= clock::now()
+        });
+        if let Some(running) = running {
+            sessions.push((&running.key, running.start_time.into(), clock::local_now()));
+        }
+        sessions.sort_by_key(|(_, start, _)| *start);
+
+        // the session ending last and the one of another tracker ending last
+        let mut last: Option<(&str, DateTime<Local>)> = None;
+        let mut last_of_others: Option<(&str, DateTime<Local>)> = None;
+        let mut violations = Vec::new();
+        for (key, start, end) in sessions {
+            let before = match last {
+                Some((last_key, _)) if last_key == key => last_of_others,
+                _ => last,
+            };
+            if let Some((overlapped, until)) = before.filter(|(_, until)| start < *until) {
+                violations.push(StateViolation::OverlappingSessions {
+                    key: key.to_string(),
+                    start,
+                    overlapped: overlapped.to_string(),
+                    until,
+                });
+                if end <= until {
+                    // removed by the repair
+                    continue;
+                }
+            }
+            match last {
+                Some((last_key, until)) if last_key == key => {
+                    last = Some((key, until.max(end)));
+                }
+                Some((_, until)) if end <= until => {
+                    if last_of_others.is_none_or(|(_, until)| end > until) {
+                        last_of_others = Some((key, end));
+                    }
+                }
+                _ => {
+                    last_of_others = last;
+                    last = Some((key, end));
+                }
+            }
+        }
+        violations
+    }
+
+    /// Fixes all violations found by [`InnerAppData::validate`] and returns them
+    fn repair(&mut self) -> Vec<StateViolation> {
+        let violations = self.validate();
+        for violation in &violations {
+            match violation {
+                StateViolation::UnknownRunningTracker(_) => self.running = None,
+                StateViolation::RunningInFuture(_) => {
+                    if let Some(running) = self.running.as_mut() {
+                        running.start_time = clock::now();
+                    }
+                }
+                StateViolation::UnknownSuspendedTracker(_)
+                | StateViolation::SuspendedWhileRunning(_) => self.suspended = None,
+                StateViolation::OverlappingSessions {
+                    key, start, until, ..
+                } => self.truncate_session(key, *start, *until),
+            }
+        }
+        violations
+    }
+
+    /// Lets the session of `key` starting at `start` start at `until` instead, removing it if it
+    /// ends before. The time cut off is removed from the ledger and the history of sessions.
+    fn truncate_session(&mut self, key: &str, start: DateTime<Local>, until: DateTime<Local>) {
+        if let Some(running) = self
+            .running
+            .as_mut()
+            .filter(|running| running.key == key && running.start_time == start.into())
+        {
+            running.start_time = until.into();
+            return;
+        }
+        let Some(tracker) = self.trackers.get_mut(key) else {
+            return;
+        };
+        let Some(index) = tracker.sessions.iter().position(|s| s.start == start) else {
+            return;
+        };
+        let session = tracker.sessions[index];
+        let removed = session.end <= until;
+        if removed {
+            tracker.sessions.remove(index);
+        } else {
+            tracker.sessions[index].start = until;
+        }
+        let cut = Session {
+            key: key.to_string(),
+            start: start.into(),
+            end: session.end.min(until).into(),
+        };
+        for (date, duration) in cut.per_day() {
+            self.subtract_from_ledger(date, key, duration);
+        }
+        let booked = self
+            .sessions
+            .iter()
+            .position(|s| s.key == key && s.start == cut.start);
+        match booked {
+            Some(index) if removed => {
+                self.sessions.remove(index);
+            }
+            Some(index) => self.sessions[index].start = until.into(),
+            None => {}
+        }
+    }
+
+    /// Repairs the state like [`InnerAppData::repaired`], e.g. after merging other states
+    fn log_repairs(&mut self) {
+        for violation in self.repair() {
+            tracing::warn!("repaired invalid state: {}", violation);
+        }
+    }
+
+    fn repaired(mut self) -> Self {
+        self.version = MIGRATIONS.len() as u64;
+        self.log_repairs();
+        self
+    }
+}
+
+/// All trackers, sharing the cached list and only recomputing the running tracker
+#[derive(Debug)]
+pub struct TrackerListing {
+    trackers: Arc<Vec<TrackerInformation>>,
+    running: Option<(usize, TrackerInformation)>,
+}
+
+impl TrackerListing {
+    pub fn len(&self) -> usize {
+        self.trackers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trackers.is_empty()
+    }
+}
+
+impl Serialize for TrackerListing {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.trackers.len()))?;
+        for (index, tracker) in self.trackers.iter().enumerate() {
+            match &self.running {
+                Some((running_index, running)) if *running_index == index => {
+                    seq.serialize_element(running)?
+                }
+                _ => seq.serialize_element(tracker)?,
+            }
+        }
+        seq.end()
+    }
+}
+
+/// Tracker list together with the state version it was computed for
+type CachedListing = (u64, Arc<Vec<TrackerInformation>>);
+
+pub struct AppData {
+    inner: RwLock<InnerAppData>,
+    /// Replaced when migrating to another backend, see [`AppData::migrate_storage`]
+    storage: RwLock<Box<dyn Storage>>,
+    /// Incremented on every change, invalidating `listings`
+    version: AtomicU64,
+    listings: Mutex<HashMap<Detail, CachedListing>>,
+    events: broadcast::Sender<TrackerEvent>,
+    audit: Option<AuditLog>,
+    /// Deleted trackers are kept for this long if given
+    purge_delay: Option<Duration>,
+    /// Archived and deleted trackers are dropped after this long on compaction if given
+    compact_retention: Option<Duration>,
+    history: Mutex<History>,
+    /// Notified on every change to be persisted
+    dirty: Notify,
+    reloads: Mutex<ReloadGuard>,
+    /// State as last loaded or saved, the base for merging changes made outside
+    base: Mutex<Encoded>,
+    /// How a corrupt state was recovered on startup, see `GET /health`
+    recovery: Option<String>,
+    /// Changes not saved yet with `STATE_JOURNAL`
+    wal: Option<WriteAheadLog>,
+}
+
+impl AppData {
+    fn reading<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&InnerAppData) -> T,
+    {
+        let AppData { inner, .. } = self;
+        f(inner.read().unwrap().deref())
+    }
+
+    /// Applies and persists a change that can be undone
+    fn writing<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut InnerAppData) -> T,
+    {
+        self.writing_recorded(false, f)
+    }
+
+    /// Like [`AppData::writing`] for changes beyond the trackers, e.g. of the archive or the goals
+    fn writing_sections<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut InnerAppData) -> T,
+    {
+        self.writing_recorded(true, f)
+    }
+
+    fn writing_recorded<F, T>(&self, sections: bool, f: F) -> T
+    where
+        F: FnOnce(&mut InnerAppData) -> T,
+    {
+        let result = self.applying(sections, true, f);
+        self.flush();
+        result
+    }
+
+    /// Wakes the background flush, see [`spawn_flush`]
+    fn flush(&self) {
+        self.dirty.notify_one();
+    }
+
+    /// Writes the state to the storage, blocking until it is written, and logs failures
+    pub fn save(&self) {
+        if let Err(e) = self.try_save() {
+            tracing::error!("could not save state: {}", e);
+        }
+    }
+
+    /// Serializes the state while holding it, but writes it only after releasing it, so that
+    /// changes don't wait for the storage
+    pub fn try_save(&self) -> Result<(), StorageError> {
+        let inner = self.inner.read().unwrap();
+        // held until written, so that the storage is not migrated in between
+        let storage = self.storage.read().unwrap();
+        debug_assert!(inner.validate().is_empty());
+        let encoded = storage.encode(&inner)?;
+        let saved = self.version.load(Ordering::Acquire);
+        drop(inner);
+        storage.write(&encoded)?;
+        *self.base.lock().unwrap() = encoded;
+        if let Some(wal) = &self.wal {
+            wal.saved(saved)?;
+        }
+        Ok(())
+    }
+
+    /// Applies a change that cannot be undone, journaling it with `STATE_JOURNAL`. Only the
+    /// trackers are journaled unless `sections`.
+    fn writing_without_flush<F, T>(&self, sections: bool, f: F) -> T
+    where
+        F: FnOnce(&mut InnerAppData) -> T,
+    {
+        self.applying(sections, false, f)
+    }
+
+    /// Applies a change, recording it for undo if `record`. The trackers before the change are
+    /// copied once at most, for undo, the published events, the audit log and the journal alike.
+    fn applying<F, T>(&self, sections: bool, record: bool, f: F) -> T
+    where
+        F: FnOnce(&mut InnerAppData) -> T,
+    {
+        let AppData {
+            inner,
+            version,
+            events,
+            audit,
+            wal,
+            history,
+            ..
+        } = self;
+        let mut inner = inner.write().unwrap();
+        let seq = version.fetch_add(1, Ordering::Release) + 1;
+        let observed = events.receiver_count() > 0 || audit.is_some() || wal.is_some();
+        let before = (record || observed).then(|| inner.snapshot());
+        let result = f(inner.deref_mut());
+        let Some(before) = before else {
+            return result;
+        };
+        if observed {
+            self.journal(seq, &before, &inner, sections);
+            let changes = inner.events_since(&before);
+            if let Some(audit) = audit {
+                let entries = changes.iter().cloned();
+                audit.record(entries.map(|event| inner.audit_entry(&before, event)));
+            }
+            for event in changes {
+                // without receivers left there is nobody to miss the event
+                let _ = events.send(event);
+            }
+        }
+        if record && !before.matches(&inner) {
+            history.lock().unwrap().record(before);
+        }
+        drop(inner);
+        // written after releasing the state, so that other changes don't wait for the file
+        if let Some(audit) = audit {
+            audit.write_pending();
+        }
+        result
+    }
+
+    /// Appends the change since `before` to the write-ahead log, if any. Failures are only logged,
+    /// the change is still saved with the state.
+    fn journal(&self, seq: u64, before: &Snapshot, after: &InnerAppData, sections: bool) {
+        let Some(wal) = &self.wal else {
+            return;
+        };
+        if let Some(change) = Change::between(before, after, sections) {
+            if let Err(e) = wal.append(seq, change) {
+                tracing::error!("could not journal change: {:?}", e);
+            }
+        }
+    }
+
+    /// Reverts the latest change of the trackers, `None` if there is none left to undo
+    pub fn undo(&self) -> Option<Vec<TrackerInformation>> {
+        self.writing_without_flush(false, |a| {
+            let mut history = self.history.lock().unwrap();
+            let before = history.undo.pop_back()?;
+            let undone = a.restore(before);
+            history.redo.push(undone);
+            Some(())
+        })?;
+        self.flush();
+        Some(self.list_trackers(Detail::Summary))
+    }
+
+    /// Reapplies the latest undone change, `None` if there is none or a new change happened since
+    pub fn redo(&self) -> Option<Vec<TrackerInformation>> {
+        self.writing_without_flush(false, |a| {
+            let mut history = self.history.lock().unwrap();
+            let undone = history.redo.pop()?;
+            let before = a.restore(undone);
+            history.undo.push_back(before);
+            Some(())
+        })?;
+        self.flush();
+        Some(self.list_trackers(Detail::Summary))
+    }
+
+    /// Events of all following changes, buffering at most [`events::CAPACITY`] of them
+    pub fn subscribe(&self) -> broadcast::Receiver<TrackerEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn current(&self) -> Result<TrackerInformation, TrackerError> {
+        self.reading(|a| a.current())
+    }
+
+    pub fn get_tracker(
+        &self,
+        key: &str,
+        detail: Detail,
+    ) -> Result<TrackerInformation, TrackerError> {
+        self.reading(|a| a.get_tracker(key, detail))
+    }
+
+    pub fn list_trackers(&self, detail: Detail) -> Vec<TrackerInformation> {
+        self.reading(|a| a.list_trackers(detail))
+    }
+
+    pub fn worklogs(&self, grouping: SubmissionGrouping) -> Vec<TrackerInformation> {
+        self.reading(|a| a.worklogs(grouping))
+    }
+
+    /// Like [`AppData::list_trackers`], but reuses the list computed for an unchanged state
+    pub fn listing(&self, detail: Detail) -> TrackerListing {
+        let AppData {
+            inner,
+            version,
+            listings,
+            ..
+        } = self;
+        let inner = inner.read().unwrap();
+        let version = version.load(Ordering::Acquire);
+        let mut listings = listings.lock().unwrap();
+        let trackers = match listings.get(&detail) {
+            Some((cached, trackers)) if *cached == version => trackers.clone(),
+            _ => {
+                let trackers = Arc::new(inner.list_trackers(detail));
+                listings.insert(detail, (version, trackers.clone()));
+                trackers
+            }
+        };
+        TrackerListing {
+            trackers,
+            running: inner.running_information(detail),
+        }
+    }
+
+    pub fn archive_candidates(&self, limit: usize) -> Vec<String> {
+        self.reading(|a| a.archive_candidates(limit))
+    }
+
+    pub fn set_description(
+        &self,
+        key: &str,
+        description: Option<String>,
+    ) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.set_description(key, description))
+    }
+
+    pub fn import_sessions(
+        &self,
+        key: &str,
+        sessions: &[(DateTime<Local>, DateTime<Local>)],
+        description: Option<String>,
+    ) -> Result<Duration, TrackerError> {
+        self.writing(|a| a.import_sessions(key, sessions, description))
+    }
+
+    pub fn patch_fields(
+        &self,
+        key: &str,
+        fields: BTreeMap<String, Option<String>>,
+    ) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.patch_fields(key, fields))
+    }
+
+    pub fn set_paired_with(
+        &self,
+        key: &str,
+        account_id: Option<String>,
+    ) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.set_paired_with(key, account_id))
+    }
+
+    pub fn adjust_positive_duration(
+        &self,
+        key: &str,
+        duration: Duration,
+    ) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.adjust_positive_duration(key, duration))
+    }
+
+    pub fn adjust_negative_duration(
+        &self,
+        key: &str,
+        duration: Duration,
+    ) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.adjust_negative_duration(key, duration))
+    }
+
+    pub fn adjust(
+        &self,
+        key: &str,
+        adjustment: AdjustTracker,
+    ) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.adjust(key, adjustment))
+    }
+
+    pub fn adjust_all(
+        &self,
+        adjustments: IndexMap<String, AdjustTracker>,
+    ) -> Result<Vec<TrackerInformation>, TrackerError> {
+        self.writing(|a| a.adjust_all(adjustments))
+    }
+
+    pub fn correct_all(
+        &self,
+        corrections: Vec<(String, Option<String>, Duration)>,
+    ) -> Result<Vec<String>, (String, TrackerError)> {
+        self.writing(|a| a.correct_all(corrections))
+    }
+
+    pub fn round_durations(&self, step: Duration, policy: RoundingPolicy) -> Vec<String> {
+        self.writing(|a| a.round_durations(step, policy))
+    }
+
+    pub fn prune_empty(&self, date: NaiveDate) -> Vec<String> {
+        self.writing(|a| a.prune_empty(date, self.purge_delay))
+    }
+
+    pub fn archive_external(&self, date: NaiveDate) -> Vec<String> {
+        self.writing_sections(|a| a.archive_external(date))
+    }
+
+    pub fn record_day(&self, summary: DaySummary) {
+        self.writing_sections(|a| a.record_day(summary))
+    }
+
+    pub fn start(&self, key: &str) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.start(key))
+    }
+
+    pub fn pause(&self) {
+        self.writing(|a| a.pause())
+    }
+
+    pub fn resume_last(&self) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.resume_last())
+    }
+
+    pub fn toggle(&self, key: &str) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.toggle(key))
+    }
+
+    pub fn account_id(&self) -> Option<String> {
+        self.reading(|a| a.account_id.clone())
+    }
+
+    pub fn set_account_id(&self, account_id: &str) {
+        if self.account_id().as_deref() != Some(account_id) {
+            self.writing_sections(|a| a.account_id = Some(account_id.to_string()))
+        }
+    }
+
+    pub fn suspend(&self) {
+        self.writing(|a| a.suspend())
+    }
+
+    /// Persists the time of the running tracker so far, see `CHECKPOINT_INTERVAL`. Its elapsed
+    /// time stays the same, so this is neither recorded for undo nor announced as change.
+    pub fn checkpoint(&self) -> Option<String> {
+        let mut inner = self.inner.write().unwrap();
+        let before = self.wal.is_some().then(|| inner.snapshot());
+        let key = inner.checkpoint()?;
+        let seq = self.version.fetch_add(1, Ordering::Release) + 1;
+        if let Some(before) = before {
+            self.journal(seq, &before, &inner, false);
+        }
+        drop(inner);
+        self.flush();
+        Some(key)
+    }
+
+    pub fn resume(&self, policy: ResumePolicy, within: Duration) -> Option<TrackerInformation> {
+        self.writing(|a| a.resume(policy, within))
+    }
+
+    pub fn create_tracker(
+        &self,
+        key: &str,
+        id: &str,
+        details: Option<IssueDetails>,
+    ) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.create_tracker(key, id, details))
+    }
+
+    pub fn create_external_tracker(&self, key: &str) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.create_external_tracker(key))
+    }
+
+    pub fn start_unknown(&self) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.start_unknown())
+    }
+
+    pub fn triage(
+        &self,
+        assignments: &[(String, Duration)],
+    ) -> Result<Vec<TrackerInformation>, TrackerError> {
+        self.writing(|a| a.triage(assignments))
+    }
+
+    pub fn link(
+        &self,
+        key: &str,
+        new_key: &str,
+        id: &str,
+        details: Option<IssueDetails>,
+    ) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.link(key, new_key, id, details))
+    }
+
+    pub fn summary(&self) -> StateSummary {
+        self.reading(|a| a.summary())
+    }
+
+    /// Keys of the trackers migrated from the legacy state whose issue id is still missing
+    pub fn legacy_keys(&self) -> Vec<String> {
+        self.reading(|a| a.legacy_keys())
+    }
+
+    pub fn resolve_legacy(
+        &self,
+        key: &str,
+        id: &str,
+        details: Option<IssueDetails>,
+    ) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.resolve_legacy(key, id, details))
+    }
+
+    pub fn remove(&self, key: &str) -> Result<PausedTracker, TrackerError> {
+        self.writing(|a| a.remove(key, self.purge_delay))
+    }
+
+    pub fn remove_all(&self) -> Vec<PausedTracker> {
+        self.writing(|a| a.remove_all(self.purge_delay))
+    }
+
+    pub fn undelete(&self, key: &str) -> Result<TrackerInformation, TrackerError> {
+        self.writing(|a| a.undelete(key))
+    }
+
+    pub fn tracker_sessions(&self, key: &str) -> Result<Vec<TrackerSession>, TrackerError> {
+        self.reading(|a| a.tracker_sessions(key))
+    }
+
+    pub fn split_session(
+        &self,
+        key: &str,
+        id: usize,
+        at: DateTime<Local>,
+        to: Option<&str>,
+    ) -> Result<Vec<TrackerInformation>, TrackerError> {
+        self.writing(|a| a.split_session(key, id, at, to))
+    }
+
+    pub fn week_archive(&self, week: IsoWeek) -> (Vec<SessionRow>, Vec<WorklogRow>) {
+        self.reading(|a| a.week_archive(week))
+    }
+
+    pub fn trash(&self) -> Vec<TrashedTracker> {
+        self.reading(|a| a.trash())
+    }
+
+    pub fn purge_deleted(&self) -> Vec<String> {
+        if self.reading(|a| a.deleted.is_empty()) {
+            return Vec::new();
+        }
+        self.writing(|a| a.purge_deleted())
+    }
+
+    pub fn compact(&self) -> Compaction {
+        self.writing_sections(|a| a.compact(self.compact_retention, self.purge_delay))
+    }
+
+    pub fn submissions(&self) -> Vec<Submission> {
+        self.reading(|a| a.submissions.clone())
+    }
+
+    pub fn submissions_since(&self, since: DateTime<Local>) -> Vec<Submission> {
+        self.reading(|a| {
+            a.submissions
+                .iter()
+                .filter(|submission| submission.submitted_at >= since)
+                .cloned()
+                .collect()
+        })
+    }
+
+    /// The complete state, as written to the storage
+    pub fn export(&self) -> Value {
+        self.reading(|a| serde_json::to_value(a).expect("state is serializable"))
+    }
+
+    /// Validates the exported state and applies it, see [`ImportMode`]
+    pub fn import(&self, state: Value, mode: ImportMode) -> Result<Imported, ImportError> {
+        let state = InnerAppData::parse(state)?;
+        Ok(self.writing_sections(|a| a.import(state, mode)))
+    }
+
+    pub fn archived(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Vec<ArchivedTracker> {
+        self.reading(|a| a.archived(from, to))
+    }
+
+    /// Archives the `submitted` trackers with the receipts of their worklogs, keeping those with
+    /// failed ones
+    pub fn record_submission(
+        &self,
+        submitted: &[String],
+        receipts: Vec<WorklogReceipt>,
+        failed: Vec<FailedWorklog>,
+        skipped: Vec<String>,
+    ) -> Submission {
+        let submission =
+            self.writing_sections(|a| a.record_submission(submitted, receipts, failed, skipped));
+        // undoing would bring back submitted trackers, which could be submitted twice then
+        *self.history.lock().unwrap() = History::default();
+        if let Some(audit) = &self.audit {
+            audit.append(submission.receipts.iter().map(AuditEntry::submitted));
+        }
+        for receipt in &submission.receipts {
+            let key = receipt.key.clone();
+            let _ = self.events.send(TrackerEvent::Submitted { key });
+        }
+        submission
+    }
+
+    pub fn sum(&self) -> Sum {
+        self.reading(|a| a.sum())
+    }
+
+    pub fn sum_since(&self, since: SystemTime) -> Sum {
+        self.reading(|a| a.sum_since(since))
+    }
+
+    pub fn goals(&self) -> Vec<Goal> {
+        self.reading(|a| a.goals.clone())
+    }
+
+    pub fn set_goal(&self, goal: Goal) {
+        self.writing_sections(|a| a.set_goal(goal))
+    }
+
+    pub fn remove_goal(&self, name: &str) -> Option<Goal> {
+        self.writing_sections(|a| a.remove_goal(name))
+    }
+
+    /// Progress of all goals in the week starting on `first`
+    pub fn goal_progress(&self, first: NaiveDate) -> Vec<GoalProgress> {
+        self.reading(|a| {
+            let week = a.tracked_trackers(first, 7);
+            a.goals.iter().map(|goal| goal.progress(&week)).collect()
+        })
+    }
+
+    /// Trackers with time on `date`, each with its time of the day, also those created before
+    pub fn day_trackers(&self, date: NaiveDate) -> Vec<TrackerInformation> {
+        self.reading(|a| a.tracked_trackers(date, 1))
+    }
+
+    pub fn day_totals(&self, date: NaiveDate) -> DayTotals {
+        DayTotals::new(date, self.reading(|a| a.day_totals(date)))
+    }
+
+    pub fn storage_backend(&self) -> StorageBackend {
+        self.storage.read().unwrap().backend()
+    }
+
+    /// File of the storage to watch for changes by hand, see [`AppData::reload_state`]
+    pub fn watched_file(&self) -> Option<PathBuf> {
+        self.storage
+            .read()
+            .unwrap()
+            .watched_file()
+            .map(Path::to_path_buf)
+    }
+
+    /// Applies changes of the stored state made by others, ignoring own writes
+    pub fn reload_state(&self) {
+        let changed = self.storage.read().unwrap().load_changed();
+        let state = match changed {
+            Ok(Some(state)) => state,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!("could not reload state: {}", e);
+                return;
+            }
+        };
+        if self.reloads.lock().unwrap().allow() {
+            let conflicts = self.merge_reloaded(state);
+            if !conflicts.is_empty() {
+                let conflicts = conflicts.join(", ");
+                tracing::warn!(
+                    "state changed outside and in memory, kept both: {}",
+                    conflicts
+                );
+            }
+        }
+    }
+
+    /// A stored state as base of merges
+    fn encode(&self, data: &InnerAppData) -> Encoded {
+        let storage = self.storage.read().unwrap();
+        storage.encode(data).expect("state is serializable")
+    }
+
+    /// Merges the state changed outside with the changes in memory since it was last saved, see
+    /// [`InnerAppData::merge_three_way`], and writes the result unless it equals the stored one
+    fn merge_reloaded(&self, theirs: InnerAppData) -> Vec<String> {
+        let theirs = theirs.repaired();
+        let stored = serde_json::to_value(&theirs).expect("state is serializable");
+        let encoded = self.encode(&theirs);
+        let base = std::mem::replace(&mut *self.base.lock().unwrap(), encoded);
+        let base = base.decode().expect("saved state is valid");
+        let (conflicts, unsaved) = self.writing_without_flush(true, |a| {
+            let conflicts = a.merge_three_way(base, theirs);
+            self.history.lock().unwrap().clear();
+            (conflicts, serde_json::to_value(&*a).ok() != Some(stored))
+        });
+        if unsaved {
+            self.flush();
+        }
+        conflicts
+    }
+
+    /// Reloads the stored state, merging it like changes detected outside or, with `force`,
+    /// replacing the state in memory. Returns the conflicting changes that were merged.
+    pub fn reload(&self, force: bool) -> Result<Vec<String>, StorageError> {
+        let theirs = self.storage.read().unwrap().load()?;
+        let theirs = theirs.unwrap_or_else(InnerAppData::new);
+        if !force {
+            return Ok(self.merge_reloaded(theirs));
+        }
+        let theirs = theirs.repaired();
+        *self.base.lock().unwrap() = self.encode(&theirs);
+        self.writing_without_flush(true, |a| {
+            a.merge_from(theirs);
+            self.history.lock().unwrap().clear();
+        });
+        Ok(Vec::new())
+    }
+
+    /// Copies the state into the storage `to` and continues with it once the copy is verified.
+    /// Changes wait until the migration is done.
+    pub fn migrate_storage(
+        &self,
+        config: &AppConfig,
+        to: StorageBackend,
+    ) -> Result<MigratedStorage, MigrationError> {
+        let inner = self.inner.read().unwrap();
+        let mut storage = self.storage.write().unwrap();
+        let (migrated, result) = storage::migrate(config, storage.as_ref(), to, &inner)?;
+        *storage = migrated;
+        Ok(result)
+    }
+
+    pub fn recovery(&self) -> Option<&str> {
+        self.recovery.as_deref()
+    }
+
+    /// Number of detected loops of reloading the state, see [`AppData::reload_state`]
+    pub fn reload_loops(&self) -> u64 {
+        self.reloads.lock().unwrap().loops
+    }
+}
+
+/// Loads the state, recovering a corrupt one only if `lease` allows to write it
+impl From<(&AppConfig, &WriterLease)> for AppData {
+    fn from((config, lease): (&AppConfig, &WriterLease)) -> Self {
+        let storage = storage::open(config).unwrap();
+        let (inner, recovery) = storage.load_recovering(lease.held()).unwrap();
+        let mut inner = inner.unwrap_or_else(InnerAppData::new);
+        let key = config
+            .state_encryption_key
+            .as_deref()
+            .and_then(FileKey::from_base64);
+        let wal = WriteAheadLog::new(config.wal_file(), key);
+        // also without `STATE_JOURNAL`, so that turning it off loses nothing
+        let writer = lease.held() && !lease.is_read_only();
+        let changes: Vec<Change> = if writer {
+            wal.replay().unwrap()
+        } else {
+            Vec::new()
+        };
+        if !changes.is_empty() {
+            tracing::warn!(
+                "replaying {} unsaved changes of {}",
+                changes.len(),
+                wal.path().display()
+            );
+            changes
+                .into_iter()
+                .for_each(|change| change.apply(&mut inner));
+        }
+        let inner = inner.repaired();
+        if writer && wal.path().exists() {
+            storage.save(&inner).unwrap();
+            wal.clear().unwrap();
+        }
+        let base = storage.encode(&inner).expect("state is serializable");
         AppData {
             inner: RwLock::new(inner),
-            path: path.into(),
+            storage: RwLock::new(storage),
+            version: AtomicU64::new(0),
+            listings: Mutex::new(HashMap::new()),
+            events: broadcast::channel(events::CAPACITY).0,
+            audit: config.audit_log.then(|| AuditLog::new(config.audit_file())),
+            purge_delay: config.purge_delay,
+            compact_retention: config.compact_retention,
+            history: Mutex::new(History::default()),
+            dirty: Notify::new(),
+            reloads: Mutex::new(ReloadGuard::default()),
+            base: Mutex::new(base),
+            recovery,
+            wal: (writer && config.state_journal).then_some(wal),
+        }
+    }
+}
+
+/// Persists changes in a background task, so that requests don't wait for the storage. Changes in
+/// quick succession are written at once. A standby instance only follows the writer and keeps its
+/// changes, e.g. merged ones, until it takes over. A read-only one writes nothing.
+pub fn spawn_flush(data: Arc<AppData>, lease: Arc<WriterLease>) {
+    tokio::spawn(async move {
+        loop {
+            data.dirty.notified().await;
+            if lease.is_read_only() {
+                continue;
+            }
+            if !lease.held() {
+                tokio::time::sleep(STANDBY_FLUSH_RETRY).await;
+                data.dirty.notify_one();
+                continue;
+            }
+            let data = data.clone();
+            if let Err(e) = tokio::task::spawn_blocking(move || data.save()).await {
+                tracing::error!("saving state failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Persists the time of the running tracker every `interval`, so that it is on disk even if the
+/// tracker runs for hours without any change
+pub fn schedule_checkpoint(scheduler: &Scheduler, data: Arc<AppData>, interval: Duration) {
+    scheduler.add("checkpoint", interval, move || {
+        let data = data.clone();
+        async move {
+            Ok(match data.checkpoint() {
+                Some(key) => format!("checkpointed {}", key),
+                None => "no tracker running".to_string(),
+            })
+        }
+    });
+}
+
+/// Compacts the state every `interval`, see [`AppData::compact`]
+pub fn schedule_compaction(scheduler: &Scheduler, data: Arc<AppData>, interval: Duration) {
+    scheduler.add("compact", interval, move || {
+        let data = data.clone();
+        async move {
+            let compaction = data.compact();
+            Ok(format!(
+                "folded {} trackers, dropped {} archived and purged {} deleted ones",
+                compaction.folded.len(),
+                compaction.archived,
+                compaction.purged.len()
+            ))
+        }
+    });
+}
+
+/// Purges deleted trackers once their `PURGE_DELAY` passed
+pub fn schedule_purge(scheduler: &Scheduler, data: Arc<AppData>) {
+    scheduler.add("purge", PURGE_INTERVAL, move || {
+        let data = data.clone();
+        async move {
+            let purged = data.purge_deleted();
+            Ok(format!("purged {} trackers", purged.len()))
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// State with a paused tracker `ABC-1`, running `running` since `start_time` if given
+    fn state(running: Option<(&str, SystemTime)>) -> InnerAppData {
+        let mut data: InnerAppData = serde_json::from_str(r#"{"trackers": {}}"#).unwrap();
+        data.trackers
+            .insert("ABC-1".to_string(), PausedTracker::new("10001", None));
+        data.running = running.map(|(key, start_time)| RunningTracker {
+            key: key.to_string(),
+            start_time,
+        });
+        data
+    }
+
+    #[test]
+    fn valid_state_is_not_repaired() {
+        let mut data = state(Some(("ABC-1", clock::now() - Duration::from_secs(60))));
+        let running = data.running.clone();
+
+        assert!(data.validate().is_empty());
+        assert!(data.repair().is_empty());
+        assert_eq!(data.running, running);
+    }
+
+    #[test]
+    fn state_without_running_tracker_is_valid() {
+        assert!(state(None).validate().is_empty());
+    }
+
+    #[test]
+    fn unknown_running_tracker_is_stopped() {
+        let mut data = state(Some(("ABC-2", clock::now())));
+
+        let violations = data.repair();
+
+        assert!(matches!(
+            violations.as_slice(),
+            [StateViolation::UnknownRunningTracker(key)] if key == "ABC-2"
+        ));
+        assert_eq!(data.running, None);
+        assert!(data.trackers.contains_key("ABC-1"));
+        assert!(data.validate().is_empty());
+    }
+
+    #[test]
+    fn running_tracker_started_in_the_future_starts_now() {
+        let future = clock::now() + Duration::from_secs(60 * 60);
+        let mut data = state(Some(("ABC-1", future)));
+
+        let violations = data.repair();
+
+        assert!(matches!(
+            violations.as_slice(),
+            [StateViolation::RunningInFuture(key)] if key == "ABC-1"
+        ));
+        let running = data.running.as_ref().unwrap();
+        assert_eq!(running.key, "ABC-1");
+        assert!(running.start_time <= clock::now());
+        assert!(data.validate().is_empty());
+    }
+
+    #[test]
+    fn all_violations_are_repaired_at_once() {
+        let future = clock::now() + Duration::from_secs(60 * 60);
+        let mut data = state(Some(("ABC-2", future)));
+
+        let violations = data.repair();
+
+        assert_eq!(violations.len(), 2);
+        assert_eq!(data.running, None);
+        assert!(data.validate().is_empty());
+    }
+
+    fn at(time: &str) -> DateTime<Local> {
+        format!("2025-01-06T{}:00Z", time).parse().unwrap()
+    }
+
+    /// Adds a paused tracker `key` with a session from `start` to `end`
+    fn with_session(data: &mut InnerAppData, key: &str, start: &str, end: &str) {
+        let tracker = data
+            .trackers
+            .entry(key.to_string())
+            .or_insert_with(|| PausedTracker::new("10002", None));
+        tracker.sessions.push(Interval {
+            start: at(start),
+            end: at(end),
+        });
+    }
+
+    #[test]
+    fn overlapping_sessions_are_truncated() {
+        let mut data = state(None);
+        with_session(&mut data, "ABC-1", "09:00", "11:00");
+        with_session(&mut data, "ABC-2", "10:00", "12:00");
+        with_session(&mut data, "ABC-3", "10:30", "11:30");
+        with_session(&mut data, "ABC-1", "11:45", "12:30");
+
+        let violations = data.repair();
+
+        assert!(matches!(
+            violations.as_slice(),
+            [
+                StateViolation::OverlappingSessions { key: second, overlapped: first, .. },
+                StateViolation::OverlappingSessions { key: contained, .. },
+                StateViolation::OverlappingSessions { key: last, overlapped: by, .. },
+            ] if second == "ABC-2" && first == "ABC-1" && contained == "ABC-3"
+                && last == "ABC-1" && by == "ABC-2"
+        ));
+        let sessions = |key: &str| data.trackers[key].sessions.clone();
+        assert_eq!(
+            sessions("ABC-2"),
+            [Interval {
+                start: at("11:00"),
+                end: at("12:00")
+            }]
+        );
+        assert!(sessions("ABC-3").is_empty());
+        assert_eq!(sessions("ABC-1")[1].start, at("12:00"));
+        assert!(data.validate().is_empty());
+    }
+
+    #[test]
+    fn running_tracker_overlapping_a_session_starts_after_it() {
+        let now = clock::local_now();
+        let mut data = state(Some(("ABC-1", (now - chrono::Duration::hours(1)).into())));
+        data.trackers
+            .insert("ABC-2".to_string(), PausedTracker::new("10002", None));
+        data.trackers["ABC-2"].sessions.push(Interval {
+            start: now - chrono::Duration::hours(2),
+            end: now - chrono::Duration::minutes(30),
+        });
+
+        let violations = data.repair();
+
+        assert!(matches!(
+            violations.as_slice(),
+            [StateViolation::OverlappingSessions { key, .. }] if key == "ABC-1"
+        ));
+        let running = data.running.as_ref().unwrap();
+        assert_eq!(
+            running.start_time,
+            SystemTime::from(now - chrono::Duration::minutes(30))
+        );
+        assert!(data.validate().is_empty());
+    }
+
+    #[test]
+    fn sessions_of_the_same_tracker_do_not_violate() {
+        let mut data = state(None);
+        with_session(&mut data, "ABC-1", "09:00", "11:00");
+        with_session(&mut data, "ABC-1", "10:00", "12:00");
+
+        assert!(data.validate().is_empty());
+    }
+
+    #[test]
+    fn unknown_suspended_tracker_is_dropped() {
+        let mut data = state(None);
+        data.suspended = Some(RunningTracker::new("ABC-2"));
+
+        let violations = data.repair();
+
+        assert!(matches!(
+            violations.as_slice(),
+            [StateViolation::UnknownSuspendedTracker(key)] if key == "ABC-2"
+        ));
+        assert_eq!(data.suspended, None);
+        assert!(data.validate().is_empty());
+    }
+
+    #[test]
+    fn suspended_tracker_is_dropped_while_one_runs() {
+        let mut data = state(Some(("ABC-1", clock::now())));
+        data.suspended = Some(RunningTracker::new("ABC-1"));
+
+        let violations = data.repair();
+
+        assert!(matches!(
+            violations.as_slice(),
+            [StateViolation::SuspendedWhileRunning(key)] if key == "ABC-1"
+        ));
+        assert_eq!(data.suspended, None);
+        assert!(data.running.is_some());
+    }
+
+    #[test]
+    fn starting_by_hand_replaces_resuming() {
+        let mut data = state(None);
+        data.suspended = Some(RunningTracker::new("ABC-1"));
+
+        data.start("ABC-1").unwrap();
+
+        assert!(data.validate().is_empty());
+    }
+
+    #[test]
+    fn repaired_state_has_all_migrations_applied() {
+        let data = state(Some(("ABC-2", clock::now()))).repaired();
+
+        assert_eq!(data.version, MIGRATIONS.len() as u64);
+        assert_eq!(data.running, None);
+    }
+
+    /// `data` after applying the changes as read back from the write-ahead log
+    fn replayed(mut data: InnerAppData, changes: Vec<Change>) -> InnerAppData {
+        for change in changes {
+            let line = serde_json::to_string(&change).unwrap();
+            serde_json::from_str::<Change>(&line)
+                .unwrap()
+                .apply(&mut data);
         }
+        data
+    }
+
+    #[test]
+    fn journaled_changes_result_in_the_same_state() {
+        let saved = serde_json::to_string(&state(None)).unwrap();
+        let mut data = state(None);
+        let mut changes = Vec::new();
+        let mut change = |data: &mut InnerAppData, f: &dyn Fn(&mut InnerAppData)| {
+            let before = data.snapshot();
+            f(data);
+            changes.push(Change::between(&before, data, false).unwrap());
+        };
+
+        change(&mut data, &|a| {
+            a.create_external_tracker("MEETING").unwrap();
+        });
+        change(&mut data, &|a| {
+            a.toggle("ABC-1").unwrap();
+        });
+        change(&mut data, &|a| {
+            a.remove("ABC-1", Some(Duration::from_secs(60))).unwrap();
+        });
+        let replayed = replayed(serde_json::from_str(&saved).unwrap(), changes);
+
+        assert_eq!(
+            serde_json::to_value(&replayed).unwrap(),
+            serde_json::to_value(&data).unwrap()
+        );
+    }
+
+    #[test]
+    fn unchanged_state_is_not_journaled() {
+        let data = state(Some(("ABC-1", clock::now())));
+
+        assert!(Change::between(&data.snapshot(), &data, false).is_none());
+        assert!(Change::between(&data.snapshot(), &data, true).is_some());
+    }
+
+    #[test]
+    fn moved_trackers_keep_their_order() {
+        let mut data = state(None);
+        data.create_external_tracker("MEETING").unwrap();
+        let saved = serde_json::to_string(&data).unwrap();
+        let before = data.snapshot();
+        data.trackers.move_index(1, 0);
+
+        let change = Change::between(&before, &data, false).unwrap();
+        let replayed = replayed(serde_json::from_str(&saved).unwrap(), vec![change]);
+
+        assert!(replayed.trackers.keys().eq(["MEETING", "ABC-1"]));
     }
 }
@@ -0,0 +1,129 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{Datelike, Days, Local, NaiveDate, TimeZone, Weekday};
+use serde::Serialize;
+
+use crate::app_data::{AppData, Submission};
+use crate::chatops::WebhookFormat;
+use crate::clock;
+use crate::goals::GoalProgress;
+use crate::report::{format_duration, week_start_of};
+use crate::rounding::DisplayRounding;
+use crate::scheduler::Scheduler;
+
+/// How often the job checks whether the summary is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Serialize)]
+struct ApproverSummary {
+    from: NaiveDate,
+    to: NaiveDate,
+    #[serde(with = "humantime_serde")]
+    total: Duration,
+    submissions: Vec<Submission>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    approval_url: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    goals: Vec<GoalProgress>,
+}
+
+impl ApproverSummary {
+    fn title(&self) -> String {
+        format!("Worklogs from {} to {}", self.from, self.to)
+    }
+
+    fn text(&self, display: DisplayRounding) -> String {
+        let mut text = format!(
+            "{} submitted in {} submissions.",
+            format_duration(display.shown(self.total)),
+            self.submissions.len()
+        );
+        if let Some(url) = &self.approval_url {
+            text.push_str(&format!(" [Approve in Tempo]({})", url));
+        }
+        if !self.goals.is_empty() {
+            text.push_str("\n\nGoals:");
+            for goal in &self.goals {
+                text.push_str(&format!("\n- {}", goal.describe(display)));
+            }
+        }
+        text
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApproverWebhook {
+    pub url: String,
+    /// Weekday on which the summary of the current week is sent
+    pub day: Weekday,
+    pub week_start: Weekday,
+    pub approval_url: Option<String>,
+    pub format: WebhookFormat,
+    /// Rounding of the durations in the text of the summary
+    pub display: DisplayRounding,
+}
+
+impl ApproverWebhook {
+    fn summary(&self, data: &AppData, today: NaiveDate) -> ApproverSummary {
+        let from = week_start_of(today, self.week_start);
+        let since = Local
+            .from_local_datetime(&from.and_hms_opt(0, 0, 0).unwrap())
+            .earliest()
+            .unwrap_or_else(clock::local_now);
+        let submissions = data.submissions_since(since);
+        ApproverSummary {
+            from,
+            to: from + Days::new(6),
+            total: submissions.iter().map(Submission::duration).sum(),
+            submissions,
+            approval_url: self.approval_url.clone(),
+            goals: data.goal_progress(from),
+        }
+    }
+
+    async fn send(
+        &self,
+        client: &reqwest::Client,
+        summary: &ApproverSummary,
+    ) -> Result<(), reqwest::Error> {
+        let request = client.post(&self.url);
+        let request = match self
+            .format
+            .message(&summary.title(), &summary.text(self.display))
+        {
+            Some(message) => request.json(&message),
+            None => request.json(summary),
+        };
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Posts the summary of the week's submissions once on every `day`. The last sent date is only
+/// kept in memory, so a restart on that day sends it again.
+pub fn schedule_approver_summary(
+    scheduler: &Scheduler,
+    data: Arc<AppData>,
+    webhook: ApproverWebhook,
+) {
+    let client = reqwest::Client::new();
+    let last_sent: Arc<Mutex<Option<NaiveDate>>> = Arc::default();
+    scheduler.add("approver_summary", CHECK_INTERVAL, move || {
+        let (data, webhook, client) = (data.clone(), webhook.clone(), client.clone());
+        let last_sent = last_sent.clone();
+        async move {
+            let today = clock::local_now().date_naive();
+            if today.weekday() != webhook.day || *last_sent.lock().unwrap() == Some(today) {
+                return Ok("not due".to_string());
+            }
+            let summary = webhook.summary(&data, today);
+            webhook
+                .send(&client, &summary)
+                .await
+                .map_err(|e| e.to_string())?;
+            *last_sent.lock().unwrap() = Some(today);
+            Ok("sent weekly summary to approver".to_string())
+        }
+    });
+}
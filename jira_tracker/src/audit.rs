@@ -0,0 +1,164 @@
+//! Append-only log of all tracker changes in `audit.jsonl` next to the state file, see `GET /audit`
+
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use domain::TrackerEvent;
+
+use crate::clock;
+use crate::files::FileError;
+use crate::tempo_api::WorklogReceipt;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    at: DateTime<Local>,
+    #[serde(flatten)]
+    event: TrackerEvent,
+    /// Duration of the tracker after the change, or before it was removed
+    #[serde(
+        default,
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    duration: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tempo_worklog_id: Option<u64>,
+}
+
+impl AuditEntry {
+    pub fn new(
+        event: TrackerEvent,
+        duration: Option<Duration>,
+        description: Option<String>,
+    ) -> Self {
+        Self {
+            at: clock::local_now(),
+            event,
+            duration,
+            description,
+            tempo_worklog_id: None,
+        }
+    }
+
+    pub fn submitted(receipt: &WorklogReceipt) -> Self {
+        Self {
+            tempo_worklog_id: Some(receipt.tempo_worklog_id),
+            ..Self::new(
+                TrackerEvent::Submitted {
+                    key: receipt.key.clone(),
+                },
+                Some(receipt.duration),
+                None,
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    key: Option<String>,
+    /// Inclusive start, e.g. `2025-02-14T00:00:00Z`
+    from: Option<DateTime<Local>>,
+    /// Exclusive end
+    to: Option<DateTime<Local>>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.key.as_ref().is_none_or(|key| entry.event.key() == key)
+            && self.from.is_none_or(|from| entry.at >= from)
+            && self.to.is_none_or(|to| entry.at < to)
+    }
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+    /// Entries in the order of their changes, not written yet
+    pending: Mutex<Vec<AuditEntry>>,
+    /// Keeps lines of concurrent appends apart
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            pending: Mutex::new(Vec::new()),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Queues entries for [`AuditLog::write_pending`], cheap enough to be done while the state
+    /// is locked, so that they keep the order of the changes
+    pub fn record<I>(&self, entries: I)
+    where
+        I: IntoIterator<Item = AuditEntry>,
+    {
+        self.pending.lock().unwrap().extend(entries);
+    }
+
+    pub fn append<I>(&self, entries: I)
+    where
+        I: IntoIterator<Item = AuditEntry>,
+    {
+        self.record(entries);
+        self.write_pending();
+    }
+
+    /// Appends the queued entries to the file. Failures are only logged, as the changes
+    /// themselves already happened.
+    pub fn write_pending(&self) {
+        let _guard = self.lock.lock().unwrap();
+        let entries = std::mem::take(&mut *self.pending.lock().unwrap());
+        let mut lines = String::new();
+        for entry in entries {
+            lines.push_str(&serde_json::to_string(&entry).unwrap());
+            lines.push('\n');
+        }
+        if lines.is_empty() {
+            return;
+        }
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)
+            })
+            .and_then(|mut file| file.write_all(lines.as_bytes()));
+        if let Err(e) = result {
+            tracing::warn!("could not append to audit log: {}", e);
+        }
+    }
+}
+
+/// Entries matching the query, oldest first. Unreadable lines are skipped.
+pub fn read<P: AsRef<Path>>(path: P, query: &AuditQuery) -> Result<Vec<AuditEntry>, FileError> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(FileError::IO(e)),
+    };
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(FileError::IO)?;
+        match serde_json::from_str::<AuditEntry>(&line) {
+            Ok(entry) if query.matches(&entry) => entries.push(entry),
+            Ok(_) => {}
+            Err(e) => tracing::debug!("skipping audit line: {}", e),
+        }
+    }
+    Ok(entries)
+}
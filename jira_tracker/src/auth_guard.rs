@@ -0,0 +1,178 @@
+//! Protection of the token authenticated endpoints, e.g. `/chat/mattermost`, against guessing.
+//! The endpoints record each rejected token or signature with the client address, other `401`s
+//! like expired Jira credentials do not count. Clients with too many recent failures are answered
+//! with `429 Too Many Requests` on these endpoints, and with `AUTH_BAN_AFTER` banned from them for
+//! `AUTH_BAN_DURATION`.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use axum::async_trait;
+use axum::extract::{ConnectInfo, FromRef, FromRequestParts, State};
+use axum::http::header::RETRY_AFTER;
+use axum::http::request::Parts;
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::clock;
+
+/// More failures of a client within [`RATE_WINDOW`] are rejected until the oldest one expires
+const RATE_LIMIT: usize = 5;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Whether `given` is `expected`, taking the same time for every `given` of the same length, so
+/// that a token cannot be guessed byte by byte from the response times
+pub fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+#[derive(Debug, Default)]
+struct Client {
+    /// Times of the recent failures, within the rate window or the ban duration if longer
+    failures: VecDeque<SystemTime>,
+    banned_until: Option<SystemTime>,
+}
+
+impl Client {
+    fn recent(&self, window: Duration) -> impl Iterator<Item = &SystemTime> {
+        self.failures
+            .iter()
+            .filter(move |failure| clock::elapsed(**failure) < window)
+    }
+
+    /// How long the client has to wait before trying again
+    fn blocked_for(&self) -> Option<Duration> {
+        let now = clock::now();
+        if let Some(until) = self.banned_until.filter(|until| *until > now) {
+            return until.duration_since(now).ok();
+        }
+        let recent: Vec<_> = self.recent(RATE_WINDOW).collect();
+        if recent.len() < RATE_LIMIT {
+            return None;
+        }
+        Some(RATE_WINDOW.saturating_sub(clock::elapsed(*recent[0])))
+    }
+}
+
+#[derive(Debug)]
+pub struct AuthGuard {
+    /// Failures after which a client is banned, never if not given
+    ban_after: Option<usize>,
+    ban_duration: Duration,
+    clients: Mutex<HashMap<IpAddr, Client>>,
+    failures: AtomicU64,
+}
+
+impl AuthGuard {
+    pub fn new(ban_after: Option<usize>, ban_duration: Duration) -> Self {
+        Self {
+            ban_after,
+            ban_duration,
+            clients: Mutex::new(HashMap::new()),
+            failures: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of failed authentications since the start, see `GET /health`
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+
+    fn blocked_for(&self, ip: IpAddr) -> Option<Duration> {
+        self.clients.lock().unwrap().get(&ip)?.blocked_for()
+    }
+
+    fn record_failure(&self, ip: IpAddr, path: &str) {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+        let window = RATE_WINDOW.max(self.ban_duration);
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain(|_, client| {
+            client
+                .banned_until
+                .is_some_and(|until| until > clock::now())
+                || client.recent(window).next().is_some()
+        });
+        let client = clients.entry(ip).or_default();
+        client.failures.push_back(clock::now());
+        while client
+            .failures
+            .front()
+            .is_some_and(|failure| clock::elapsed(*failure) >= window)
+        {
+            client.failures.pop_front();
+        }
+        let failures = client.failures.len();
+        tracing::warn!(
+            "authentication of {} failed for {} ({} recent failures)",
+            ip,
+            path,
+            failures
+        );
+        if self
+            .ban_after
+            .is_some_and(|ban_after| failures >= ban_after)
+        {
+            tracing::warn!("banning {} for {:?}", ip, self.ban_duration);
+            client.banned_until = Some(clock::now() + self.ban_duration);
+            client.failures.clear();
+        }
+    }
+}
+
+/// Middleware of the token authenticated routes rejecting blocked clients
+pub async fn guard<B>(
+    State(guard): State<Arc<AuthGuard>>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let ip = address.ip();
+    if let Some(wait) = guard.blocked_for(ip) {
+        let retry_after = (wait.as_secs() + 1).to_string();
+        return (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, retry_after)]).into_response();
+    }
+    next.run(request).await
+}
+
+/// Extractor for the token authenticated endpoints to reject a request and count the failure
+pub struct Rejection {
+    guard: Arc<AuthGuard>,
+    ip: IpAddr,
+    path: String,
+}
+
+impl Rejection {
+    /// Counts the rejected token or signature of the client
+    pub fn unauthorized(&self) -> StatusCode {
+        self.guard.record_failure(self.ip, &self.path);
+        StatusCode::UNAUTHORIZED
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Rejection
+where
+    Arc<AuthGuard>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = <ConnectInfo<SocketAddr> as FromRequestParts<S>>::Rejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(address) =
+            ConnectInfo::<SocketAddr>::from_request_parts(parts, state).await?;
+        Ok(Self {
+            guard: Arc::from_ref(state),
+            ip: address.ip(),
+            path: parts.uri.path().to_string(),
+        })
+    }
+}
@@ -0,0 +1,134 @@
+//! Mattermost and Microsoft Teams integration. Their slash commands and outgoing webhooks apply
+//! text commands (see `commands.rs`) via `POST /chat/mattermost` and `POST /chat/teams`, and the
+//! weekly approver summary can be formatted for their incoming webhooks.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::auth_guard;
+
+/// Body of the approver webhook
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// The summary as it is
+    #[default]
+    Json,
+    Mattermost,
+    Teams,
+}
+
+impl WebhookFormat {
+    /// Message with the markdown `text` for the incoming webhook, `None` for plain JSON
+    pub fn message(&self, title: &str, text: &str) -> Option<Value> {
+        match self {
+            WebhookFormat::Json => None,
+            WebhookFormat::Mattermost => {
+                Some(json!({ "text": format!("#### {}\n{}", title, text) }))
+            }
+            WebhookFormat::Teams => Some(json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "summary": title,
+                "title": title,
+                "text": text,
+            })),
+        }
+    }
+}
+
+/// Form posted by a Mattermost slash command
+#[derive(Debug, Deserialize)]
+pub struct MattermostCommand {
+    token: String,
+    pub text: String,
+}
+
+impl MattermostCommand {
+    pub fn verify(&self, token: &str) -> bool {
+        auth_guard::tokens_match(&self.token, token)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MattermostResponse {
+    response_type: &'static str,
+    text: String,
+}
+
+impl MattermostResponse {
+    /// Only shown to the user who sent the command
+    pub fn ephemeral(text: String) -> Self {
+        Self {
+            response_type: "ephemeral",
+            text,
+        }
+    }
+}
+
+/// Message sent by a Teams outgoing webhook
+#[derive(Debug, Deserialize)]
+pub struct TeamsMessage {
+    text: String,
+}
+
+impl TeamsMessage {
+    /// Text without the mention of the webhook and without HTML
+    pub fn command(&self) -> String {
+        let mut text = self.text.clone();
+        while let (Some(start), Some(end)) = (text.find("<at>"), text.find("</at>")) {
+            if end < start {
+                break;
+            }
+            text.replace_range(start..end + "</at>".len(), "");
+        }
+        let mut command = String::new();
+        let mut in_tag = false;
+        for c in text.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                c if !in_tag => command.push(c),
+                _ => {}
+            }
+        }
+        command.replace("&nbsp;", " ").trim().to_string()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TeamsResponse {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    text: String,
+}
+
+impl TeamsResponse {
+    pub fn message(text: String) -> Self {
+        Self {
+            kind: "message",
+            text,
+        }
+    }
+}
+
+/// Checks the `Authorization: HMAC <signature>` header of a Teams outgoing webhook, the base64
+/// HMAC-SHA256 of the body keyed with the base64 security token of the webhook
+pub fn verify_teams_signature(secret: &str, authorization: Option<&str>, body: &[u8]) -> bool {
+    let Some(signature) = authorization
+        .and_then(|header| header.strip_prefix("HMAC "))
+        .and_then(|signature| STANDARD.decode(signature.trim()).ok())
+    else {
+        return false;
+    };
+    let Ok(key) = STANDARD.decode(secret) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(&key).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
@@ -0,0 +1,95 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use axum::extract::State;
+use axum::http::header::USER_AGENT;
+use axum::http::{HeaderMap, Method, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::clock;
+
+/// Header a client can send to identify itself, preferred over its user agent
+const CLIENT_HEADER: &str = "x-client";
+const UNKNOWN_CLIENT: &str = "unknown";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Mutation {
+    method: String,
+    path: String,
+    at: DateTime<Local>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientInfo {
+    name: String,
+    last_seen: DateTime<Local>,
+    requests: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_mutation: Option<Mutation>,
+}
+
+/// Last access of every client since startup
+#[derive(Debug, Default)]
+pub struct ClientRegistry {
+    clients: RwLock<HashMap<String, ClientInfo>>,
+}
+
+fn client_name(headers: &HeaderMap) -> String {
+    headers
+        .get(CLIENT_HEADER)
+        .or_else(|| headers.get(USER_AGENT))
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or(UNKNOWN_CLIENT)
+        .to_string()
+}
+
+impl ClientRegistry {
+    /// Clients ordered by their last access, most recent first
+    pub fn list(&self) -> Vec<ClientInfo> {
+        let mut clients: Vec<_> = self.clients.read().unwrap().values().cloned().collect();
+        clients.sort_by_key(|client| Reverse(client.last_seen));
+        clients
+    }
+
+    fn record(&self, name: String, method: &Method, path: &str) {
+        let now = clock::local_now();
+        let mutation = (method != Method::GET && method != Method::HEAD).then(|| {
+            tracing::info!("{} {} by {}", method, path, name);
+            Mutation {
+                method: method.to_string(),
+                path: path.to_string(),
+                at: now,
+            }
+        });
+        let mut clients = self.clients.write().unwrap();
+        let client = clients.entry(name.clone()).or_insert(ClientInfo {
+            name,
+            last_seen: now,
+            requests: 0,
+            last_mutation: None,
+        });
+        client.last_seen = now;
+        client.requests += 1;
+        if mutation.is_some() {
+            client.last_mutation = mutation;
+        }
+    }
+}
+
+/// Middleware recording the client of every request
+pub async fn track<B>(
+    State(clients): State<Arc<ClientRegistry>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    clients.record(
+        client_name(request.headers()),
+        request.method(),
+        request.uri().path(),
+    );
+    next.run(request).await
+}
@@ -0,0 +1,38 @@
+//! Source of the current time. With the `dev` feature the clock can be advanced, see
+//! `POST /debug/advance-clock`.
+
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local};
+
+#[cfg(feature = "dev")]
+static OFFSET_SECONDS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+#[cfg(feature = "dev")]
+fn offset() -> Duration {
+    Duration::from_secs(OFFSET_SECONDS.load(std::sync::atomic::Ordering::Relaxed))
+}
+
+#[cfg(not(feature = "dev"))]
+fn offset() -> Duration {
+    Duration::ZERO
+}
+
+/// Shifts the clock forward by `duration`, truncated to whole seconds
+#[cfg(feature = "dev")]
+pub fn advance(duration: Duration) {
+    OFFSET_SECONDS.fetch_add(duration.as_secs(), std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn now() -> SystemTime {
+    SystemTime::now() + offset()
+}
+
+pub fn local_now() -> DateTime<Local> {
+    now().into()
+}
+
+/// Time passed since `earlier`, zero if it lies in the future
+pub fn elapsed(earlier: SystemTime) -> Duration {
+    now().duration_since(earlier).unwrap_or_default()
+}
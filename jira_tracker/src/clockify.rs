@@ -0,0 +1,62 @@
+//! Clockify JSON for `POST /import/clockify`, the detailed report with its `timeentries` or the
+//! plain list of time entries of the API
+
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+
+use crate::time_import::{ExternalEntry, TimeImportError};
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ClockifyExport {
+    Report {
+        #[serde(rename = "timeentries", alias = "timeEntries")]
+        time_entries: Vec<ClockifyEntry>,
+    },
+    Entries(Vec<ClockifyEntry>),
+}
+
+/// Time entry, other fields are ignored
+#[derive(Debug, Deserialize)]
+struct ClockifyEntry {
+    #[serde(default)]
+    description: String,
+    /// Searched for the issue key if the description has none
+    #[serde(rename = "taskName", default)]
+    task_name: Option<String>,
+    #[serde(rename = "timeInterval")]
+    time_interval: TimeInterval,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimeInterval {
+    start: DateTime<Local>,
+    /// Not given while the timer is running
+    end: Option<DateTime<Local>>,
+}
+
+impl ClockifyEntry {
+    fn entry(&self) -> Result<ExternalEntry, String> {
+        let Some(end) = self.time_interval.end else {
+            return Err(format!("'{}' is still running", self.description));
+        };
+        let start = self.time_interval.start;
+        ExternalEntry::parse(&self.description, start, end).or_else(|reason| {
+            self.task_name
+                .as_ref()
+                .map(|task| format!("{} {}", task, self.description))
+                .and_then(|description| ExternalEntry::parse(&description, start, end).ok())
+                .ok_or(reason)
+        })
+    }
+}
+
+pub fn parse(json: &str) -> Result<Vec<Result<ExternalEntry, String>>, TimeImportError> {
+    let export: ClockifyExport =
+        serde_json::from_str(json).map_err(|e| TimeImportError::Format(e.to_string()))?;
+    let entries = match export {
+        ClockifyExport::Report { time_entries } => time_entries,
+        ClockifyExport::Entries(entries) => entries,
+    };
+    Ok(entries.iter().map(ClockifyEntry::entry).collect())
+}
@@ -0,0 +1,140 @@
+//! Text commands like `track ABC-1 30m meeting with client`, shared by the mail gateway and the chat
+//! integrations
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use domain::{Detail, TrackerInformation};
+
+use crate::app_data::{AppData, TrackerError};
+use crate::issue_cache::IssueCache;
+use crate::jira_api::JiraApi;
+use crate::report::format_duration;
+use crate::rounding::DisplayRounding;
+
+const USAGE: &str = "unknown command, use e.g. \"track ABC-1 30m meeting\" or \"pause\"";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    /// Adds the duration to the tracker if given, otherwise starts it. Creates missing trackers.
+    Track {
+        key: String,
+        duration: Option<Duration>,
+        description: Option<String>,
+    },
+    Pause,
+}
+
+impl Command {
+    fn parse(text: &str) -> Option<Self> {
+        let mut words = text.split_whitespace();
+        match words.next()?.to_lowercase().as_str() {
+            "track" => {
+                // uppercased after resolving aliases, which may be lowercase
+                let key = words.next()?.to_string();
+                let mut rest: Vec<&str> = words.collect();
+                let duration = rest
+                    .first()
+                    .and_then(|word| humantime::parse_duration(word).ok());
+                if duration.is_some() {
+                    rest.remove(0);
+                }
+                let description = Some(rest.join(" ")).filter(|d| !d.is_empty());
+                Some(Command::Track {
+                    key,
+                    duration,
+                    description,
+                })
+            }
+            "pause" => Some(Command::Pause),
+            _ => None,
+        }
+    }
+}
+
+fn describe(tracker: &TrackerInformation, display: DisplayRounding) -> String {
+    format!(
+        "{} is {} at {}",
+        tracker.key,
+        if tracker.running { "running" } else { "paused" },
+        format_duration(display.shown(tracker.duration))
+    )
+}
+
+pub fn describe_error(key: &str, error: TrackerError) -> String {
+    match error {
+        TrackerError::NotFoundError => format!("{} was not found", key),
+        TrackerError::OccupiedError => format!("{} has a tracker already", key),
+        TrackerError::UnknownProjectError(project) => {
+            format!("project {} does not exist", project)
+        }
+        TrackerError::KeyFormatError => format!("{} is not an issue key", key),
+        TrackerError::IssueNotFoundError(detail) => {
+            format!("{} does not exist in Jira: {}", key, detail)
+        }
+        TrackerError::UpstreamError(error) => format!("{} could not be looked up: {}", key, error),
+        e => format!("{} could not be changed: {:?}", key, e),
+    }
+}
+
+/// Everything a command may need to apply itself
+pub struct CommandContext<'a> {
+    pub data: &'a AppData,
+    pub jira: &'a JiraApi,
+    pub cache: &'a IssueCache,
+    pub aliases: &'a HashMap<String, String>,
+    pub display: DisplayRounding,
+}
+
+impl CommandContext<'_> {
+    /// Adds the duration to the tracker of `key`, which may be an alias, if given, otherwise starts
+    /// it. Creates missing trackers.
+    pub async fn track(
+        &self,
+        key: &str,
+        duration: Option<Duration>,
+        description: Option<String>,
+    ) -> Result<TrackerInformation, TrackerError> {
+        let key = self
+            .aliases
+            .get(key)
+            .map_or(key, String::as_str)
+            .to_uppercase();
+        let key = key.as_str();
+        if self.data.get_tracker(key, Detail::Summary).is_err() {
+            let issue = self
+                .cache
+                .get_or_fetch(self.jira, key)
+                .await
+                .map_err(TrackerError::issue_lookup)?;
+            self.data
+                .create_tracker(key, &issue.id, Some((&issue).into()))?;
+        }
+        if description.is_some() {
+            self.data.set_description(key, description)?;
+        }
+        match duration {
+            Some(duration) => self.data.adjust_positive_duration(key, duration),
+            None => self.data.start(key),
+        }
+    }
+
+    /// Applies the command in `text`, returning a human readable result
+    pub async fn apply(&self, text: &str) -> String {
+        match Command::parse(text) {
+            Some(Command::Track {
+                key,
+                duration,
+                description,
+            }) => self
+                .track(&key, duration, description)
+                .await
+                .map_or_else(|e| describe_error(&key, e), |t| describe(&t, self.display)),
+            Some(Command::Pause) => {
+                self.data.pause();
+                "paused tracking".to_string()
+            }
+            None => USAGE.to_string(),
+        }
+    }
+}
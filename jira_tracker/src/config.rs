@@ -1,19 +1,55 @@
-use std::error::Error;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
 
-use axum::http::StatusCode;
-use axum::response::{IntoResponse, Response};
-use figment::providers::Env;
+use chrono::{NaiveTime, Weekday};
+use figment::providers::{Env, Serialized};
 use figment::Figment;
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
 use tower_http::classify::{ServerErrorsAsFailures, SharedClassifier};
 use tower_http::trace::TraceLayer;
 use tracing::Level;
 use tracing_subscriber::filter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
+use crate::anomalies::Thresholds;
+use crate::approver::ApproverWebhook;
+use crate::chatops::WebhookFormat;
+use crate::day::PrunePolicy;
+use crate::diagnostics::{self, redacted};
+use crate::files;
+use crate::freeze::FreezeWindow;
+use crate::locale::Locale;
+use crate::mail::MailGateway;
+use crate::rounding::{DisplayRounding, Rounding, RoundingPolicy};
+use crate::routing::SubmitRules;
+use crate::storage::{StateFormat, StorageBackend};
+
 const DEFAULT_PORT: fn() -> u16 = || 8080;
+const DEFAULT_PREFETCH_INTERVAL: fn() -> Duration = || Duration::from_secs(60 * 60);
+const DEFAULT_PREFETCH_DELAY: fn() -> Duration = || Duration::from_millis(500);
+const DEFAULT_RESUME_WITHIN: fn() -> Duration = || Duration::from_secs(60 * 60);
+const DEFAULT_ISSUE_CACHE_TTL: fn() -> Duration = || Duration::from_secs(2 * 60 * 60);
+const DEFAULT_PROJECT_CACHE_TTL: fn() -> Duration = || Duration::from_secs(24 * 60 * 60);
+const DEFAULT_WORKING_HOURS: fn() -> Duration = || Duration::from_secs(8 * 60 * 60);
+const DEFAULT_SPRINT_FIELD: fn() -> String = || "customfield_10020".to_string();
+const DEFAULT_APPROVER_DAY: fn() -> Weekday = || Weekday::Fri;
+const DEFAULT_ANOMALY_MAX_ISSUE_DURATION: fn() -> Duration = || Duration::from_secs(6 * 60 * 60);
+const DEFAULT_ANOMALY_MAX_SWITCHES: fn() -> usize = || 10;
+const DEFAULT_ANOMALY_MAX_SESSION: fn() -> Duration = || Duration::from_secs(3 * 60 * 60);
+const DEFAULT_ANOMALY_LATE_HOUR: fn() -> u32 = || 20;
+const DEFAULT_IDEMPOTENCY_WINDOW: fn() -> Duration = || Duration::from_secs(24 * 60 * 60);
+const DEFAULT_MAIL_POLL_INTERVAL: fn() -> Duration = || Duration::from_secs(60);
+const DEFAULT_WEEK_ARCHIVE_INTERVAL: fn() -> Duration = || Duration::from_secs(6 * 60 * 60);
+const DEFAULT_TEMPO_ACCOUNT_ATTRIBUTE: fn() -> String = || "_Account_".to_string();
+const DEFAULT_PAIRING_ATTRIBUTE: fn() -> String = || "_Pairing_".to_string();
+const DEFAULT_AUTH_BAN_DURATION: fn() -> Duration = || Duration::from_secs(15 * 60);
+const DEFAULT_UPDATE_CHECK_INTERVAL: fn() -> Duration = || Duration::from_secs(24 * 60 * 60);
 
 fn deserialize_path<'de, D>(deserializer: D) -> Result<PathBuf, D::Error>
 where
@@ -23,45 +59,486 @@ where
     Ok(PathBuf::from(shellexpand::full(&string).unwrap().as_ref()))
 }
 
-#[derive(Debug, Deserialize)]
+/// Comma separated list, e.g. `ABC-1,ABC-2`
+fn deserialize_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let string = String::deserialize(deserializer)?;
+    Ok(string
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Comma separated assignments, e.g. `standup=ABC-1,review=ABC-2`
+fn deserialize_map<'de, D>(deserializer: D) -> Result<HashMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let string = String::deserialize(deserializer)?;
+    string
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|pair| {
+            pair.split_once('=')
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .ok_or_else(|| serde::de::Error::custom(format!("invalid assignment '{}'", pair)))
+        })
+        .collect()
+}
+
+/// Whether the tracker running before a suspension is restarted on resume
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResumePolicy {
+    Never,
+    Always,
+    /// Only if the suspension did not last longer than `RESUME_WITHIN`
+    #[default]
+    Recent,
+}
+
+/// How time on trackers paired with a colleague is submitted
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PairingMode {
+    /// Submit the full duration with the colleague in the `PAIRING_ATTRIBUTE` work attribute
+    #[default]
+    Attribute,
+    /// Submit half of the duration for each of both accounts
+    Split,
+}
+
+/// How trackers are turned into Tempo worklogs on submission
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubmissionGrouping {
+    /// One worklog per tracker
+    #[default]
+    Tracker,
+    /// One worklog per tracker and day it ran on
+    Day,
+    /// One worklog per session of a tracker, from start to pause
+    Session,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AppConfig {
+    /// Credentials, not needed in the sandbox
+    #[serde(default, serialize_with = "redacted")]
     pub jira_email: String,
+    #[serde(default, serialize_with = "redacted")]
     pub jira_api_token: String,
+    #[serde(default, serialize_with = "redacted")]
     pub tempo_api_token: String,
+    /// Fakes Jira and Tempo, see `sandbox.rs`
+    #[serde(default)]
+    pub sandbox: bool,
+    /// Resolved from the Jira user if not set
+    pub jira_account_id: Option<String>,
     #[serde(default = "DEFAULT_PORT")]
     pub tracker_port: u16,
     #[serde(deserialize_with = "deserialize_path")]
     pub json_file: PathBuf,
+    #[serde(default)]
+    pub locale: Locale,
+    /// Overrides the first day of the week implied by the locale
+    pub week_start: Option<Weekday>,
+    #[serde(default, deserialize_with = "deserialize_list")]
+    pub favorites: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_map")]
+    pub aliases: HashMap<String, String>,
+    #[serde(default = "DEFAULT_PREFETCH_INTERVAL", with = "humantime_serde")]
+    pub prefetch_interval: Duration,
+    /// Pause between two consecutive prefetch requests to not flood Jira
+    #[serde(default = "DEFAULT_PREFETCH_DELAY", with = "humantime_serde")]
+    pub prefetch_delay: Duration,
+    /// How long looked up issues are cached, longer than `prefetch_interval` to keep prefetched ones
+    #[serde(default = "DEFAULT_ISSUE_CACHE_TTL", with = "humantime_serde")]
+    pub issue_cache_ttl: Duration,
+    #[serde(default = "DEFAULT_PROJECT_CACHE_TTL", with = "humantime_serde")]
+    pub project_cache_ttl: Duration,
+    #[serde(default)]
+    pub resume_policy: ResumePolicy,
+    #[serde(default = "DEFAULT_RESUME_WITHIN", with = "humantime_serde")]
+    pub resume_within: Duration,
+    /// Allows submitting worklogs on behalf of other Jira accounts
+    #[serde(default)]
+    pub allow_delegation: bool,
+    /// Requires `?confirm=<today's date>` on `DELETE /trackers` and `POST /submit`
+    #[serde(default)]
+    pub confirm_destructive: bool,
+    /// Number of trackers above which listing suggests trackers to archive
+    pub tracker_soft_limit: Option<usize>,
+    /// Periodically looks for a new release on GitHub
+    #[serde(default)]
+    pub update_check: bool,
+    #[serde(default = "DEFAULT_UPDATE_CHECK_INTERVAL", with = "humantime_serde")]
+    pub update_check_interval: Duration,
+    /// Instances of teammates shown in `GET /team`, e.g. `alice=http://alice:8080`
+    #[serde(default, deserialize_with = "deserialize_map")]
+    pub team_peers: HashMap<String, String>,
+    /// Bearer tokens for the instances in `team_peers`, by name
+    #[serde(
+        default,
+        deserialize_with = "deserialize_map",
+        serialize_with = "redacted"
+    )]
+    pub team_tokens: HashMap<String, String>,
+    /// Working time per weekday, used for the sprint capacity
+    #[serde(default = "DEFAULT_WORKING_HOURS", with = "humantime_serde")]
+    pub working_hours: Duration,
+    /// Jira field containing the sprints of an issue
+    #[serde(default = "DEFAULT_SPRINT_FIELD")]
+    pub sprint_field: String,
+    /// Generates missing worklog descriptions from commits and own Jira comments on submit
+    #[serde(default)]
+    pub auto_description: bool,
+    /// Receives the weekly summary of submitted worklogs for the approver
+    #[serde(serialize_with = "redacted")]
+    pub approver_webhook: Option<String>,
+    #[serde(default = "DEFAULT_APPROVER_DAY")]
+    pub approver_day: Weekday,
+    /// Link to the approval in Tempo, included in the weekly summary
+    pub approval_url: Option<String>,
+    #[serde(default)]
+    pub approver_webhook_format: WebhookFormat,
+    /// Daily window in which mutating requests are rejected, e.g. `17:45-18:00`
+    pub freeze_window: Option<FreezeWindow>,
+    #[serde(
+        default = "DEFAULT_ANOMALY_MAX_ISSUE_DURATION",
+        with = "humantime_serde"
+    )]
+    pub anomaly_max_issue_duration: Duration,
+    #[serde(default = "DEFAULT_ANOMALY_MAX_SWITCHES")]
+    pub anomaly_max_switches: usize,
+    /// Time tracked without a break after which a session is considered unusually long
+    #[serde(default = "DEFAULT_ANOMALY_MAX_SESSION", with = "humantime_serde")]
+    pub anomaly_max_session: Duration,
+    /// Hour of the day after which tracking is considered late
+    #[serde(default = "DEFAULT_ANOMALY_LATE_HOUR")]
+    pub anomaly_late_hour: u32,
+    #[serde(default)]
+    pub pairing_mode: PairingMode,
+    #[serde(default)]
+    pub submission_grouping: SubmissionGrouping,
+    /// Where the trackers of projects are submitted to, see `routing.rs`
+    #[serde(default)]
+    pub submit_rules: SubmitRules,
+    /// Key of the Tempo work attribute holding the account id of the pairing colleague
+    #[serde(default = "DEFAULT_PAIRING_ATTRIBUTE")]
+    pub pairing_attribute: String,
+    /// Key of the Tempo work attribute holding the account of a worklog
+    #[serde(default = "DEFAULT_TEMPO_ACCOUNT_ATTRIBUTE")]
+    pub tempo_account_attribute: String,
+    /// How long responses are replayed for requests with the same `Idempotency-Key`
+    #[serde(default = "DEFAULT_IDEMPOTENCY_WINDOW", with = "humantime_serde")]
+    pub idempotency_window: Duration,
+    #[serde(default)]
+    pub storage: StorageBackend,
+    /// Encoding of `json_file`, binary ones being smaller and faster to write
+    #[serde(default)]
+    pub state_format: StateFormat,
+    /// Base64 encoded 32 byte key encrypting `json_file` at rest
+    #[serde(serialize_with = "redacted")]
+    pub state_encryption_key: Option<String>,
+    /// How long activity from `POST /heartbeat/activity` is kept, not recorded at all if missing
+    #[serde(default, with = "humantime_serde")]
+    pub activity_retention: Option<Duration>,
+    /// Duration of the single writer lease between instances sharing the SQLite database
+    #[serde(default, with = "humantime_serde")]
+    pub writer_lease: Option<Duration>,
+    /// Trackers created when opening the day, unless they exist
+    #[serde(default, deserialize_with = "deserialize_list")]
+    pub recurring_trackers: Vec<String>,
+    #[serde(default)]
+    pub prune_policy: PrunePolicy,
+    /// Tracked time is rounded to multiples of this when closing the day and submitting
+    #[serde(default, with = "humantime_serde")]
+    pub rounding_step: Option<Duration>,
+    #[serde(default)]
+    pub rounding_policy: RoundingPolicy,
+    /// Durations are shown rounded to multiples of this, whole minutes otherwise
+    #[serde(default, with = "humantime_serde")]
+    pub display_rounding_step: Option<Duration>,
+    /// Starts read-only instead of failing if another instance owns the state
+    #[serde(default)]
+    pub read_only_if_locked: bool,
+    /// Never changes the state, e.g. for a dashboard on a state another instance writes
+    #[serde(default)]
+    pub read_only: bool,
+    /// Appends every change to `audit.jsonl`, see `GET /audit`
+    #[serde(default)]
+    pub audit_log: bool,
+    /// Appends every change to `state.wal` before answering it, see `write_ahead.rs`
+    #[serde(default)]
+    pub state_journal: bool,
+    /// Deleted trackers can be restored for this long before they are purged
+    #[serde(default, with = "humantime_serde")]
+    pub purge_delay: Option<Duration>,
+    /// Persists the time of the running tracker this often, also without any change
+    #[serde(default, with = "humantime_serde")]
+    pub checkpoint_interval: Option<Duration>,
+    /// Compacts the state this often, see `POST /admin/compact`
+    #[serde(default, with = "humantime_serde")]
+    pub compact_interval: Option<Duration>,
+    /// Archived and deleted trackers older than this are dropped on compaction
+    #[serde(default, with = "humantime_serde")]
+    pub compact_retention: Option<Duration>,
+    /// Directory or WebDAV URL receiving the CSV archive of each week, see `week_archive.rs`
+    #[serde(serialize_with = "redacted")]
+    pub week_archive: Option<String>,
+    #[serde(default = "DEFAULT_WEEK_ARCHIVE_INTERVAL", with = "humantime_serde")]
+    pub week_archive_interval: Duration,
+    /// `host:port` of the IMAP server polled for tracker commands, e.g. `imap.example.com:993`
+    pub mail_imap_server: Option<String>,
+    /// `host:port` of the SMTP server for replies to commands, e.g. `smtp.example.com:465`
+    pub mail_smtp_server: Option<String>,
+    #[serde(serialize_with = "redacted")]
+    pub mail_username: Option<String>,
+    #[serde(serialize_with = "redacted")]
+    pub mail_password: Option<String>,
+    /// Addresses whose mails are applied as commands
+    #[serde(default, deserialize_with = "deserialize_list")]
+    pub mail_senders: Vec<String>,
+    /// First word of the subject of each mail command, the gateway is off without it
+    #[serde(serialize_with = "redacted")]
+    pub mail_secret: Option<String>,
+    #[serde(default = "DEFAULT_MAIL_POLL_INTERVAL", with = "humantime_serde")]
+    pub mail_poll_interval: Duration,
+    /// Failed authentications within `auth_ban_duration` after which a client is banned
+    pub auth_ban_after: Option<usize>,
+    #[serde(default = "DEFAULT_AUTH_BAN_DURATION", with = "humantime_serde")]
+    pub auth_ban_duration: Duration,
+    /// Token of the Mattermost slash command posting to `/chat/mattermost`
+    #[serde(serialize_with = "redacted")]
+    pub mattermost_token: Option<String>,
+    /// Base64 security token of the Teams outgoing webhook posting to `/chat/teams`
+    #[serde(serialize_with = "redacted")]
+    pub teams_secret: Option<String>,
+    /// Secret of the Jira webhook posting to `/webhooks/jira`
+    #[serde(serialize_with = "redacted")]
+    pub jira_webhook_secret: Option<String>,
+    /// Further independent tracker sets, e.g. `sideproject`, see [`AppConfig::profile`]
+    #[serde(default, deserialize_with = "deserialize_list")]
+    pub profiles: Vec<String>,
+    /// Name of this profile, `None` for the main one
+    #[serde(skip)]
+    pub profile_name: Option<String>,
 }
 
 impl AppConfig {
+    /// The state file defaults to a platform specific location
     pub fn new() -> Self {
-        let figment = Figment::from(Env::raw());
-        figment.extract().unwrap()
+        let mut figment = Figment::new();
+        if let Some(path) = files::default_state_file() {
+            figment = figment.merge(Serialized::default("json_file", path));
+        }
+        figment.merge(Env::raw()).extract().unwrap()
     }
-}
 
-pub struct LogError(Box<dyn Error>);
+    /// Configuration of the profile `name`, taken from the variables prefixed with its uppercase
+    /// name, e.g. `SIDEPROJECT_JIRA_API_TOKEN`, falling back to the unprefixed ones, except for
+    /// `APPROVER_WEBHOOK` and `MAIL_IMAP_SERVER`. Its state and all files next to it are kept in
+    /// `profiles/<name>` next to the state file.
+    pub fn profile(&self, name: &str) -> Self {
+        let directory = self.json_file.with_file_name("profiles").join(name);
+        let file_name = self.json_file.file_name().unwrap_or("state.json".as_ref());
+        let own = Env::prefixed(&format!("{}_", name.to_uppercase()));
+        let config: Self = Figment::new()
+            .merge(Serialized::default("json_file", directory.join(file_name)))
+            .merge(Env::raw().ignore(&["json_file", "profiles"]))
+            .merge(own.clone())
+            .extract()
+            .unwrap();
+        // jobs acting on the mailbox or the approver are only inherited by the main profile,
+        // otherwise each profile would apply the same mails and send the same summaries
+        let own = Figment::from(own);
+        let set = |key: &str| own.find_value(key).is_ok();
+        Self {
+            profile_name: Some(name.to_string()),
+            approver_webhook: config.approver_webhook.filter(|_| set("approver_webhook")),
+            mail_imap_server: config.mail_imap_server.filter(|_| set("mail_imap_server")),
+            ..config
+        }
+    }
 
-impl<E> From<E> for LogError
-where
-    E: Error + 'static,
-{
-    fn from(value: E) -> Self {
-        LogError(Box::new(value))
+    /// Settings that cannot work, e.g. missing credentials outside of the sandbox, which would
+    /// only show up as failing requests later, or an encryption key the storage would ignore
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.sandbox {
+            let credentials = [
+                ("JIRA_EMAIL", &self.jira_email),
+                ("JIRA_API_TOKEN", &self.jira_api_token),
+                ("TEMPO_API_TOKEN", &self.tempo_api_token),
+            ];
+            let missing: Vec<&str> = credentials
+                .into_iter()
+                .filter(|(_, value)| value.trim().is_empty())
+                .map(|(name, _)| name)
+                .collect();
+            if !missing.is_empty() {
+                let profile = match &self.profile_name {
+                    Some(name) => format!(" for profile {}", name),
+                    None => String::new(),
+                };
+                return Err(format!(
+                    "{} must be set{} unless SANDBOX",
+                    missing.join(", "),
+                    profile
+                ));
+            }
+        }
+        if self.anomaly_late_hour > 23 {
+            return Err("ANOMALY_LATE_HOUR must be an hour from 0 to 23".to_string());
+        }
+        if self.writer_lease.is_some() && self.storage != StorageBackend::Sqlite {
+            return Err("WRITER_LEASE requires STORAGE=sqlite".to_string());
+        }
+        // instances sharing the state would replay the changes of each other
+        if self.state_journal && self.writer_lease.is_some() {
+            return Err("STATE_JOURNAL cannot be used with WRITER_LEASE".to_string());
+        }
+        if let Some(key) = &self.state_encryption_key {
+            if files::FileKey::from_base64(key).is_none() {
+                return Err("STATE_ENCRYPTION_KEY must be 32 base64 encoded bytes".to_string());
+            }
+            // the database is not encrypted, it must not hold a state meant to be encrypted
+            if self.storage == StorageBackend::Sqlite {
+                return Err("STATE_ENCRYPTION_KEY cannot be used with STORAGE=sqlite".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn week_start(&self) -> Weekday {
+        self.week_start.unwrap_or_else(|| self.locale.week_start())
+    }
+
+    pub fn anomaly_thresholds(&self) -> Thresholds {
+        Thresholds {
+            max_issue_duration: self.anomaly_max_issue_duration,
+            max_switches: self.anomaly_max_switches,
+            max_session: self.anomaly_max_session,
+            // checked in `validate`
+            late_hour: NaiveTime::from_hms_opt(self.anomaly_late_hour, 0, 0).unwrap(),
+        }
+    }
+
+    /// Weekly YAML journals, next to the state file
+    pub fn journal_directory(&self) -> PathBuf {
+        self.json_file.with_file_name("journal")
+    }
+
+    /// Last runs of scheduled jobs, next to the state file
+    pub fn jobs_file(&self) -> PathBuf {
+        self.json_file.with_file_name("jobs.json")
+    }
+
+    /// Advisory lock of the instance owning the state, next to the state file
+    pub fn lock_file(&self) -> PathBuf {
+        self.json_file.with_file_name("jira_tracker.lock")
+    }
+
+    /// Log of all changes, next to the state file
+    pub fn audit_file(&self) -> PathBuf {
+        self.json_file.with_file_name("audit.jsonl")
+    }
+
+    /// Changes not saved yet, next to the state file
+    pub fn wal_file(&self) -> PathBuf {
+        self.json_file.with_file_name("state.wal")
+    }
+
+    /// Worklogs submitted in the sandbox, next to the state file
+    pub fn sandbox_file(&self) -> PathBuf {
+        self.json_file.with_file_name("sandbox.jsonl")
+    }
+
+    /// Database of the SQLite storage backend, next to the state file
+    pub fn sqlite_file(&self) -> PathBuf {
+        self.json_file.with_file_name("state.sqlite")
+    }
+
+    pub fn approver_webhook(&self) -> Option<ApproverWebhook> {
+        self.approver_webhook.as_ref().map(|url| ApproverWebhook {
+            url: url.clone(),
+            day: self.approver_day,
+            week_start: self.week_start(),
+            approval_url: self.approval_url.clone(),
+            format: self.approver_webhook_format,
+            display: self.display_rounding(),
+        })
+    }
+
+    pub fn rounding(&self) -> Rounding {
+        Rounding {
+            step: self.rounding_step,
+            policy: self.rounding_policy,
+        }
+    }
+
+    pub fn display_rounding(&self) -> DisplayRounding {
+        DisplayRounding {
+            step: self.display_rounding_step,
+        }
+    }
+
+    pub fn mail_gateway(&self) -> Option<MailGateway> {
+        Some(MailGateway {
+            imap_server: self.mail_imap_server.clone()?,
+            smtp_server: self.mail_smtp_server.clone(),
+            username: self.mail_username.clone()?,
+            password: self.mail_password.clone()?,
+            senders: self.mail_senders.clone(),
+            secret: self.mail_secret.clone()?,
+            aliases: self.aliases.clone(),
+            display: self.display_rounding(),
+            poll_interval: self.mail_poll_interval,
+        })
+    }
+
+    pub fn resolve_alias<'a>(&'a self, key: &'a str) -> &'a str {
+        self.aliases.get(key).map_or(key, String::as_str)
+    }
+
+    /// Favorites and alias targets, without duplicates
+    pub fn prefetch_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.favorites.clone();
+        for key in self.aliases.values() {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        keys
     }
 }
 
-impl IntoResponse for LogError {
-    fn into_response(self) -> Response {
-        let LogError(error) = self;
-        eprintln!("Internal Server Error: {}", error);
-        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+/// Services log without colors. On Windows they have no console at all and log into a file
+/// next to the default state file.
+fn log_writer(service: bool) -> BoxMakeWriter {
+    if service && cfg!(windows) {
+        if let Some(path) =
+            files::default_state_file().map(|p| p.with_file_name("jira_tracker.log"))
+        {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .unwrap();
+            return BoxMakeWriter::new(Mutex::new(file));
+        }
     }
+    BoxMakeWriter::new(io::stdout)
 }
 
 #[must_use]
-pub fn setup_logging() -> TraceLayer<SharedClassifier<ServerErrorsAsFailures>> {
+pub fn setup_logging(service: bool) -> TraceLayer<SharedClassifier<ServerErrorsAsFailures>> {
     let targets = filter::Targets::new()
         .with_target("tower_http::trace::on_request", Level::DEBUG)
         .with_target("tower_http::trace::make_span", Level::DEBUG)
@@ -69,7 +546,12 @@ pub fn setup_logging() -> TraceLayer<SharedClassifier<ServerErrorsAsFailures>> {
         .with_default(Level::INFO);
 
     tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer())
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(!service)
+                .with_writer(log_writer(service)),
+        )
+        .with(diagnostics::ErrorCapture)
         .with(targets)
         .init();
 
@@ -0,0 +1,183 @@
+//! Opening the day with `POST /day/open`: pruning stale trackers, creating recurring ones and
+//! warming the issue cache. Closing it with `POST /day/close`: pausing, rounding, reviewing and
+//! optionally submitting the trackers, keeping a summary of the day and rendering it for the
+//! standup. `GET /days/:date` reports the time tracked on any day from the ledger of the state.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use domain::TrackerInformation;
+
+use crate::app_data::Submission;
+use crate::clock;
+use crate::report::format_duration;
+use crate::review::SubmitReview;
+use crate::rounding;
+use crate::rounding::DisplayRounding;
+
+/// Which trackers of previous days are deleted when opening the day
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrunePolicy {
+    #[default]
+    None,
+    /// Trackers without any time
+    Empty,
+    /// Trackers without any time, and external trackers with time are archived, as they are never
+    /// submitted
+    Archive,
+}
+
+/// Response of `POST /day/open`
+#[derive(Debug, Serialize)]
+pub struct OpenedDay {
+    /// Keys of the deleted stale trackers
+    pub pruned: Vec<String>,
+    /// Keys of the external trackers that were archived
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub archived: Vec<String>,
+    /// Keys of the recurring trackers that were created
+    pub created: Vec<String>,
+    /// Number of favorite issues loaded into the cache
+    pub prefetched: usize,
+    /// Trackers of previous days with time that was not submitted yet
+    pub unsubmitted: Vec<TrackerInformation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DayEntry {
+    key: String,
+    /// Summary of the Jira issue
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(with = "humantime_serde")]
+    duration: Duration,
+}
+
+/// What was done on a day, as kept in the state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaySummary {
+    date: NaiveDate,
+    closed_at: DateTime<Local>,
+    #[serde(with = "humantime_serde")]
+    total: Duration,
+    entries: Vec<DayEntry>,
+    submitted: bool,
+}
+
+impl DaySummary {
+    /// Summary of the trackers started today, with full details
+    pub fn new(trackers: &[TrackerInformation], submitted: bool) -> Self {
+        let closed_at = clock::local_now();
+        let date = closed_at.date_naive();
+        let entries: Vec<DayEntry> = trackers
+            .iter()
+            .filter(|tracker| tracker.start_time.date_naive() == date)
+            .map(|tracker| DayEntry {
+                key: tracker.key.clone(),
+                summary: tracker.details.as_ref().map(|d| d.summary.clone()),
+                description: tracker.description.clone(),
+                duration: tracker.duration,
+            })
+            .collect();
+        Self {
+            date,
+            closed_at,
+            total: entries.iter().map(|entry| entry.duration).sum(),
+            entries,
+            submitted,
+        }
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    /// Markdown list of the day for the standup
+    pub fn standup(&self, display: DisplayRounding) -> String {
+        let shown: Duration = self
+            .entries
+            .iter()
+            .map(|entry| display.shown(entry.duration))
+            .sum();
+        let mut out = format!("### {} ({})\n\n", self.date, format_duration(shown));
+        for entry in &self.entries {
+            write!(out, "- **{}**", entry.key).unwrap();
+            if let Some(summary) = &entry.summary {
+                write!(out, " {}", summary).unwrap();
+            }
+            if let Some(description) = &entry.description {
+                write!(out, ": {}", description).unwrap();
+            }
+            writeln!(out, " ({})", format_duration(display.shown(entry.duration))).unwrap();
+        }
+        out
+    }
+}
+
+/// Response of `POST /day/close`
+#[derive(Debug, Serialize)]
+pub struct ClosedDay {
+    pub review: SubmitReview,
+    /// Keys of the trackers whose duration was rounded
+    pub rounded: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submission: Option<Submission>,
+    pub summary: DaySummary,
+    pub standup: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DayTotal {
+    key: String,
+    #[serde(with = "humantime_serde")]
+    duration: Duration,
+}
+
+/// Response of `GET /days/:date`, what was tracked on the day as kept in the ledger
+#[derive(Debug, Serialize)]
+pub struct DayTotals {
+    date: NaiveDate,
+    #[serde(with = "humantime_serde")]
+    total: Duration,
+    entries: Vec<DayTotal>,
+    /// Required working time of the day, see `schedule.rs`
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    required: Option<Duration>,
+    /// Gap between the total and the required working time
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    missing: Option<Duration>,
+}
+
+impl DayTotals {
+    pub fn new(date: NaiveDate, totals: Vec<(String, Duration)>) -> Self {
+        let entries: Vec<DayTotal> = totals
+            .into_iter()
+            .filter(|(_, duration)| !duration.is_zero())
+            .map(|(key, duration)| DayTotal {
+                key,
+                duration: rounding::seconds(duration),
+            })
+            .collect();
+        Self {
+            date,
+            total: entries.iter().map(|entry| entry.duration).sum(),
+            entries,
+            required: None,
+            missing: None,
+        }
+    }
+
+    pub fn with_required(self, required: Duration) -> Self {
+        Self {
+            required: Some(required),
+            missing: Some(required.saturating_sub(self.total)),
+            ..self
+        }
+    }
+}
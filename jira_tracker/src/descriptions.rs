@@ -0,0 +1,80 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+
+use serde::Deserialize;
+
+use crate::jira_api;
+use crate::jira_api::JiraApi;
+
+/// Number of commit subjects remembered per issue
+const MAX_SUBJECTS: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct Commit {
+    pub subject: String,
+}
+
+/// Recent commit subjects by the issue keys they mention, fed by `POST /heartbeat/commits`
+#[derive(Debug, Default)]
+pub struct CommitFeed {
+    subjects: RwLock<HashMap<String, VecDeque<String>>>,
+}
+
+impl CommitFeed {
+    /// Returns the number of commits mentioning at least one issue
+    pub fn record(&self, commits: &[Commit]) -> usize {
+        let key_pattern = jira_api::issue_key_pattern();
+        let mut subjects = self.subjects.write().unwrap();
+        commits
+            .iter()
+            .filter(|commit| {
+                let mut matched = false;
+                for key in key_pattern.find_iter(&commit.subject) {
+                    let recent = subjects.entry(key.as_str().to_string()).or_default();
+                    if !recent.contains(&commit.subject) {
+                        recent.push_front(commit.subject.clone());
+                        recent.truncate(MAX_SUBJECTS);
+                    }
+                    matched = true;
+                }
+                matched
+            })
+            .count()
+    }
+
+    /// Most recent subjects first
+    pub fn subjects(&self, key: &str) -> Vec<String> {
+        self.subjects
+            .read()
+            .unwrap()
+            .get(key)
+            .map(|recent| recent.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Description from the commits mentioning `key`, or else from the latest own comment on the issue
+pub async fn generate(
+    feed: &CommitFeed,
+    jira: &JiraApi,
+    account_id: &str,
+    key: &str,
+) -> Option<String> {
+    let subjects = feed.subjects(key);
+    if !subjects.is_empty() {
+        return Some(subjects.join("; "));
+    }
+    match jira.get_own_comments(key, account_id).await {
+        Ok(comments) => comments.into_iter().find_map(|comment| {
+            comment
+                .lines()
+                .map(str::trim)
+                .find(|l| !l.is_empty())
+                .map(str::to_string)
+        }),
+        Err(e) => {
+            tracing::debug!("could not fetch comments of {}: {}", key, e);
+            None
+        }
+    }
+}
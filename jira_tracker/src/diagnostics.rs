@@ -0,0 +1,164 @@
+//! Bundle of `GET /diagnostics` to attach to bug reports: the effective configuration without
+//! secrets, versions, the size of the state, the storage, the periodic jobs and the last warnings
+//! and errors that were logged.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Write};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Local};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::app_data::{AppData, StateSummary};
+use crate::clock;
+use crate::config::AppConfig;
+use crate::scheduler::JobStatus;
+use crate::storage::StorageBackend;
+
+/// Number of logged warnings and errors that are kept
+const RECENT_ERRORS: usize = 50;
+const REDACTED: &str = "<redacted>";
+
+static ERRORS: Mutex<VecDeque<LoggedError>> = Mutex::new(VecDeque::new());
+
+/// Serializes secrets of the configuration as `<redacted>` if they are set
+pub fn redacted<T: Serialize, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    fn redact(value: Value) -> Value {
+        match value {
+            Value::String(s) if !s.is_empty() => Value::String(REDACTED.to_string()),
+            Value::Object(map) => map.into_iter().map(|(k, v)| (k, redact(v))).collect(),
+            value => value,
+        }
+    }
+    let value = serde_json::to_value(value).map_err(serde::ser::Error::custom)?;
+    redact(value).serialize(serializer)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoggedError {
+    at: DateTime<Local>,
+    level: String,
+    target: String,
+    message: String,
+}
+
+/// Message and fields of an event, like the log line
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+/// Layer keeping the last [`RECENT_ERRORS`] warnings and errors, see [`recent_errors`]
+pub struct ErrorCapture;
+
+impl<S: Subscriber> Layer<S> for ErrorCapture {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > Level::WARN {
+            return;
+        }
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let mut errors = ERRORS.lock().unwrap();
+        if errors.len() == RECENT_ERRORS {
+            errors.pop_front();
+        }
+        errors.push_back(LoggedError {
+            at: clock::local_now(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.0,
+        });
+    }
+}
+
+/// Oldest first
+pub fn recent_errors() -> Vec<LoggedError> {
+    ERRORS.lock().unwrap().iter().cloned().collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct Versions {
+    tracker: &'static str,
+    os: &'static str,
+    arch: &'static str,
+}
+
+impl Versions {
+    pub fn new(tracker: &'static str) -> Self {
+        Self {
+            tracker,
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StorageHealth {
+    backend: StorageBackend,
+    file: PathBuf,
+    /// Missing if the file cannot be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified: Option<DateTime<Local>>,
+    /// The state was corrupt on startup and recovered like this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recovered: Option<String>,
+    /// Detected loops of reloading the state file, a hint at another writer
+    reload_loops: u64,
+    /// Whether this instance holds the writer lease
+    writer: bool,
+    read_only: bool,
+}
+
+impl StorageHealth {
+    pub fn new(config: &AppConfig, data: &AppData, writer: bool, read_only: bool) -> Self {
+        let backend = data.storage_backend();
+        let file = match backend {
+            StorageBackend::Json => config.json_file.clone(),
+            StorageBackend::Sqlite => config.sqlite_file(),
+        };
+        let metadata = fs::metadata(&file).ok();
+        Self {
+            backend,
+            size: metadata.as_ref().map(fs::Metadata::len),
+            modified: metadata
+                .and_then(|metadata| metadata.modified().ok())
+                .map(DateTime::from),
+            file,
+            recovered: data.recovery().map(str::to_string),
+            reload_loops: data.reload_loops(),
+            writer,
+            read_only,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Diagnostics<'a> {
+    pub generated_at: DateTime<Local>,
+    pub versions: Versions,
+    pub config: &'a AppConfig,
+    pub state: StateSummary,
+    pub storage: StorageHealth,
+    pub jobs: Vec<JobStatus>,
+    /// Failed authentications since the start, see `auth_guard.rs`
+    pub auth_failures: u64,
+    pub recent_errors: Vec<LoggedError>,
+}
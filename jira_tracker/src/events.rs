@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::Stream;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::MissedTickBehavior;
+
+use domain::TrackerEvent;
+
+use crate::app_data::AppData;
+
+/// Events buffered per subscriber, older ones are dropped for subscribers that fall behind
+pub const CAPACITY: usize = 256;
+/// Shortest interval of [`ticks`], the elapsed time is given in whole seconds
+const MIN_TICK: Duration = Duration::from_secs(1);
+
+/// Elapsed time of the running tracker, not a change but sent along with them on `GET /events`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename = "tick")]
+pub struct Tick {
+    key: String,
+    /// Whole seconds tracked, including the running session
+    elapsed: u64,
+}
+
+/// Consumes events of `receiver` on its own task, so a slow subscriber never blocks writers
+pub fn spawn_subscriber<F>(
+    name: &'static str,
+    mut receiver: broadcast::Receiver<TrackerEvent>,
+    mut handler: F,
+) where
+    F: FnMut(TrackerEvent) + Send + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => handler(event),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("{} subscriber skipped {} events", name, skipped)
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Events of `receiver` as a stream, skipping over events missed by falling behind
+pub fn stream(receiver: broadcast::Receiver<TrackerEvent>) -> impl Stream<Item = TrackerEvent> {
+    futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((event, receiver)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+/// [`Tick`] every `interval`, skipped while no tracker is running
+pub fn ticks(data: Arc<AppData>, interval: Duration) -> impl Stream<Item = Tick> {
+    let mut interval = tokio::time::interval(interval.max(MIN_TICK));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    futures::stream::unfold((data, interval), |(data, mut interval)| async move {
+        loop {
+            interval.tick().await;
+            if let Ok(current) = data.current() {
+                let tick = Tick {
+                    key: current.key,
+                    elapsed: current.duration.as_secs(),
+                };
+                return Some((tick, (data, interval)));
+            }
+        }
+    })
+}
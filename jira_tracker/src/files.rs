@@ -1,29 +1,113 @@
+use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::io::{BufReader, ErrorKind, Write};
 use std::path::{Path, PathBuf};
-use std::{fs, io};
+use std::{fmt, fs, io};
 
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use directories::ProjectDirs;
 use notify::event::{AccessKind, AccessMode, ModifyKind, RenameMode};
 use notify::{recommended_watcher, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use serde_json::Value;
 use tracing::info_span;
 
 #[derive(Debug)]
 pub enum FileError {
     IO(io::Error),
     Serde(serde_json::Error),
+    /// Written by a newer version of the tracker
+    UnknownVersion(u64),
+    /// Encrypted without a key given, with another key, or tampered with
+    Encryption,
 }
 
 impl FileError {
     pub fn is_not_found(&self) -> bool {
         match self {
             FileError::IO(e) => e.kind() == ErrorKind::NotFound,
-            FileError::Serde(_) => false,
+            FileError::Serde(_) | FileError::UnknownVersion(_) | FileError::Encryption => false,
         }
     }
 }
 
+impl Display for FileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::IO(e) => write!(f, "{}", e),
+            FileError::Serde(e) => write!(f, "invalid JSON: {}", e),
+            FileError::UnknownVersion(version) => write!(
+                f,
+                "version {} was written by a newer version of the tracker",
+                version
+            ),
+            FileError::Encryption => {
+                write!(
+                    f,
+                    "encrypted without STATE_ENCRYPTION_KEY, with another key, or tampered with"
+                )
+            }
+        }
+    }
+}
+
+/// Start of encrypted files, followed by the nonce and the ciphertext
+const ENCRYPTED_MAGIC: &[u8] = b"jira_tracker:chacha20poly1305:";
+const NONCE_LENGTH: usize = 12;
+
+/// Key encrypting files at rest with ChaCha20-Poly1305
+#[derive(Clone)]
+pub struct FileKey(Key);
+
+impl FileKey {
+    /// From 32 base64 encoded bytes, e.g. generated by `openssl rand -base64 32`
+    pub fn from_base64(encoded: &str) -> Option<Self> {
+        let bytes = STANDARD.decode(encoded.trim()).ok()?;
+        (bytes.len() == 32).then(|| FileKey(*Key::from_slice(&bytes)))
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&self.0)
+            .encrypt(&nonce, plaintext)
+            .expect("encrypting into memory does not fail");
+        [ENCRYPTED_MAGIC, nonce.as_slice(), &ciphertext].concat()
+    }
+}
+
+/// Plaintext of possibly encrypted bytes, unencrypted ones are returned as they are
+pub fn decrypt(bytes: Vec<u8>, key: Option<&FileKey>) -> Result<Vec<u8>, FileError> {
+    let Some(encrypted) = bytes.strip_prefix(ENCRYPTED_MAGIC) else {
+        return Ok(bytes);
+    };
+    let key = key.ok_or(FileError::Encryption)?;
+    if encrypted.len() < NONCE_LENGTH {
+        return Err(FileError::Encryption);
+    }
+    let (nonce, ciphertext) = encrypted.split_at(NONCE_LENGTH);
+    ChaCha20Poly1305::new(&key.0)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| FileError::Encryption)
+}
+
+/// Upgrades a file of one version to the next
+pub type Migration = fn(&mut Value);
+
+/// Platform specific location of the state file, `$XDG_STATE_HOME/jira_tracker/state.json` with
+/// `~/.local/state` as default on Linux, `~/Library/Application Support/jira_tracker/state.json` on
+/// macOS and `%LOCALAPPDATA%\jira_tracker\data\state.json` on Windows
+pub fn default_state_file() -> Option<PathBuf> {
+    let directories = ProjectDirs::from("", "", "jira_tracker")?;
+    let directory = directories
+        .state_dir()
+        .unwrap_or_else(|| directories.data_local_dir());
+    Some(directory.join("state.json"))
+}
+
 pub fn read_file<P, D>(path: P) -> Result<D, FileError>
 where
     P: AsRef<Path>,
@@ -35,18 +119,48 @@ where
     Ok(app_data)
 }
 
+/// Applies the `migrations` from the `version` of the value on, a missing version being 0, and
+/// sets it to the latest one
+pub fn migrate(value: &mut Value, migrations: &[Migration]) -> Result<(), FileError> {
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    let latest = migrations.len() as u64;
+    if version > latest {
+        return Err(FileError::UnknownVersion(version));
+    }
+    for (from, migration) in migrations.iter().enumerate().skip(version as usize) {
+        tracing::info!("migrating file from version {} to {}", from, from + 1);
+        migration(value);
+    }
+    if let Value::Object(object) = value {
+        object.insert("version".to_string(), latest.into());
+    }
+    Ok(())
+}
+
+/// Writes to a temporary file next to `buf` first and renames it afterwards, so that a crash
+/// while writing never leaves a truncated file behind
 pub fn write_file<P, S>(buf: P, value: &S) -> Result<(), FileError>
 where
     P: AsRef<Path>,
     S: ?Sized + Serialize,
 {
-    let parent_directory = buf.as_ref().parent().unwrap();
-    fs::create_dir_all(parent_directory).map_err(FileError::IO)?;
-    let file = File::create(buf).map_err(FileError::IO)?;
+    let bytes = serde_json::to_vec_pretty(value).map_err(FileError::Serde)?;
+    write_bytes(buf, &bytes)
+}
 
-    let mut writer = BufWriter::new(file);
-    serde_json::to_writer_pretty(&mut writer, value).map_err(FileError::Serde)?;
-    writer.flush().map_err(FileError::IO)?;
+/// Replaces the file atomically by writing a temporary file next to it first
+pub fn write_bytes<P: AsRef<Path>>(buf: P, bytes: &[u8]) -> Result<(), FileError> {
+    let path = buf.as_ref();
+    let parent_directory = path.parent().unwrap();
+    fs::create_dir_all(parent_directory).map_err(FileError::IO)?;
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    let mut file = File::create(&temporary).map_err(FileError::IO)?;
+    file.write_all(bytes).map_err(FileError::IO)?;
+    file.sync_all().map_err(FileError::IO)?;
+    drop(file);
+    fs::rename(&temporary, path).map_err(FileError::IO)?;
     Ok(())
 }
 
@@ -72,7 +186,10 @@ where
     let parent = watched_path
         .parent()
         .filter(|path| !path.as_os_str().is_empty())
-        .unwrap_or(Path::new("."))
+        .unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)
+        .unwrap_or_else(|e| panic!("Could not create {}: {}", parent.display(), e));
+    let parent = parent
         .canonicalize()
         .unwrap_or_else(|_| panic!("Parent path for {} does not exist.", watched_path.display()));
     let mut watcher = recommended_watcher(move |event| {
@@ -0,0 +1,467 @@
+//! Filter expressions like `project=PROJ and duration>30m`, see `?filter=` on report endpoints
+//!
+//! ```text
+//! expression := term ("or" term)*
+//! term       := factor ("and" factor)*
+//! factor     := "not" factor | "(" expression ")" | field operator value
+//! operator   := "=" | "!=" | "~" | "<" | "<=" | ">" | ">="
+//! ```
+//!
+//! Fields are `key`, `project`, `description`, `status`, `duration`, `external` and `running`.
+//! `~` tests whether the field contains the value, ignoring case.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::FromStr;
+use std::time::Duration;
+use std::vec::IntoIter;
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+use domain::TrackerInformation;
+
+#[derive(Debug)]
+pub struct FilterError(String);
+
+impl Display for FilterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid filter: {}", self.0)
+    }
+}
+
+impl IntoResponse for FilterError {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Operator(String),
+    Open,
+    Close,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '=' | '!' | '<' | '>' | '~' => {
+                let mut operator = String::from(c);
+                chars.next();
+                if chars.peek() == Some(&'=') && c != '=' && c != '~' {
+                    operator.push('=');
+                    chars.next();
+                }
+                tokens.push(Token::Operator(operator));
+            }
+            '"' => {
+                chars.next();
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(FilterError("unterminated quote".to_string())),
+                    }
+                }
+                tokens.push(Token::Word(word));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()=!<>~\"".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Field {
+    Key,
+    Project,
+    Description,
+    Status,
+    Duration,
+    External,
+    Running,
+}
+
+impl FromStr for Field {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "key" => Ok(Field::Key),
+            "project" => Ok(Field::Project),
+            "description" => Ok(Field::Description),
+            "status" => Ok(Field::Status),
+            "duration" => Ok(Field::Duration),
+            "external" => Ok(Field::External),
+            "running" => Ok(Field::Running),
+            _ => Err(FilterError(format!("unknown field '{}'", s))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Contains,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl FromStr for Operator {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "=" => Ok(Operator::Equal),
+            "!=" => Ok(Operator::NotEqual),
+            "~" => Ok(Operator::Contains),
+            "<" => Ok(Operator::Less),
+            "<=" => Ok(Operator::LessOrEqual),
+            ">" => Ok(Operator::Greater),
+            ">=" => Ok(Operator::GreaterOrEqual),
+            _ => Err(FilterError(format!("unknown operator '{}'", s))),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Value {
+    Text(String),
+    Duration(Duration),
+    Flag(bool),
+}
+
+#[derive(Debug)]
+pub enum Filter {
+    Compare(Field, Operator, Value),
+    Not(Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+struct Parser {
+    tokens: Peekable<IntoIter<Token>>,
+}
+
+impl Parser {
+    fn next_word(&mut self, expected: &str) -> Result<String, FilterError> {
+        match self.tokens.next() {
+            Some(Token::Word(word)) => Ok(word),
+            _ => Err(FilterError(format!("expected {}", expected))),
+        }
+    }
+
+    fn is_keyword(&mut self, keyword: &str) -> bool {
+        let found =
+            matches!(self.tokens.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword));
+        if found {
+            self.tokens.next();
+        }
+        found
+    }
+
+    fn expression(&mut self) -> Result<Filter, FilterError> {
+        let mut filter = self.term()?;
+        while self.is_keyword("or") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.term()?));
+        }
+        Ok(filter)
+    }
+
+    fn term(&mut self) -> Result<Filter, FilterError> {
+        let mut filter = self.factor()?;
+        while self.is_keyword("and") {
+            filter = Filter::And(Box::new(filter), Box::new(self.factor()?));
+        }
+        Ok(filter)
+    }
+
+    fn factor(&mut self) -> Result<Filter, FilterError> {
+        if self.is_keyword("not") {
+            return Ok(Filter::Not(Box::new(self.factor()?)));
+        }
+        if self.tokens.peek() == Some(&Token::Open) {
+            self.tokens.next();
+            let filter = self.expression()?;
+            return match self.tokens.next() {
+                Some(Token::Close) => Ok(filter),
+                _ => Err(FilterError("expected ')'".to_string())),
+            };
+        }
+        let field: Field = self.next_word("field")?.parse()?;
+        let operator: Operator = match self.tokens.next() {
+            Some(Token::Operator(operator)) => operator.parse()?,
+            _ => return Err(FilterError("expected operator".to_string())),
+        };
+        let value = self.next_word("value")?;
+        let value = match field {
+            Field::Duration => humantime::parse_duration(&value)
+                .map(Value::Duration)
+                .map_err(|e| FilterError(format!("invalid duration '{}': {}", value, e)))?,
+            Field::External | Field::Running => value
+                .parse()
+                .map(Value::Flag)
+                .map_err(|_| FilterError(format!("expected true or false, got '{}'", value)))?,
+            _ => Value::Text(value),
+        };
+        Ok(Filter::Compare(field, operator, value))
+    }
+}
+
+impl FromStr for Filter {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?.into_iter().peekable(),
+        };
+        let filter = parser.expression()?;
+        match parser.tokens.next() {
+            None => Ok(filter),
+            Some(token) => Err(FilterError(format!("unexpected {:?}", token))),
+        }
+    }
+}
+
+fn compare<T: PartialOrd + ?Sized>(actual: &T, operator: Operator, expected: &T) -> bool {
+    match operator {
+        Operator::Equal | Operator::Contains => actual == expected,
+        Operator::NotEqual => actual != expected,
+        Operator::Less => actual < expected,
+        Operator::LessOrEqual => actual <= expected,
+        Operator::Greater => actual > expected,
+        Operator::GreaterOrEqual => actual >= expected,
+    }
+}
+
+fn compare_text(actual: &str, operator: Operator, expected: &str) -> bool {
+    match operator {
+        Operator::Contains => actual.to_lowercase().contains(&expected.to_lowercase()),
+        _ => compare(actual, operator, expected),
+    }
+}
+
+impl Filter {
+    /// Status is only known for trackers listed with full details
+    pub fn matches(&self, tracker: &TrackerInformation) -> bool {
+        match self {
+            Filter::Not(filter) => !filter.matches(tracker),
+            Filter::And(a, b) => a.matches(tracker) && b.matches(tracker),
+            Filter::Or(a, b) => a.matches(tracker) || b.matches(tracker),
+            Filter::Compare(field, operator, value) => {
+                let operator = *operator;
+                match (field, value) {
+                    (Field::Key, Value::Text(text)) => compare_text(&tracker.key, operator, text),
+                    (Field::Project, Value::Text(text)) => {
+                        let project = tracker.key.split_once('-').map_or("", |(p, _)| p);
+                        compare_text(project, operator, text)
+                    }
+                    (Field::Description, Value::Text(text)) => compare_text(
+                        tracker.description.as_deref().unwrap_or_default(),
+                        operator,
+                        text,
+                    ),
+                    (Field::Status, Value::Text(text)) => compare_text(
+                        tracker
+                            .details
+                            .as_ref()
+                            .and_then(|d| d.status.as_deref())
+                            .unwrap_or_default(),
+                        operator,
+                        text,
+                    ),
+                    (Field::Duration, Value::Duration(duration)) => {
+                        compare(&tracker.duration, operator, duration)
+                    }
+                    (Field::External, Value::Flag(flag)) => {
+                        compare(&tracker.external, operator, flag)
+                    }
+                    (Field::Running, Value::Flag(flag)) => {
+                        compare(&tracker.running, operator, flag)
+                    }
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::Local;
+
+    use domain::IssueDetails;
+
+    use super::*;
+
+    fn tracker(key: &str, description: Option<&str>, minutes: u64) -> TrackerInformation {
+        TrackerInformation {
+            key: key.to_string(),
+            id: "10001".to_string(),
+            description: description.map(str::to_string),
+            duration: Duration::from_secs(minutes * 60),
+            running: false,
+            start_time: Local::now(),
+            external: false,
+            paired_with: None,
+            details: Some(IssueDetails {
+                summary: "Summary".to_string(),
+                status: Some("In Progress".to_string()),
+                issue_type: None,
+                priority: None,
+                avatar_url: None,
+                project_id: None,
+            }),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    fn matches(filter: &str, tracker: &TrackerInformation) -> bool {
+        filter.parse::<Filter>().unwrap().matches(tracker)
+    }
+
+    fn error(filter: &str) -> String {
+        filter.parse::<Filter>().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn compares_each_field() {
+        let tracker = tracker("ABC-1", Some("Fix login"), 45);
+
+        assert!(matches("key=ABC-1", &tracker));
+        assert!(matches("project=ABC", &tracker));
+        assert!(matches("description~login", &tracker));
+        assert!(matches("status=\"In Progress\"", &tracker));
+        assert!(matches("duration>30m", &tracker));
+        assert!(matches("external=false", &tracker));
+        assert!(matches("running=false", &tracker));
+        assert!(!matches("project=ABD", &tracker));
+    }
+
+    #[test]
+    fn comparison_operators() {
+        let tracker = tracker("ABC-1", None, 30);
+
+        assert!(matches("duration=30m", &tracker));
+        assert!(matches("duration!=1h", &tracker));
+        assert!(matches("duration<1h", &tracker));
+        assert!(matches("duration<=30m", &tracker));
+        assert!(!matches("duration<30m", &tracker));
+        assert!(matches("duration>10m", &tracker));
+        assert!(matches("duration>=30m", &tracker));
+        assert!(!matches("duration>30m", &tracker));
+        assert!(matches("key != ABC-2", &tracker));
+    }
+
+    #[test]
+    fn contains_ignores_case_but_equal_does_not() {
+        let tracker = tracker("ABC-1", Some("Weekly Meeting"), 30);
+
+        assert!(matches("description~meeting", &tracker));
+        assert!(!matches("description=\"weekly meeting\"", &tracker));
+        assert!(matches("description=\"Weekly Meeting\"", &tracker));
+    }
+
+    #[test]
+    fn quoted_values_keep_spaces_and_operators() {
+        let tracker = tracker("ABC-1", Some("a=b (c)"), 30);
+
+        assert!(matches("description=\"a=b (c)\"", &tracker));
+        assert!(matches("description~\"b (\"", &tracker));
+    }
+
+    #[test]
+    fn and_binds_stronger_than_or() {
+        let tracker = tracker("ABC-1", None, 30);
+
+        // true or (false and false)
+        assert!(matches(
+            "project=ABC or project=XYZ and duration>1h",
+            &tracker
+        ));
+        // (false and true) or false
+        assert!(!matches(
+            "project=XYZ and duration<1h or key=XYZ-1",
+            &tracker
+        ));
+        assert!(matches(
+            "(project=XYZ or project=ABC) and duration<1h",
+            &tracker
+        ));
+        assert!(!matches(
+            "(project=ABC or project=XYZ) and duration>1h",
+            &tracker
+        ));
+    }
+
+    #[test]
+    fn not_applies_to_the_next_factor() {
+        let tracker = tracker("ABC-1", None, 30);
+
+        assert!(!matches("not project=ABC and duration<1h", &tracker));
+        assert!(matches("not (project=ABC and duration>1h)", &tracker));
+        assert!(matches("not not project=ABC", &tracker));
+    }
+
+    #[test]
+    fn keywords_ignore_case() {
+        let tracker = tracker("ABC-1", None, 30);
+
+        assert!(matches(
+            "NOT project=XYZ AND duration<1h Or key=XYZ-1",
+            &tracker
+        ));
+    }
+
+    #[test]
+    fn invalid_filters_are_rejected() {
+        assert_eq!(error("owner=me"), "invalid filter: unknown field 'owner'");
+        assert_eq!(error("key!ABC-1"), "invalid filter: unknown operator '!'");
+        assert_eq!(error("key ABC-1"), "invalid filter: expected operator");
+        assert_eq!(error("key="), "invalid filter: expected value");
+        assert_eq!(error(""), "invalid filter: expected field");
+        assert_eq!(
+            error("description=\"open"),
+            "invalid filter: unterminated quote"
+        );
+        assert_eq!(error("(key=ABC-1"), "invalid filter: expected ')'");
+        assert_eq!(error("key=ABC-1)"), "invalid filter: unexpected Close");
+        assert_eq!(
+            error("key=ABC-1 project=ABC"),
+            "invalid filter: unexpected Word(\"project\")"
+        );
+        assert!(error("duration>soon").starts_with("invalid filter: invalid duration 'soon'"));
+        assert_eq!(
+            error("running=yes"),
+            "invalid filter: expected true or false, got 'yes'"
+        );
+    }
+}
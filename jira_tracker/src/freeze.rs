@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::{Method, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::NaiveTime;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::clock;
+use crate::config::AppConfig;
+
+/// Daily time span in which trackers must not be changed, e.g. while submitting
+#[derive(Debug, Clone, Copy)]
+pub struct FreezeWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl FreezeWindow {
+    /// End of the window if `time` lies within it. Windows may span midnight.
+    pub fn unlock_at(&self, time: NaiveTime) -> Option<NaiveTime> {
+        let frozen = if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        };
+        frozen.then_some(self.end)
+    }
+}
+
+/// Parses windows like `17:45-18:00`
+impl<'de> Deserialize<'de> for FreezeWindow {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").ok();
+        string
+            .split_once('-')
+            .and_then(|(start, end)| Some((parse(start)?, parse(end)?)))
+            .map(|(start, end)| FreezeWindow { start, end })
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid window '{}'", string)))
+    }
+}
+
+impl Serialize for FreezeWindow {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let window = format!(
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        );
+        serializer.serialize_str(&window)
+    }
+}
+
+/// End of the configured window if it is open now
+pub fn frozen_until(config: &AppConfig) -> Option<NaiveTime> {
+    config.freeze_window?.unlock_at(clock::local_now().time())
+}
+
+/// Middleware answering mutating requests with 423 Locked while the freeze window is open.
+/// Submitting stays possible, as that is what the window protects.
+pub async fn reject_frozen<B>(
+    State(config): State<Arc<AppConfig>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let mutating = request.method() != Method::GET && request.method() != Method::HEAD;
+    let submitting = request.uri().path().starts_with("/submit");
+    if let Some(unlock_at) = frozen_until(&config).filter(|_| mutating && !submitting) {
+        let message = format!("frozen until {}", unlock_at.format("%H:%M"));
+        return (StatusCode::LOCKED, message).into_response();
+    }
+    next.run(request).await
+}
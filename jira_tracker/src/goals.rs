@@ -0,0 +1,130 @@
+//! Weekly goals like "at least 10h on `project=EPIC`" or "at most 5h on `description~meeting`",
+//! managed with `/goals`. Their progress is evaluated on the time tracked this week as kept in the
+//! ledger, so submitted trackers still count, and shown in the weekly report and summary.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+
+use domain::TrackerInformation;
+
+use crate::filter::{Filter, FilterError};
+use crate::report::format_duration;
+use crate::rounding::DisplayRounding;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Goal {
+    pub name: String,
+    /// Trackers counting towards the goal, see `filter.rs`
+    pub filter: String,
+    #[serde(
+        default,
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub at_least: Option<Duration>,
+    #[serde(
+        default,
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub at_most: Option<Duration>,
+}
+
+/// Body of `PUT /goals/:name`
+#[derive(Debug, Deserialize)]
+pub struct GoalBody {
+    pub filter: String,
+    #[serde(default, with = "humantime_serde")]
+    pub at_least: Option<Duration>,
+    #[serde(default, with = "humantime_serde")]
+    pub at_most: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub enum GoalError {
+    Filter(FilterError),
+    /// Neither `at_least` nor `at_most` given
+    NoBound,
+}
+
+impl Display for GoalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GoalError::Filter(e) => write!(f, "{}", e),
+            GoalError::NoBound => write!(f, "a goal needs at_least, at_most or both"),
+        }
+    }
+}
+
+impl IntoResponse for GoalError {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+    }
+}
+
+impl Goal {
+    pub fn new(name: &str, body: GoalBody) -> Result<Self, GoalError> {
+        body.filter.parse::<Filter>().map_err(GoalError::Filter)?;
+        if body.at_least.is_none() && body.at_most.is_none() {
+            return Err(GoalError::NoBound);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            filter: body.filter,
+            at_least: body.at_least,
+            at_most: body.at_most,
+        })
+    }
+
+    /// Progress on the week's trackers, each with its time of the week
+    pub fn progress(&self, week: &[TrackerInformation]) -> GoalProgress {
+        let tracked = match self.filter.parse::<Filter>() {
+            Ok(filter) => week
+                .iter()
+                .filter(|tracker| filter.matches(tracker))
+                .map(|tracker| tracker.duration)
+                .sum(),
+            Err(_) => Duration::ZERO,
+        };
+        GoalProgress {
+            met: self.at_least.is_none_or(|at_least| tracked >= at_least)
+                && self.at_most.is_none_or(|at_most| tracked <= at_most),
+            goal: self.clone(),
+            tracked,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalProgress {
+    #[serde(flatten)]
+    goal: Goal,
+    /// Time of the matching trackers this week
+    #[serde(with = "humantime_serde")]
+    tracked: Duration,
+    met: bool,
+}
+
+impl GoalProgress {
+    /// e.g. `EPIC: 8h 30m (≥ 10h 00m) ✗`
+    pub fn describe(&self, display: DisplayRounding) -> String {
+        let bounds: Vec<String> = [("≥", self.goal.at_least), ("≤", self.goal.at_most)]
+            .into_iter()
+            .filter_map(|(sign, bound)| {
+                bound.map(|b| format!("{} {}", sign, format_duration(display.shown(b))))
+            })
+            .collect();
+        format!(
+            "{}: {} ({}) {}",
+            self.goal.name,
+            format_duration(display.shown(self.tracked)),
+            bounds.join(", "),
+            if self.met { "✓" } else { "✗" }
+        )
+    }
+}
@@ -0,0 +1,190 @@
+//! Plain HTML views under `/html` for text browsers, screen readers and kiosks without
+//! JavaScript. All changes are posted as forms to `POST /html/trackers`, which redirects back to the
+//! list or shows it again with the error. Links are relative, so that the views also work below
+//! `/profiles/<name>`.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use domain::{Sum, TrackerInformation};
+
+use crate::locale::{Label, Locale};
+use crate::report::{escape_html, format_duration};
+use crate::rounding::DisplayRounding;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FormAction {
+    /// Starts the tracker of the key, creating it if missing
+    Track,
+    Start,
+    Pause,
+    Adjust,
+}
+
+/// Form posted to `POST /html/trackers`
+#[derive(Debug, Deserialize)]
+pub struct TrackerForm {
+    pub action: FormAction,
+    #[serde(default)]
+    pub key: String,
+    /// e.g. `+15m` or `-10m`
+    #[serde(default)]
+    pub adjustment: String,
+}
+
+impl TrackerForm {
+    /// Duration of the adjustment and whether it is negative
+    pub fn adjustment(&self) -> Option<(Duration, bool)> {
+        let adjustment = self.adjustment.trim();
+        let (negative, duration) = match adjustment.strip_prefix('-') {
+            Some(duration) => (true, duration),
+            None => (false, adjustment.strip_prefix('+').unwrap_or(adjustment)),
+        };
+        humantime::parse_duration(duration.trim())
+            .ok()
+            .map(|duration| (duration, negative))
+    }
+}
+
+fn page(locale: Locale, title: &str, body: &str) -> String {
+    let mut out = String::new();
+    writeln!(out, "<!DOCTYPE html>").unwrap();
+    writeln!(out, "<html lang=\"{}\">", locale.lang()).unwrap();
+    writeln!(
+        out,
+        "<head><meta charset=\"utf-8\"><title>{}</title></head>",
+        escape_html(title)
+    )
+    .unwrap();
+    writeln!(out, "<body>").unwrap();
+    writeln!(
+        out,
+        "<nav><a href=\"trackers\">{}</a> | <a href=\"sum\">{}</a></nav>",
+        locale.label(Label::Trackers),
+        locale.label(Label::Total)
+    )
+    .unwrap();
+    writeln!(out, "<main>").unwrap();
+    writeln!(out, "<h1>{}</h1>", escape_html(title)).unwrap();
+    out.push_str(body);
+    writeln!(out, "</main>").unwrap();
+    writeln!(out, "</body>").unwrap();
+    writeln!(out, "</html>").unwrap();
+    out
+}
+
+/// Submit button of a form of its own, posting `action` for `key`
+fn button(out: &mut String, action: &str, key: &str, label: &str) {
+    writeln!(
+        out,
+        "<form method=\"post\" action=\"trackers\"><input type=\"hidden\" name=\"action\" value=\"{}\"><input type=\"hidden\" name=\"key\" value=\"{}\"><button type=\"submit\">{} {}</button></form>",
+        action,
+        escape_html(key),
+        label,
+        escape_html(key)
+    )
+    .unwrap();
+}
+
+/// `GET /html/trackers`: the trackers with forms to start, pause and adjust them, and `error` of
+/// the last posted form if it failed
+pub fn trackers_page(
+    locale: Locale,
+    display: DisplayRounding,
+    trackers: &[TrackerInformation],
+    error: Option<&str>,
+) -> String {
+    let mut out = String::new();
+    if let Some(error) = error {
+        writeln!(
+            out,
+            "<p role=\"alert\"><strong>{}</strong></p>",
+            escape_html(error)
+        )
+        .unwrap();
+    }
+    if let Some(running) = trackers.iter().find(|tracker| tracker.running) {
+        writeln!(
+            out,
+            "<p role=\"status\">{} {}</p>",
+            escape_html(&running.key),
+            locale.label(Label::Running)
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "<form method=\"post\" action=\"trackers\"><input type=\"hidden\" name=\"action\" value=\"pause\"><button type=\"submit\">{}</button></form>",
+            locale.label(Label::Pause)
+        )
+        .unwrap();
+    }
+
+    writeln!(
+        out,
+        "<form method=\"post\" action=\"trackers\"><input type=\"hidden\" name=\"action\" value=\"track\"><label for=\"track-key\">{}</label> <input id=\"track-key\" name=\"key\" required> <button type=\"submit\">{}</button></form>",
+        locale.label(Label::Issue),
+        locale.label(Label::Track)
+    )
+    .unwrap();
+
+    if trackers.is_empty() {
+        writeln!(out, "<p>{}</p>", locale.label(Label::NoEntries)).unwrap();
+        return page(locale, locale.label(Label::Trackers), &out);
+    }
+    writeln!(out, "<table>").unwrap();
+    writeln!(
+        out,
+        "<tr><th scope=\"col\">{}</th><th scope=\"col\">{}</th><th scope=\"col\">{}</th><th scope=\"col\">{}</th><th scope=\"col\">{}</th></tr>",
+        locale.label(Label::Issue),
+        locale.label(Label::Description),
+        locale.label(Label::Duration),
+        locale.label(Label::Status),
+        locale.label(Label::Adjust)
+    )
+    .unwrap();
+    for (index, tracker) in trackers.iter().enumerate() {
+        let description = tracker
+            .description
+            .as_deref()
+            .or(tracker.details.as_ref().map(|d| d.summary.as_str()))
+            .unwrap_or_default();
+        let status = if tracker.running {
+            locale.label(Label::Running)
+        } else {
+            locale.label(Label::Paused)
+        };
+        writeln!(
+            out,
+            "<tr><th scope=\"row\">{}</th><td>{}</td><td>{}</td><td>{}",
+            escape_html(&tracker.key),
+            escape_html(description),
+            format_duration(display.shown(tracker.duration)),
+            status
+        )
+        .unwrap();
+        if !tracker.running {
+            button(&mut out, "start", &tracker.key, locale.label(Label::Start));
+        }
+        writeln!(out, "</td><td>").unwrap();
+        writeln!(
+            out,
+            "<form method=\"post\" action=\"trackers\"><input type=\"hidden\" name=\"action\" value=\"adjust\"><input type=\"hidden\" name=\"key\" value=\"{key}\"><label for=\"adjust-{index}\">{label} {key} (+15m, -10m)</label> <input id=\"adjust-{index}\" name=\"adjustment\" size=\"6\" required> <button type=\"submit\">{label}</button></form>",
+            key = escape_html(&tracker.key),
+            index = index,
+            label = locale.label(Label::Adjust)
+        )
+        .unwrap();
+        writeln!(out, "</td></tr>").unwrap();
+    }
+    writeln!(out, "</table>").unwrap();
+    page(locale, locale.label(Label::Trackers), &out)
+}
+
+/// `GET /html/sum`: the time of all trackers
+pub fn sum_page(locale: Locale, display: DisplayRounding, sum: &Sum) -> String {
+    let body = format!("<p>{}</p>\n", format_duration(display.shown(sum.duration)));
+    page(locale, locale.label(Label::Total), &body)
+}
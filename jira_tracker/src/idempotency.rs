@@ -0,0 +1,192 @@
+//! Replays responses of mutating requests retried with the same `Idempotency-Key` header
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use axum::body::{boxed, Body, Bytes, Full};
+use axum::extract::State;
+use axum::http::{HeaderMap, Method, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::clock;
+
+const IDEMPOTENCY_HEADER: &str = "idempotency-key";
+
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl From<CachedResponse> for Response {
+    fn from(cached: CachedResponse) -> Self {
+        let mut response = Response::new(boxed(Full::new(cached.body)));
+        *response.status_mut() = cached.status;
+        *response.headers_mut() = cached.headers;
+        response
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// Method, path, query and a hash of the body of the request first sent with the key
+    request: String,
+    at: SystemTime,
+    /// `None` while the first request is still being handled
+    response: Option<CachedResponse>,
+}
+
+/// Responses by idempotency key, kept for the configured window
+#[derive(Debug)]
+pub struct IdempotencyCache {
+    window: Duration,
+    entries: RwLock<HashMap<String, Entry>>,
+}
+
+enum Lookup {
+    New,
+    Replay(CachedResponse),
+    InFlight,
+    Mismatch,
+}
+
+impl IdempotencyCache {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Reserves the key for `request` unless it was seen within the window
+    fn lookup(&self, key: &str, request: &str) -> Lookup {
+        let mut entries = self.entries.write().unwrap();
+        entries.retain(|_, entry| clock::elapsed(entry.at) < self.window);
+        match entries.get(key) {
+            Some(entry) if entry.request != request => Lookup::Mismatch,
+            Some(Entry {
+                response: Some(response),
+                ..
+            }) => Lookup::Replay(response.clone()),
+            Some(_) => Lookup::InFlight,
+            None => {
+                entries.insert(
+                    key.to_string(),
+                    Entry {
+                        request: request.to_string(),
+                        at: clock::now(),
+                        response: None,
+                    },
+                );
+                Lookup::New
+            }
+        }
+    }
+
+    /// Keeps the response, or releases the key if it is not worth replaying
+    fn store(&self, key: &str, response: Option<CachedResponse>) {
+        let mut entries = self.entries.write().unwrap();
+        match response {
+            Some(response) => {
+                if let Some(entry) = entries.get_mut(key) {
+                    entry.response = Some(response);
+                }
+            }
+            None => {
+                entries.remove(key);
+            }
+        }
+    }
+}
+
+/// Key reserved for a request being handled, released if the request is cancelled, e.g. by the
+/// client disconnecting, or panics before its response is kept
+struct Reservation {
+    cache: Arc<IdempotencyCache>,
+    key: String,
+    stored: bool,
+}
+
+impl Reservation {
+    fn store(mut self, response: Option<CachedResponse>) {
+        self.cache.store(&self.key, response);
+        self.stored = true;
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if !self.stored {
+            self.cache.store(&self.key, None);
+        }
+    }
+}
+
+/// Middleware replaying the response of a mutating request sent again with the same
+/// `Idempotency-Key`. Server errors are not kept, so the request can be retried.
+pub async fn replay(
+    State(cache): State<Arc<IdempotencyCache>>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    let mutating = matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::DELETE
+    );
+    let key = request
+        .headers()
+        .get(IDEMPOTENCY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let Some(key) = key.filter(|_| mutating) else {
+        return next.run(request).await;
+    };
+    let (parts, body) = request.into_parts();
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    let fingerprint = format!("{} {} {:x}", parts.method, parts.uri, hasher.finish());
+    let request = Request::from_parts(parts, Body::from(body));
+    match cache.lookup(&key, &fingerprint) {
+        Lookup::Replay(response) => return response.into(),
+        Lookup::InFlight => {
+            let message = "a request with this idempotency key is in progress";
+            return (StatusCode::CONFLICT, message).into_response();
+        }
+        Lookup::Mismatch => {
+            let message = "idempotency key was used for a different request";
+            return (StatusCode::UNPROCESSABLE_ENTITY, message).into_response();
+        }
+        Lookup::New => {}
+    }
+
+    let reservation = Reservation {
+        cache,
+        key,
+        stored: false,
+    };
+    let (parts, body) = next.run(request).await.into_parts();
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(e) => {
+            reservation.store(None);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    let cached = CachedResponse {
+        status: parts.status,
+        headers: parts.headers,
+        body,
+    };
+    let keep = !cached.status.is_server_error();
+    reservation.store(keep.then(|| cached.clone()));
+    cached.into()
+}
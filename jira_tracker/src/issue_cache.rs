@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use crate::clock;
+use crate::jira_api::{JiraApi, JiraIssue};
+use crate::scheduler::Scheduler;
+use crate::upstream::UpstreamError;
+
+/// How long a key Jira did not find is answered from the cache, e.g. when retrying a typo
+const NOT_FOUND_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+pub struct IssueCache {
+    /// How long a fetched issue is answered from the cache, see `ISSUE_CACHE_TTL`
+    ttl: Duration,
+    /// Issues with the time they were fetched
+    issues: RwLock<HashMap<String, (SystemTime, JiraIssue)>>,
+    /// Keys Jira did not find, with the time of the lookup and the error detail of Jira
+    not_found: RwLock<HashMap<String, (SystemTime, String)>>,
+}
+
+impl IssueCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            issues: RwLock::new(HashMap::new()),
+            not_found: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The cached issue unless it expired, so that changes in Jira show up eventually
+    pub fn get(&self, key: &str) -> Option<JiraIssue> {
+        let issues = self.issues.read().unwrap();
+        let (fetched, issue) = issues.get(key)?;
+        (clock::elapsed(*fetched) < self.ttl).then(|| issue.clone())
+    }
+
+    pub fn insert(&self, issue: JiraIssue) {
+        self.not_found.write().unwrap().remove(&issue.key);
+        let mut issues = self.issues.write().unwrap();
+        issues.retain(|_, (fetched, _)| clock::elapsed(*fetched) < self.ttl);
+        issues.insert(issue.key.clone(), (clock::now(), issue));
+    }
+
+    /// Detail of the error of Jira if it did not find the key within [`NOT_FOUND_TTL`]
+    fn not_found(&self, key: &str) -> Option<String> {
+        let not_found = self.not_found.read().unwrap();
+        let (at, detail) = not_found.get(key)?;
+        (clock::elapsed(*at) < NOT_FOUND_TTL).then(|| detail.clone())
+    }
+
+    /// Returns the cached issue or looks it up in Jira, caching the result. Keys Jira did not
+    /// find are not looked up again for a while.
+    pub async fn get_or_fetch(
+        &self,
+        jira: &JiraApi,
+        key: &str,
+    ) -> Result<JiraIssue, UpstreamError> {
+        if let Some(issue) = self.get(key) {
+            return Ok(issue);
+        }
+        if let Some(detail) = self.not_found(key) {
+            return Err(UpstreamError::JiraNotFound(detail));
+        }
+        match jira.get_issue_info(key).await {
+            Ok(issue) => {
+                self.insert(issue.clone());
+                Ok(issue)
+            }
+            Err(UpstreamError::JiraNotFound(detail)) => {
+                let mut not_found = self.not_found.write().unwrap();
+                not_found.retain(|_, (at, _)| clock::elapsed(*at) < NOT_FOUND_TTL);
+                not_found.insert(key.to_string(), (clock::now(), detail.clone()));
+                Err(UpstreamError::JiraNotFound(detail))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Fetches the given keys one after another, waiting `delay` in between requests. Returns
+    /// the number of successfully fetched issues.
+    pub async fn prefetch(&self, jira: &JiraApi, keys: &[String], delay: Duration) -> usize {
+        let mut fetched = 0;
+        for key in keys {
+            match jira.get_issue_info(key).await {
+                Ok(issue) => {
+                    self.insert(issue);
+                    fetched += 1;
+                }
+                Err(e) => tracing::warn!("prefetching {} failed: {}", key, e),
+            }
+            tokio::time::sleep(delay).await;
+        }
+        tracing::debug!("prefetched {} issues", fetched);
+        fetched
+    }
+}
+
+pub fn schedule_prefetch(
+    scheduler: &Scheduler,
+    cache: Arc<IssueCache>,
+    jira: Arc<JiraApi>,
+    keys: Vec<String>,
+    interval: Duration,
+    delay: Duration,
+) {
+    if keys.is_empty() {
+        return;
+    }
+    let keys = Arc::new(keys);
+    scheduler.add("prefetch", interval, move || {
+        let (cache, jira, keys) = (cache.clone(), jira.clone(), keys.clone());
+        async move {
+            let fetched = cache.prefetch(&jira, &keys, delay).await;
+            Ok(format!("prefetched {} of {} issues", fetched, keys.len()))
+        }
+    });
+}
@@ -1,14 +1,40 @@
 use crate::config::AppConfig;
+use crate::sandbox;
+use crate::upstream;
+use crate::upstream::{Service, UpstreamError};
 use axum::http::header::AUTHORIZATION;
 use axum::http::{HeaderMap, HeaderValue};
 use base64::{engine::general_purpose::STANDARD, Engine as _};
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
 
+use domain::{IconReference, IssueDetails};
+
+const SITE_URI: &str = "https://anevis.atlassian.net";
 const BASE_URI: &str = "https://anevis.atlassian.net/rest/api/latest";
 
+/// Link to the issue page with the given worklog highlighted
+pub fn worklog_url(key: &str, jira_worklog_id: u64) -> String {
+    format!(
+        "{}/browse/{}?focusedWorklogId={}",
+        SITE_URI, key, jira_worklog_id
+    )
+}
+
+/// Matches issue keys like `ABC-1` in free text, e.g. commit subjects
+pub fn issue_key_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\b[A-Z][A-Z0-9]+-\d+\b").unwrap())
+}
+
 #[derive(Debug)]
 pub struct JiraApi {
     client: reqwest::Client,
+    /// Answers without requests, see `sandbox.rs`
+    sandbox: bool,
 }
 
 impl From<&AppConfig> for JiraApi {
@@ -29,42 +55,259 @@ impl From<&AppConfig> for JiraApi {
             .build()
             .unwrap();
 
-        Self { client }
+        Self {
+            client,
+            sandbox: value.sandbox,
+        }
     }
 }
 
 impl JiraApi {
-    pub async fn get_account_id(&self) -> Result<String, reqwest::Error> {
+    pub fn is_sandbox(&self) -> bool {
+        self.sandbox
+    }
+
+    pub async fn get_account_id(&self) -> Result<String, UpstreamError> {
+        if self.sandbox {
+            return Ok(sandbox::ACCOUNT_ID.to_string());
+        }
         let url = format!("{}/myself", BASE_URI);
-        let response = self.client.get(&url).send().await?;
-        let json = response.json::<serde_json::Value>().await?;
-        let account_id = json["accountId"].as_str().unwrap();
-        Ok(account_id.to_string())
+        let myself: Myself = upstream::send_json(Service::Jira, self.client.get(&url)).await?;
+        Ok(myself.account_id)
+    }
+
+    pub async fn get_projects(&self) -> Result<Vec<JiraProject>, UpstreamError> {
+        if self.sandbox {
+            return Ok(Vec::new());
+        }
+        let url = format!("{}/project", BASE_URI);
+        upstream::send_json(Service::Jira, self.client.get(&url)).await
     }
 
     pub async fn get_issue_info<K: AsRef<str>>(
         &self,
         issue_key: K,
-    ) -> Result<JiraIssue, reqwest::Error> {
+    ) -> Result<JiraIssue, UpstreamError> {
+        if self.sandbox {
+            return Ok(sandbox::issue(issue_key.as_ref()));
+        }
         let url = format!("{}/issue/{}", BASE_URI, issue_key.as_ref());
-        let response = self
+        let request = self.client.get(&url).query(&[(
+            "fields",
+            "summary,status,issuetype,priority,project,timeoriginalestimate,timespent",
+        )]);
+        upstream::send_json(Service::Jira, request).await
+    }
+
+    /// Bodies of the comments by `account_id` on the issue, newest first
+    pub async fn get_own_comments(
+        &self,
+        issue_key: &str,
+        account_id: &str,
+    ) -> Result<Vec<String>, UpstreamError> {
+        if self.sandbox {
+            return Ok(Vec::new());
+        }
+        let url = format!("{}/issue/{}/comment", BASE_URI, issue_key);
+        let request = self
             .client
             .get(&url)
-            .query(&[("fields", "summary")])
-            .send()
-            .await?;
-        response.error_for_status()?.json::<JiraIssue>().await
+            .query(&[("orderBy", "-created"), ("maxResults", "50")]);
+        let response: CommentsResponse = upstream::send_json(Service::Jira, request).await?;
+        Ok(response
+            .comments
+            .into_iter()
+            .filter(|comment| comment.author.account_id == account_id)
+            .map(|comment| comment.body)
+            .collect())
     }
+
+    /// Issues of open sprints assigned to the own account
+    pub async fn get_sprint_issues(
+        &self,
+        sprint_field: &str,
+    ) -> Result<Vec<SprintIssue>, UpstreamError> {
+        if self.sandbox {
+            return Ok(Vec::new());
+        }
+        let url = format!("{}/search", BASE_URI);
+        let request = self.client.get(&url).query(&[
+            (
+                "jql",
+                "assignee = currentUser() AND sprint in openSprints()",
+            ),
+            ("fields", &format!("status,timeestimate,{}", sprint_field)),
+            ("maxResults", "100"),
+        ]);
+        let response: SearchResponse<SprintIssue> =
+            upstream::send_json(Service::Jira, request).await?;
+        Ok(response.issues)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse<T> {
+    issues: Vec<T>,
 }
 
 #[derive(Debug, Deserialize)]
+struct CommentsResponse {
+    comments: Vec<Comment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Comment {
+    author: CommentAuthor,
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommentAuthor {
+    #[serde(rename = "accountId")]
+    account_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SprintIssue {
+    pub key: String,
+    pub fields: SprintIssueFields,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SprintIssueFields {
+    pub status: Option<StatusField>,
+    /// Remaining estimate in seconds
+    #[serde(rename = "timeestimate")]
+    pub remaining_estimate: Option<u64>,
+    /// Contains the sprint field, whose name differs between Jira instances
+    #[serde(flatten)]
+    other: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Sprint {
+    pub name: String,
+    pub state: String,
+    #[serde(rename = "endDate")]
+    pub end_date: Option<DateTime<Utc>>,
+}
+
+impl SprintIssue {
+    pub fn sprints(&self, sprint_field: &str) -> Vec<Sprint> {
+        self.fields
+            .other
+            .get(sprint_field)
+            .cloned()
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Myself {
+    #[serde(rename = "accountId")]
+    account_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JiraProject {
+    pub id: String,
+    pub key: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct JiraIssue {
     pub id: String,
     pub key: String,
     pub fields: IssueFields,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct IssueFields {
     pub summary: String,
+    pub status: Option<StatusField>,
+    #[serde(rename = "issuetype")]
+    pub issue_type: Option<IconField>,
+    pub priority: Option<IconField>,
+    pub project: Option<ProjectField>,
+    /// Original estimate in seconds
+    #[serde(rename = "timeoriginalestimate")]
+    pub original_estimate: Option<u64>,
+    /// Logged time in seconds
+    #[serde(rename = "timespent")]
+    pub time_spent: Option<u64>,
+    /// Not requested from Jira, only given in webhooks, see `jira_webhook.rs`
+    #[serde(default)]
+    pub assignee: Option<UserField>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserField {
+    #[serde(rename = "accountId")]
+    pub account_id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatusField {
+    pub name: String,
+    #[serde(rename = "statusCategory")]
+    pub category: Option<StatusCategory>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatusCategory {
+    pub key: String,
+}
+
+impl StatusField {
+    pub fn is_done(&self) -> bool {
+        self.category.as_ref().is_some_and(|c| c.key == "done")
+    }
+
+    pub fn is_in_progress(&self) -> bool {
+        self.category
+            .as_ref()
+            .is_some_and(|c| c.key == "indeterminate")
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IconField {
+    pub name: String,
+    #[serde(rename = "iconUrl")]
+    pub icon_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProjectField {
+    pub id: String,
+    #[serde(rename = "avatarUrls", default)]
+    pub avatar_urls: HashMap<String, String>,
+}
+
+impl From<&IconField> for IconReference {
+    fn from(value: &IconField) -> Self {
+        Self {
+            name: value.name.clone(),
+            icon_url: value.icon_url.clone(),
+        }
+    }
+}
+
+impl From<&JiraIssue> for IssueDetails {
+    fn from(issue: &JiraIssue) -> Self {
+        let fields = &issue.fields;
+        Self {
+            summary: fields.summary.clone(),
+            status: fields.status.as_ref().map(|s| s.name.clone()),
+            issue_type: fields.issue_type.as_ref().map(Into::into),
+            priority: fields.priority.as_ref().map(Into::into),
+            avatar_url: fields
+                .project
+                .as_ref()
+                .and_then(|p| p.avatar_urls.get("48x48").cloned()),
+            project_id: fields.project.as_ref().map(|p| p.id.clone()),
+        }
+    }
 }
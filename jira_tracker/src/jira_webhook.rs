@@ -0,0 +1,93 @@
+//! Jira webhook on `POST /webhooks/jira` creating paused trackers for the issues picked up in
+//! Jira: assigned to the own account, or moved to a status of the category In Progress by it.
+//! Requests are authenticated by the `X-Hub-Signature` Jira sends for webhooks with a secret.
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::jira_api::{JiraIssue, UserField};
+
+#[derive(Debug, Deserialize)]
+pub struct JiraWebhookEvent {
+    #[serde(rename = "webhookEvent")]
+    event: String,
+    /// Who caused the event
+    user: Option<UserField>,
+    issue: Option<JiraIssue>,
+    changelog: Option<Changelog>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Changelog {
+    #[serde(default)]
+    items: Vec<ChangeItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangeItem {
+    field: String,
+    /// New value, the account id for assignees
+    to: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    Assigned,
+    InProgress,
+}
+
+impl JiraWebhookEvent {
+    fn changed(&self, field: &str) -> Option<&ChangeItem> {
+        self.changelog
+            .as_ref()?
+            .items
+            .iter()
+            .find(|item| item.field == field)
+    }
+
+    /// The issue with the reason to track it, if the event picked it up for `account_id`
+    pub fn trigger(&self, account_id: &str) -> Option<(&JiraIssue, Trigger)> {
+        let issue = self.issue.as_ref()?;
+        let assigned = match self.event.as_str() {
+            "jira:issue_created" => issue.fields.assignee.as_ref().map(|a| &a.account_id),
+            _ => self.changed("assignee").and_then(|item| item.to.as_ref()),
+        };
+        if assigned.is_some_and(|assignee| assignee == account_id) {
+            return Some((issue, Trigger::Assigned));
+        }
+        let by_me = self
+            .user
+            .as_ref()
+            .is_some_and(|user| user.account_id == account_id);
+        let in_progress = issue
+            .fields
+            .status
+            .as_ref()
+            .is_some_and(|status| status.is_in_progress());
+        (by_me && in_progress && self.changed("status").is_some())
+            .then_some((issue, Trigger::InProgress))
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Checks `sha256=<hex HMAC of the body>`
+pub fn verify_signature(secret: &str, signature: Option<&str>, body: &[u8]) -> bool {
+    let Some(signature) = signature
+        .and_then(|header| header.strip_prefix("sha256="))
+        .and_then(|signature| decode_hex(signature.trim()))
+    else {
+        return false;
+    };
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
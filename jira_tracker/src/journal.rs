@@ -0,0 +1,172 @@
+//! Hand-editable YAML journal of a week's trackers, one file per ISO week
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{Datelike, IsoWeek, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+use domain::{Detail, TrackerInformation};
+
+use crate::app_data::AppData;
+use crate::commands;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Journal {
+    week: String,
+    days: Vec<JournalDay>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalDay {
+    date: NaiveDate,
+    entries: Vec<JournalEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalEntry {
+    key: String,
+    #[serde(with = "humantime_serde")]
+    duration: Duration,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum JournalError {
+    InvalidWeek(String),
+    IO(String),
+    /// The journal could not be parsed or does not match the trackers
+    Invalid(Vec<String>),
+}
+
+impl IntoResponse for JournalError {
+    fn into_response(self) -> Response {
+        match self {
+            JournalError::InvalidWeek(week) => (
+                StatusCode::BAD_REQUEST,
+                format!("invalid week '{}', expected e.g. 2025-W07", week),
+            )
+                .into_response(),
+            JournalError::IO(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+            JournalError::Invalid(problems) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, Json(problems)).into_response()
+            }
+        }
+    }
+}
+
+pub fn week_name(week: IsoWeek) -> String {
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+/// Parses weeks like `2025-W07`
+pub fn parse_week(week: &str) -> Result<IsoWeek, JournalError> {
+    week.split_once("-W")
+        .and_then(|(year, week)| {
+            NaiveDate::from_isoywd_opt(year.parse().ok()?, week.parse().ok()?, Weekday::Mon)
+        })
+        .map(|monday| monday.iso_week())
+        .ok_or_else(|| JournalError::InvalidWeek(week.to_string()))
+}
+
+/// Location of the journal of `week` in `directory`
+pub fn journal_file(directory: &Path, week: IsoWeek) -> PathBuf {
+    directory.join(format!("{}.yaml", week_name(week)))
+}
+
+impl Journal {
+    fn new(week: IsoWeek, trackers: &[TrackerInformation]) -> Self {
+        let monday = NaiveDate::from_isoywd_opt(week.year(), week.week(), Weekday::Mon).unwrap();
+        let days = monday
+            .iter_days()
+            .take(7)
+            .map(|date| JournalDay {
+                date,
+                entries: trackers
+                    .iter()
+                    .filter(|t| t.start_time.date_naive() == date)
+                    .map(|t| JournalEntry {
+                        key: t.key.clone(),
+                        duration: t.duration,
+                        description: t.description.clone(),
+                    })
+                    .collect(),
+            })
+            .filter(|day| !day.entries.is_empty())
+            .collect();
+        Self {
+            week: week_name(week),
+            days,
+        }
+    }
+
+    fn validate(&self, week: IsoWeek, trackers: &[TrackerInformation]) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.week != week_name(week) {
+            problems.push(format!("journal is for week {}", self.week));
+        }
+        let mut seen = HashSet::new();
+        for day in &self.days {
+            for entry in &day.entries {
+                if !seen.insert(&entry.key) {
+                    problems.push(format!("{} appears more than once", entry.key));
+                }
+                match trackers.iter().find(|t| t.key == entry.key) {
+                    None => problems.push(format!("{} has no tracker", entry.key)),
+                    Some(tracker) if tracker.start_time.date_naive() != day.date => {
+                        problems.push(format!("{} was not tracked on {}", entry.key, day.date))
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        problems
+    }
+}
+
+/// Writes the journal of `week`, returning its location
+pub fn export(data: &AppData, directory: &Path, week: IsoWeek) -> Result<PathBuf, JournalError> {
+    let journal = Journal::new(week, &data.list_trackers(Detail::Summary));
+    let yaml = serde_yaml::to_string(&journal).map_err(|e| JournalError::IO(e.to_string()))?;
+    fs::create_dir_all(directory).map_err(|e| JournalError::IO(e.to_string()))?;
+    let path = journal_file(directory, week);
+    fs::write(&path, yaml).map_err(|e| JournalError::IO(e.to_string()))?;
+    Ok(path)
+}
+
+/// Applies descriptions and durations corrected in the journal of `week`. Nothing is changed if
+/// the journal is invalid or one of the corrections fails. Returns the keys of the corrected
+/// trackers.
+pub fn import(
+    data: &AppData,
+    directory: &Path,
+    week: IsoWeek,
+) -> Result<Vec<String>, JournalError> {
+    let yaml = fs::read_to_string(journal_file(directory, week))
+        .map_err(|e| JournalError::IO(e.to_string()))?;
+    let journal: Journal =
+        serde_yaml::from_str(&yaml).map_err(|e| JournalError::Invalid(vec![e.to_string()]))?;
+    let trackers = data.list_trackers(Detail::Summary);
+    let problems = journal.validate(week, &trackers);
+    if !problems.is_empty() {
+        return Err(JournalError::Invalid(problems));
+    }
+
+    let corrections = journal
+        .days
+        .into_iter()
+        .flat_map(|day| day.entries)
+        .map(|entry| {
+            let description = entry.description.filter(|d| !d.is_empty());
+            (entry.key, description, entry.duration)
+        })
+        .collect();
+    data.correct_all(corrections)
+        .map_err(|(key, e)| JournalError::Invalid(vec![commands::describe_error(&key, e)]))
+}
@@ -0,0 +1,252 @@
+//! Single writer lease in the SQLite database, so that only one of several instances sharing the
+//! state changes it. The others answer mutating requests and `GET /ready` with 503 until they take
+//! over an expired lease. Without lease, an advisory lock next to the state file keeps a second
+//! instance from starting.
+
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use std::{fs, io};
+
+use axum::extract::State;
+use axum::http::{Method, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use rusqlite::{params, Connection};
+
+use crate::app_data::AppData;
+use crate::clock;
+use crate::config::AppConfig;
+
+#[derive(Debug)]
+pub enum LeaseError {
+    Sqlite(rusqlite::Error),
+    IO(io::Error),
+    /// Another instance owns the state, holding the lock file
+    Locked(PathBuf),
+}
+
+impl Display for LeaseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LeaseError::Sqlite(e) => write!(f, "{}", e),
+            LeaseError::IO(e) => write!(f, "{}", e),
+            LeaseError::Locked(path) => write!(
+                f,
+                "another instance owns the state, locked by {}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for LeaseError {}
+
+impl From<rusqlite::Error> for LeaseError {
+    fn from(e: rusqlite::Error) -> Self {
+        LeaseError::Sqlite(e)
+    }
+}
+
+impl From<io::Error> for LeaseError {
+    fn from(e: io::Error) -> Self {
+        LeaseError::IO(e)
+    }
+}
+
+struct Lease {
+    connection: Connection,
+    duration: Duration,
+}
+
+pub struct WriterLease {
+    holder: String,
+    /// `None` if there is no lease to hold, as there is only a single instance
+    lease: Option<Mutex<Lease>>,
+    held: AtomicBool,
+    /// Never held, as configured with `READ_ONLY`
+    read_only: bool,
+    /// Advisory lock on the state of a single instance, released when dropped
+    _lock: Option<File>,
+}
+
+impl WriterLease {
+    /// Lease that is always held
+    pub fn single() -> Self {
+        Self {
+            holder: String::new(),
+            lease: None,
+            held: AtomicBool::new(true),
+            read_only: false,
+            _lock: None,
+        }
+    }
+
+    /// Lease that is never held, leaving the state to another instance without locking it
+    pub fn read_only() -> Self {
+        Self {
+            held: AtomicBool::new(false),
+            read_only: true,
+            ..Self::single()
+        }
+    }
+
+    /// Lease that is held as long as no other instance runs on the same state, see
+    /// [`WriterLease::for_config`]
+    pub fn locked<P: AsRef<Path>>(path: P, read_only_if_locked: bool) -> Result<Self, LeaseError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Self {
+                _lock: Some(file),
+                ..Self::single()
+            }),
+            Err(TryLockError::WouldBlock) if read_only_if_locked => {
+                tracing::warn!(
+                    "another instance owns the state, starting read-only as {} is locked",
+                    path.display()
+                );
+                Ok(Self {
+                    held: AtomicBool::new(false),
+                    ..Self::single()
+                })
+            }
+            Err(TryLockError::WouldBlock) => Err(LeaseError::Locked(path.to_path_buf())),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+
+    /// Lease in the SQLite database with `WRITER_LEASE`, otherwise [`WriterLease::locked`] on the
+    /// lock file next to the state file. Never held with `READ_ONLY`. The configuration is
+    /// expected to be validated, see [`AppConfig::validate`].
+    pub fn for_config(config: &AppConfig) -> Result<Self, LeaseError> {
+        if config.read_only {
+            return Ok(Self::read_only());
+        }
+        match config.writer_lease {
+            Some(duration) => Ok(Self::open(config.sqlite_file(), duration)?),
+            None => Self::locked(config.lock_file(), config.read_only_if_locked),
+        }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P, duration: Duration) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS writer_lease (
+                 id INTEGER PRIMARY KEY CHECK (id = 0),
+                 holder TEXT NOT NULL,
+                 expires_at INTEGER NOT NULL
+             );",
+        )?;
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+        Ok(Self {
+            holder: format!("{}-{}", host, std::process::id()),
+            lease: Some(Mutex::new(Lease {
+                connection,
+                duration,
+            })),
+            held: AtomicBool::new(false),
+            read_only: false,
+            _lock: None,
+        })
+    }
+
+    pub fn held(&self) -> bool {
+        self.held.load(Ordering::Acquire)
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Why this instance must not change the state now, `None` if it may
+    pub fn refusal(&self) -> Option<(StatusCode, &'static str)> {
+        if self.read_only {
+            return Some((StatusCode::FORBIDDEN, "read-only mode"));
+        }
+        let standby = "standby, another instance is the writer";
+        (!self.held()).then_some((StatusCode::SERVICE_UNAVAILABLE, standby))
+    }
+
+    /// Takes or extends the lease, returns whether this instance became the writer
+    pub fn renew(&self) -> rusqlite::Result<bool> {
+        let Some(lease) = &self.lease else {
+            return Ok(false);
+        };
+        let lease = lease.lock().unwrap();
+        let now = clock::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let changed = lease.connection.execute(
+            "INSERT INTO writer_lease (id, holder, expires_at) VALUES (0, ?1, ?2)
+             ON CONFLICT (id) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+             WHERE holder = excluded.holder OR expires_at < ?3",
+            params![self.holder, now + lease.duration.as_secs(), now],
+        )?;
+        let held = changed > 0;
+        let was_held = self.held.swap(held, Ordering::AcqRel);
+        if held != was_held {
+            tracing::info!(
+                "{} writer lease as {}",
+                if held { "took" } else { "lost" },
+                self.holder
+            );
+        }
+        Ok(held && !was_held)
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        self.lease
+            .as_ref()
+            .map(|lease| lease.lock().unwrap().duration / 3)
+    }
+}
+
+/// Renews the lease three times per lease duration. The state is reloaded when taking over, as the
+/// previous writer changed it in the meantime.
+pub fn spawn_renewal(lease: Arc<WriterLease>, data: Arc<AppData>) {
+    let Some(interval) = lease.interval() else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            match lease.renew() {
+                Ok(true) => data.reload_state(),
+                Ok(false) => {}
+                Err(e) => {
+                    tracing::warn!("could not renew writer lease: {}", e);
+                    lease.held.store(false, Ordering::Release);
+                }
+            }
+        }
+    });
+}
+
+/// Middleware answering mutating requests with 503 while another instance holds the lease or owns
+/// the state, and with 403 in read-only mode
+pub async fn reject_standby<B>(
+    State(lease): State<Arc<WriterLease>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let mutating = request.method() != Method::GET && request.method() != Method::HEAD;
+    if let Some(refusal) = lease.refusal().filter(|_| mutating) {
+        return refusal.into_response();
+    }
+    next.run(request).await
+}
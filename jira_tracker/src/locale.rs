@@ -0,0 +1,164 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Label {
+    Report,
+    Week,
+    Issue,
+    Description,
+    Duration,
+    Total,
+    NoEntries,
+    Trackers,
+    Status,
+    Running,
+    Paused,
+    Start,
+    Pause,
+    Adjust,
+    Track,
+    Goals,
+}
+
+const EN_WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+const DE_WEEKDAYS: [&str; 7] = [
+    "Montag",
+    "Dienstag",
+    "Mittwoch",
+    "Donnerstag",
+    "Freitag",
+    "Samstag",
+    "Sonntag",
+];
+const EN_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+const DE_MONTHS: [&str; 12] = [
+    "Januar",
+    "Februar",
+    "März",
+    "April",
+    "Mai",
+    "Juni",
+    "Juli",
+    "August",
+    "September",
+    "Oktober",
+    "November",
+    "Dezember",
+];
+
+impl Locale {
+    /// Language tag for the `lang` attribute of HTML pages
+    pub fn lang(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+        }
+    }
+
+    pub fn week_start(&self) -> Weekday {
+        match self {
+            Locale::En => Weekday::Sun,
+            Locale::De => Weekday::Mon,
+        }
+    }
+
+    pub fn weekday_name(&self, weekday: Weekday) -> &'static str {
+        let index = weekday.num_days_from_monday() as usize;
+        match self {
+            Locale::En => EN_WEEKDAYS[index],
+            Locale::De => DE_WEEKDAYS[index],
+        }
+    }
+
+    fn month_name(&self, month0: u32) -> &'static str {
+        match self {
+            Locale::En => EN_MONTHS[month0 as usize],
+            Locale::De => DE_MONTHS[month0 as usize],
+        }
+    }
+
+    /// Long date, e.g. "Monday, May 5, 2025" or "Montag, 5. Mai 2025"
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        let weekday = self.weekday_name(date.weekday());
+        let month = self.month_name(date.month0());
+        match self {
+            Locale::En => format!("{}, {} {}, {}", weekday, month, date.day(), date.year()),
+            Locale::De => format!("{}, {}. {} {}", weekday, date.day(), month, date.year()),
+        }
+    }
+
+    /// Short numeric date, e.g. "05/05/2025" or "05.05.2025"
+    pub fn format_short_date(&self, date: NaiveDate) -> String {
+        match self {
+            Locale::En => date.format("%m/%d/%Y").to_string(),
+            Locale::De => date.format("%d.%m.%Y").to_string(),
+        }
+    }
+
+    pub fn label(&self, label: Label) -> &'static str {
+        match (self, label) {
+            (Locale::En, Label::Report) => "Report",
+            (Locale::En, Label::Week) => "Week",
+            (Locale::En, Label::Issue) => "Issue",
+            (Locale::En, Label::Description) => "Description",
+            (Locale::En, Label::Duration) => "Duration",
+            (Locale::En, Label::Total) => "Total",
+            (Locale::En, Label::NoEntries) => "No entries",
+            (Locale::En, Label::Trackers) => "Trackers",
+            (Locale::En, Label::Status) => "Status",
+            (Locale::En, Label::Running) => "running",
+            (Locale::En, Label::Paused) => "paused",
+            (Locale::En, Label::Start) => "Start",
+            (Locale::En, Label::Pause) => "Pause",
+            (Locale::En, Label::Adjust) => "Adjust",
+            (Locale::En, Label::Track) => "Track issue",
+            (Locale::En, Label::Goals) => "Goals",
+            (Locale::De, Label::Report) => "Bericht",
+            (Locale::De, Label::Week) => "Woche",
+            (Locale::De, Label::Issue) => "Vorgang",
+            (Locale::De, Label::Description) => "Beschreibung",
+            (Locale::De, Label::Duration) => "Dauer",
+            (Locale::De, Label::Total) => "Gesamt",
+            (Locale::De, Label::NoEntries) => "Keine Einträge",
+            (Locale::De, Label::Trackers) => "Tracker",
+            (Locale::De, Label::Status) => "Status",
+            (Locale::De, Label::Running) => "läuft",
+            (Locale::De, Label::Paused) => "pausiert",
+            (Locale::De, Label::Start) => "Starten",
+            (Locale::De, Label::Pause) => "Pausieren",
+            (Locale::De, Label::Adjust) => "Anpassen",
+            (Locale::De, Label::Track) => "Vorgang erfassen",
+            (Locale::De, Label::Goals) => "Ziele",
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! Email-to-tracker gateway for machines where nothing but a mail client may run. Unread mails of
+//! allowed senders in the IMAP inbox are applied as commands from their subject, e.g.
+//! `<secret> track ABC-1 30m meeting with client`, and answered via SMTP with the result. As the
+//! `From` header is easily forged, the subject has to start with the secret of the gateway.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_native_tls::native_tls;
+use tokio_native_tls::TlsStream;
+
+use crate::app_data::AppData;
+use crate::auth_guard;
+use crate::commands::CommandContext;
+use crate::issue_cache::IssueCache;
+use crate::jira_api::JiraApi;
+use crate::rounding::DisplayRounding;
+use crate::scheduler::{JobResult, Scheduler};
+
+#[derive(Debug)]
+pub enum MailError {
+    IO(std::io::Error),
+    Tls(native_tls::Error),
+    /// Unexpected answer of the server
+    Protocol(String),
+}
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailError::IO(e) => write!(f, "{}", e),
+            MailError::Tls(e) => write!(f, "{}", e),
+            MailError::Protocol(answer) => write!(f, "unexpected answer: {}", answer.trim_end()),
+        }
+    }
+}
+
+impl From<std::io::Error> for MailError {
+    fn from(e: std::io::Error) -> Self {
+        MailError::IO(e)
+    }
+}
+
+impl From<native_tls::Error> for MailError {
+    fn from(e: native_tls::Error) -> Self {
+        MailError::Tls(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MailGateway {
+    /// `host:port` of the IMAP server, using implicit TLS
+    pub imap_server: String,
+    /// `host:port` of the SMTP server for replies, using implicit TLS. No replies without it.
+    pub smtp_server: Option<String>,
+    /// Login of both servers and sender address of replies
+    pub username: String,
+    pub password: String,
+    /// Mails of other senders are ignored
+    pub senders: Vec<String>,
+    /// First word of the subject of each command, mails without it are ignored
+    pub secret: String,
+    pub aliases: HashMap<String, String>,
+    pub display: DisplayRounding,
+    pub poll_interval: Duration,
+}
+
+/// Subject without reply and forward prefixes
+fn strip_reply_prefixes(subject: &str) -> &str {
+    let mut subject = subject.trim();
+    while let Some((prefix, rest)) = subject.split_once(':') {
+        if !["re", "aw", "fw", "fwd", "wg"].contains(&prefix.trim().to_lowercase().as_str()) {
+            break;
+        }
+        subject = rest.trim();
+    }
+    subject
+}
+
+/// Command of the subject if it starts with `secret`
+fn authenticated_command<'a>(subject: &'a str, secret: &str) -> Option<&'a str> {
+    let subject = strip_reply_prefixes(subject);
+    let (token, command) = subject
+        .split_once(char::is_whitespace)
+        .unwrap_or((subject, ""));
+    auth_guard::tokens_match(token, secret).then(|| command.trim())
+}
+
+/// Unread mail, identified by its IMAP uid
+struct Mail {
+    uid: u32,
+    from: String,
+    subject: String,
+}
+
+impl Mail {
+    /// Reads `From` and `Subject` of a header, unfolding continued lines. Encoded words are kept.
+    fn parse(uid: u32, header: &str) -> Self {
+        let unfolded = header.replace("\r\n ", " ").replace("\r\n\t", " ");
+        let field = |name: &str| {
+            unfolded
+                .lines()
+                .find_map(|line| {
+                    let (field, value) = line.split_once(':')?;
+                    field
+                        .eq_ignore_ascii_case(name)
+                        .then(|| value.trim().to_string())
+                })
+                .unwrap_or_default()
+        };
+        let from = field("From");
+        let from = match (from.rfind('<'), from.rfind('>')) {
+            (Some(start), Some(end)) if start < end => from[start + 1..end].to_string(),
+            _ => from,
+        };
+        Mail {
+            uid,
+            from,
+            subject: field("Subject"),
+        }
+    }
+}
+
+type Stream = BufReader<TlsStream<TcpStream>>;
+
+async fn connect(server: &str) -> Result<Stream, MailError> {
+    let host = server.rsplit_once(':').map_or(server, |(host, _)| host);
+    let connector = tokio_native_tls::TlsConnector::from(native_tls::TlsConnector::new()?);
+    let tcp = TcpStream::connect(server).await?;
+    Ok(BufReader::new(connector.connect(host, tcp).await?))
+}
+
+async fn read_line(stream: &mut Stream) -> Result<String, MailError> {
+    let mut line = String::new();
+    if stream.read_line(&mut line).await? == 0 {
+        return Err(MailError::Protocol("connection closed".to_string()));
+    }
+    Ok(line)
+}
+
+/// IMAP quoted string
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+struct ImapSession {
+    stream: Stream,
+    tag: u32,
+}
+
+impl ImapSession {
+    async fn login(gateway: &MailGateway) -> Result<Self, MailError> {
+        let mut stream = connect(&gateway.imap_server).await?;
+        read_line(&mut stream).await?;
+        let mut session = ImapSession { stream, tag: 0 };
+        let login = format!(
+            "LOGIN {} {}",
+            quote(&gateway.username),
+            quote(&gateway.password)
+        );
+        session.command(&login).await?;
+        session.command("SELECT INBOX").await?;
+        Ok(session)
+    }
+
+    /// Untagged response of the command with literals inlined, failing unless it completes with OK
+    async fn command(&mut self, command: &str) -> Result<String, MailError> {
+        self.tag += 1;
+        let tag = format!("a{}", self.tag);
+        let request = format!("{} {}\r\n", tag, command);
+        self.stream.get_mut().write_all(request.as_bytes()).await?;
+        let mut response = String::new();
+        loop {
+            let line = read_line(&mut self.stream).await?;
+            if let Some(status) = line.strip_prefix(&format!("{} ", tag)) {
+                return if status.starts_with("OK") {
+                    Ok(response)
+                } else {
+                    Err(MailError::Protocol(line))
+                };
+            }
+            let literal = line
+                .trim_end()
+                .strip_suffix('}')
+                .and_then(|line| line.rsplit_once('{'))
+                .and_then(|(_, size)| size.parse::<usize>().ok());
+            response.push_str(&line);
+            if let Some(size) = literal {
+                let mut bytes = vec![0; size];
+                self.stream.read_exact(&mut bytes).await?;
+                response.push_str(&String::from_utf8_lossy(&bytes));
+            }
+        }
+    }
+
+    async fn unread(&mut self) -> Result<Vec<Mail>, MailError> {
+        let response = self.command("UID SEARCH UNSEEN").await?;
+        let uids: Vec<u32> = response
+            .lines()
+            .filter_map(|line| line.strip_prefix("* SEARCH"))
+            .flat_map(str::split_whitespace)
+            .filter_map(|uid| uid.parse().ok())
+            .collect();
+        let mut mails = Vec::new();
+        for uid in uids {
+            let fetch = format!(
+                "UID FETCH {} (BODY.PEEK[HEADER.FIELDS (FROM SUBJECT)])",
+                uid
+            );
+            let header = self.command(&fetch).await?;
+            let header = header.split_once("}\r\n").map_or("", |(_, header)| header);
+            mails.push(Mail::parse(uid, header));
+        }
+        Ok(mails)
+    }
+
+    async fn mark_read(&mut self, uid: u32) -> Result<(), MailError> {
+        let store = format!("UID STORE {} +FLAGS (\\Seen)", uid);
+        self.command(&store).await.map(|_| ())
+    }
+
+    async fn logout(mut self) -> Result<(), MailError> {
+        self.command("LOGOUT").await.map(|_| ())
+    }
+}
+
+/// Reads an SMTP reply, which may span several lines, failing unless its code is `expected`
+async fn expect_reply(stream: &mut Stream, expected: &str) -> Result<(), MailError> {
+    loop {
+        let line = read_line(stream).await?;
+        if !line.starts_with(expected) {
+            return Err(MailError::Protocol(line));
+        }
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok(());
+        }
+    }
+}
+
+async fn send_reply(
+    gateway: &MailGateway,
+    server: &str,
+    to: &str,
+    subject: &str,
+    body: &str,
+) -> Result<(), MailError> {
+    let mut stream = connect(server).await?;
+    expect_reply(&mut stream, "220").await?;
+    let credentials = format!("\0{}\0{}", gateway.username, gateway.password);
+    let steps = [
+        ("EHLO jira-tracker".to_string(), "250"),
+        (
+            format!("AUTH PLAIN {}", STANDARD.encode(credentials)),
+            "235",
+        ),
+        (format!("MAIL FROM:<{}>", gateway.username), "250"),
+        (format!("RCPT TO:<{}>", to), "250"),
+        ("DATA".to_string(), "354"),
+    ];
+    for (command, expected) in steps {
+        let line = format!("{}\r\n", command);
+        stream.get_mut().write_all(line.as_bytes()).await?;
+        expect_reply(&mut stream, expected).await?;
+    }
+    let body = body.replace("\n.", "\n..").replace('\n', "\r\n");
+    let message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: Re: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n.\r\n",
+        gateway.username, to, subject, body
+    );
+    stream.get_mut().write_all(message.as_bytes()).await?;
+    expect_reply(&mut stream, "250").await?;
+    stream.get_mut().write_all(b"QUIT\r\n").await?;
+    Ok(())
+}
+
+impl MailGateway {
+    async fn poll(&self, data: &AppData, jira: &JiraApi, cache: &IssueCache) -> JobResult {
+        let mut session = ImapSession::login(self).await.map_err(|e| e.to_string())?;
+        let mails = session.unread().await.map_err(|e| e.to_string())?;
+        let mut applied = 0;
+        for mail in mails {
+            // other mails are left unread for the mail client
+            if !self
+                .senders
+                .iter()
+                .any(|s| s.eq_ignore_ascii_case(&mail.from))
+            {
+                continue;
+            }
+            let Some(command) = authenticated_command(&mail.subject, &self.secret) else {
+                tracing::warn!("ignoring mail of {} without the secret", mail.from);
+                continue;
+            };
+            // marked first, so that a failing mail is not applied again on every poll
+            session
+                .mark_read(mail.uid)
+                .await
+                .map_err(|e| e.to_string())?;
+            let context = CommandContext {
+                data,
+                jira,
+                cache,
+                aliases: &self.aliases,
+                display: self.display,
+            };
+            let reply = context.apply(command).await;
+            applied += 1;
+            if let Some(server) = &self.smtp_server {
+                if let Err(e) = send_reply(self, server, &mail.from, command, &reply).await {
+                    tracing::warn!("could not reply to {}: {}", mail.from, e);
+                }
+            }
+        }
+        session.logout().await.map_err(|e| e.to_string())?;
+        Ok(format!("applied {} mails", applied))
+    }
+}
+
+pub fn schedule_mail_poll(
+    scheduler: &Scheduler,
+    gateway: MailGateway,
+    data: Arc<AppData>,
+    jira: Arc<JiraApi>,
+    cache: Arc<IssueCache>,
+) {
+    let gateway = Arc::new(gateway);
+    scheduler.add("mail_poll", gateway.poll_interval, move || {
+        let (gateway, data) = (gateway.clone(), data.clone());
+        let (jira, cache) = (jira.clone(), cache.clone());
+        async move { gateway.poll(&data, &jira, &cache).await }
+    });
+}
@@ -2,84 +2,514 @@
 extern crate core;
 
 use std::error::Error;
+use std::future::Future;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
 use axum::extract::FromRef;
-use axum::ServiceExt;
+use axum::http::Request;
+use axum::{middleware, Router, ServiceExt};
+use tower::util::MapRequestLayer;
+use tower::Layer;
 use tower_http::normalize_path::NormalizePath;
 
+use crate::activity::ActivityLog;
 use crate::app_data::AppData;
+use crate::auth_guard::AuthGuard;
+use crate::clients::ClientRegistry;
 use crate::config::AppConfig;
+use crate::descriptions::CommitFeed;
+use crate::idempotency::IdempotencyCache;
+use crate::issue_cache::IssueCache;
 use crate::jira_api::JiraApi;
+use crate::lease::WriterLease;
+use crate::project_cache::ProjectCache;
+use crate::scheduler::Scheduler;
+use crate::team::Team;
 use crate::tempo_api::TempoApi;
+use crate::update_check::UpdateCheck;
+use crate::week_archive::WeekArchive;
 
+mod accounts;
+mod activity;
+mod anomalies;
 mod app_data;
+mod approver;
+mod audit;
+mod auth_guard;
+mod chatops;
+mod clients;
+mod clock;
+mod clockify;
+mod commands;
 mod config;
+mod day;
+mod descriptions;
+mod diagnostics;
+mod events;
 mod files;
+mod filter;
+mod freeze;
+mod goals;
+mod html;
+mod idempotency;
+mod issue_cache;
 mod jira_api;
+mod jira_webhook;
+mod journal;
+mod lease;
+mod locale;
+mod mail;
+mod plans;
+mod project_cache;
+mod report;
+mod review;
+mod rounding;
+mod routing;
+mod sandbox;
+mod schedule;
+mod scheduler;
+mod selection;
+#[cfg(windows)]
+mod service;
+mod sprint;
+mod storage;
+mod team;
 mod tempo_api;
+mod time_import;
+mod toggl;
+mod ui;
+mod update_check;
+mod upstream;
 mod web;
+mod websocket;
+mod week_archive;
+mod write_ahead;
+
+/// Header selecting the profile of a request instead of the `/profiles/<name>` prefix
+const PROFILE_HEADER: &str = "x-profile";
 
 #[derive(Clone)]
 pub struct AppState {
+    activity: Arc<ActivityLog>,
+    auth_guard: Arc<AuthGuard>,
+    clients: Arc<ClientRegistry>,
+    commit_feed: Arc<CommitFeed>,
+    config: Arc<AppConfig>,
     data: Arc<AppData>,
+    idempotency: Arc<IdempotencyCache>,
+    issue_cache: Arc<IssueCache>,
     jira_api: Arc<JiraApi>,
+    lease: Arc<WriterLease>,
+    project_cache: Arc<ProjectCache>,
+    scheduler: Arc<Scheduler>,
+    team: Arc<Team>,
     tempo_api: Arc<TempoApi>,
+    update_check: Arc<UpdateCheck>,
 }
 
 impl AppState {
-    async fn create(config: &AppConfig) -> Result<Self, Box<dyn Error>> {
+    /// Configured account id, or the one of the Jira user. Falls back to the last resolved
+    /// account id if Jira is unreachable.
+    async fn resolve_account_id(
+        config: &AppConfig,
+        jira_api: &JiraApi,
+        data: &AppData,
+    ) -> Result<String, Box<dyn Error>> {
+        if let Some(account_id) = &config.jira_account_id {
+            return Ok(account_id.clone());
+        }
+        match jira_api.get_account_id().await {
+            Ok(account_id) => {
+                data.set_account_id(&account_id);
+                Ok(account_id)
+            }
+            Err(e) => match data.account_id() {
+                Some(account_id) => {
+                    tracing::warn!("could not resolve Jira account id, using cached one: {}", e);
+                    Ok(account_id)
+                }
+                None => Err(e.into()),
+            },
+        }
+    }
+
+    /// Resolves the issue ids of the trackers migrated from the legacy state, returns the keys
+    /// that could not be resolved. They stay external and are tried again on the next start.
+    async fn resolve_legacy_trackers(jira_api: &JiraApi, data: &AppData) -> Vec<String> {
+        let mut unresolved = Vec::new();
+        for key in data.legacy_keys() {
+            match jira_api.get_issue_info(&key).await {
+                Ok(issue) => {
+                    tracing::info!("resolved issue id {} of tracker {}", issue.id, key);
+                    data.resolve_legacy(&key, &issue.id, Some((&issue).into()))
+                        .unwrap();
+                }
+                Err(e) => {
+                    tracing::warn!("could not resolve issue id of tracker {}: {}", key, e);
+                    unresolved.push(key);
+                }
+            }
+        }
+        unresolved
+    }
+
+    /// `auth_guard` is shared by all profiles, so that failures count across them, and so is
+    /// `update_check`, which is run by the main profile
+    async fn create(
+        config: &AppConfig,
+        auth_guard: Arc<AuthGuard>,
+        update_check: Arc<UpdateCheck>,
+    ) -> Result<Self, Box<dyn Error>> {
+        config.validate()?;
+        if config.sandbox {
+            tracing::warn!(
+                "sandbox mode, worklogs are recorded in {:?} instead of being submitted",
+                config.sandbox_file()
+            );
+        }
+        // taken before loading, so that only the writer may write the state while loading it
+        let lease = Arc::new(WriterLease::for_config(config)?);
+        lease.renew()?;
         let jira_api: JiraApi = config.into();
-        let jira_account_id = jira_api.get_account_id().await?;
+        let data: Arc<AppData> = Arc::new((config, lease.as_ref()).into());
+        let jira_account_id = Self::resolve_account_id(config, &jira_api, &data).await?;
+        Self::resolve_legacy_trackers(&jira_api, &data).await;
 
-        let data = Arc::new(config.into());
         let jira_api = Arc::new(jira_api);
         let tempo_api = Arc::new((config, jira_account_id).into());
+        lease::spawn_renewal(lease.clone(), data.clone());
+        app_data::spawn_flush(data.clone(), lease.clone());
+        let scheduler = Arc::new(Scheduler::new(config.jobs_file(), lease.clone()));
+        let issue_cache = Arc::new(IssueCache::new(config.issue_cache_ttl));
+        issue_cache::schedule_prefetch(
+            &scheduler,
+            issue_cache.clone(),
+            jira_api.clone(),
+            config.prefetch_keys(),
+            config.prefetch_interval,
+            config.prefetch_delay,
+        );
+        let project_cache = Arc::new(ProjectCache::new(config.project_cache_ttl));
+        if config.purge_delay.is_some() {
+            app_data::schedule_purge(&scheduler, data.clone());
+        }
+        if let Some(interval) = config.checkpoint_interval {
+            app_data::schedule_checkpoint(&scheduler, data.clone(), interval);
+        }
+        if let Some(interval) = config.compact_interval {
+            app_data::schedule_compaction(&scheduler, data.clone(), interval);
+        }
+        if let Some(target) = &config.week_archive {
+            week_archive::schedule_week_archive(
+                &scheduler,
+                data.clone(),
+                WeekArchive::parse(target)?,
+                config.profile_name.clone(),
+                config.week_archive_interval,
+            );
+        }
+        if let Some(webhook) = config.approver_webhook() {
+            approver::schedule_approver_summary(&scheduler, data.clone(), webhook);
+        }
+        if let Some(gateway) = config.mail_gateway() {
+            mail::schedule_mail_poll(
+                &scheduler,
+                gateway,
+                data.clone(),
+                jira_api.clone(),
+                issue_cache.clone(),
+            );
+        }
+        let team = Arc::new(config.into());
+        if config.update_check && config.profile_name.is_none() {
+            update_check::schedule_update_check(
+                &scheduler,
+                update_check.clone(),
+                config.update_check_interval,
+            );
+        }
+        scheduler.start();
+        let idempotency = Arc::new(IdempotencyCache::new(config.idempotency_window));
+        let config = Arc::new(config.clone());
 
         Ok(Self {
+            activity: Arc::new(ActivityLog::new(config.activity_retention)),
+            auth_guard,
+            clients: Arc::new(ClientRegistry::default()),
+            commit_feed: Arc::new(CommitFeed::default()),
+            config,
             data,
+            idempotency,
+            issue_cache,
             jira_api,
+            lease,
+            project_cache,
+            scheduler,
+            team,
             tempo_api,
+            update_check,
         })
     }
 }
 
+impl FromRef<AppState> for Arc<ActivityLog> {
+    fn from_ref(input: &AppState) -> Self {
+        input.activity.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<AuthGuard> {
+    fn from_ref(input: &AppState) -> Self {
+        input.auth_guard.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<ClientRegistry> {
+    fn from_ref(input: &AppState) -> Self {
+        input.clients.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<CommitFeed> {
+    fn from_ref(input: &AppState) -> Self {
+        input.commit_feed.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<AppConfig> {
+    fn from_ref(input: &AppState) -> Self {
+        input.config.clone()
+    }
+}
+
 impl FromRef<AppState> for Arc<AppData> {
     fn from_ref(input: &AppState) -> Self {
         input.data.clone()
     }
 }
 
+impl FromRef<AppState> for Arc<IdempotencyCache> {
+    fn from_ref(input: &AppState) -> Self {
+        input.idempotency.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<IssueCache> {
+    fn from_ref(input: &AppState) -> Self {
+        input.issue_cache.clone()
+    }
+}
+
 impl FromRef<AppState> for Arc<JiraApi> {
     fn from_ref(input: &AppState) -> Self {
         input.jira_api.clone()
     }
 }
 
+impl FromRef<AppState> for Arc<WriterLease> {
+    fn from_ref(input: &AppState) -> Self {
+        input.lease.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<ProjectCache> {
+    fn from_ref(input: &AppState) -> Self {
+        input.project_cache.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Scheduler> {
+    fn from_ref(input: &AppState) -> Self {
+        input.scheduler.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Team> {
+    fn from_ref(input: &AppState) -> Self {
+        input.team.clone()
+    }
+}
+
 impl FromRef<AppState> for Arc<TempoApi> {
     fn from_ref(input: &AppState) -> Self {
         input.tempo_api.clone()
     }
 }
 
-#[tokio::main]
-async fn main() {
-    let logging_layer = config::setup_logging();
+impl FromRef<AppState> for Arc<UpdateCheck> {
+    fn from_ref(input: &AppState) -> Self {
+        input.update_check.clone()
+    }
+}
 
-    let config = &AppConfig::new();
-    let state: AppState = AppState::create(config).await.unwrap();
-    let cloned_state = state.data.clone();
+/// Routes of one profile with its middleware
+fn app(state: AppState) -> Router {
+    let token_routes = web::token_routes().route_layer(middleware::from_fn_with_state(
+        state.auth_guard.clone(),
+        auth_guard::guard,
+    ));
+    web::router()
+        .merge(token_routes)
+        .layer(middleware::from_fn_with_state(
+            state.idempotency.clone(),
+            idempotency::replay,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.config.clone(),
+            freeze::reject_frozen,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.lease.clone(),
+            lease::reject_standby,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clients.clone(),
+            clients::track,
+        ))
+        .with_state(state)
+}
 
-    let _hotwatch = files::watch_file(&config.json_file, move || cloned_state.reload_state());
+/// Requests with an `X-Profile` header are routed to `/profiles/<name>` of that profile
+fn select_profile<B>(mut request: Request<B>) -> Request<B> {
+    let profile = request
+        .headers()
+        .get(PROFILE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        });
+    if let Some(profile) = profile {
+        let path = request.uri().path().trim_end_matches('/');
+        let query = request.uri().query().map(|query| format!("?{}", query));
+        let uri = format!("/profiles/{}{}{}", profile, path, query.unwrap_or_default());
+        if let Ok(uri) = uri.parse() {
+            *request.uri_mut() = uri;
+        }
+    }
+    request
+}
+
+/// Runs the server until `shutdown` completes. In service mode there is no terminal attached.
+async fn serve<F>(service: bool, shutdown: F)
+where
+    F: Future<Output = ()>,
+{
+    let logging_layer = config::setup_logging(service);
 
-    let router = web::router().layer(logging_layer).with_state(state);
+    let config = &AppConfig::new();
+    let auth_guard = Arc::new(AuthGuard::new(
+        config.auth_ban_after,
+        config.auth_ban_duration,
+    ));
+    let update_check = Arc::new(UpdateCheck::default());
+    let mut states = vec![(
+        None,
+        AppState::create(config, auth_guard.clone(), update_check.clone())
+            .await
+            .unwrap(),
+    )];
+    for name in &config.profiles {
+        let profile = config.profile(name);
+        let state = AppState::create(&profile, auth_guard.clone(), update_check.clone())
+            .await
+            .unwrap();
+        states.push((Some(name), state));
+    }
+
+    let mut router = Router::new();
+    let mut watchers = Vec::new();
+    let mut flushed = Vec::new();
+    for (name, state) in states {
+        let cloned_state = state.data.clone();
+        flushed.push((state.data.clone(), state.lease.clone()));
+        events::spawn_subscriber("log", state.data.subscribe(), |event| {
+            tracing::debug!("tracker event: {:?}", event);
+        });
+        watchers.extend(
+            state
+                .data
+                .watched_file()
+                .map(|path| files::watch_file(path, move || cloned_state.reload_state())),
+        );
+        router = match name {
+            Some(name) => router.nest(&format!("/profiles/{}", name), app(state)),
+            None => router.merge(app(state)),
+        };
+    }
+    let router = MapRequestLayer::new(select_profile).layer(router.layer(logging_layer));
     let app = NormalizePath::trim_trailing_slash(router);
 
     let addr = SocketAddr::from(([127, 0, 0, 1], config.tracker_port));
     tracing::debug!("listening on {}", addr);
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown)
         .await
         .unwrap();
+    // changes of the last requests may not have been flushed yet
+    for (data, lease) in flushed {
+        if lease.held() {
+            data.save();
+        }
+    }
+}
+
+/// `--migrate`: upgrades the state of each profile to the current format and resolves the issue
+/// ids of legacy trackers, without serving
+async fn migrate() -> Result<(), Box<dyn Error>> {
+    let _ = config::setup_logging(false);
+    let config = AppConfig::new();
+    let profiles = config.profiles.iter().map(|name| config.profile(name));
+    for config in std::iter::once(config.clone()).chain(profiles) {
+        config.validate()?;
+        let lease = WriterLease::for_config(&config)?;
+        lease.renew()?;
+        if !lease.held() {
+            return Err(format!("{:?} is used by another instance", config.json_file).into());
+        }
+        let jira_api: JiraApi = (&config).into();
+        let data: AppData = (&config, &lease).into();
+        let unresolved = AppState::resolve_legacy_trackers(&jira_api, &data).await;
+        data.try_save()?;
+        if unresolved.is_empty() {
+            tracing::info!("migrated {:?}", config.json_file);
+        } else {
+            tracing::warn!(
+                "migrated {:?}, trackers without issue id: {}",
+                config.json_file,
+                unresolved.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let service = args.iter().any(|arg| arg == "--service");
+
+    #[cfg(windows)]
+    {
+        if args.iter().any(|arg| arg == "--install-service") {
+            return service::install().unwrap();
+        }
+        if args.iter().any(|arg| arg == "--uninstall-service") {
+            return service::uninstall().unwrap();
+        }
+        if service {
+            return service::run().unwrap();
+        }
+    }
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    if args.iter().any(|arg| arg == "--migrate") {
+        return runtime.block_on(migrate()).unwrap();
+    }
+    runtime.block_on(serve(service, async {
+        tokio::signal::ctrl_c().await.unwrap();
+    }));
 }
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use domain::TrackerInformation;
+
+use crate::tempo_api::{PlanItemType, TempoPlan};
+
+#[derive(Debug, Serialize)]
+pub struct PlannedItem {
+    id: String,
+    #[serde(rename = "type")]
+    item_type: &'static str,
+    #[serde(with = "humantime_serde")]
+    planned: Duration,
+    #[serde(with = "humantime_serde")]
+    tracked: Duration,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlanOverview {
+    plans: Vec<PlannedItem>,
+    /// Keys of trackers whose issue is not covered by any plan
+    unplanned: Vec<String>,
+}
+
+fn covers(plan: &TempoPlan, tracker: &TrackerInformation) -> bool {
+    let item = &plan.plan_item;
+    match item.item_type {
+        PlanItemType::Issue => item.id == tracker.id,
+        PlanItemType::Project => tracker
+            .details
+            .as_ref()
+            .and_then(|d| d.project_id.as_ref())
+            .is_some_and(|project_id| *project_id == item.id),
+        PlanItemType::Other => false,
+    }
+}
+
+impl PlanOverview {
+    /// `trackers` are expected to be requested with full details to match project plans
+    pub fn new(plans: &[TempoPlan], trackers: &[TrackerInformation]) -> Self {
+        let planned_items = plans
+            .iter()
+            .map(|plan| PlannedItem {
+                id: plan.plan_item.id.clone(),
+                item_type: match plan.plan_item.item_type {
+                    PlanItemType::Issue => "issue",
+                    PlanItemType::Project => "project",
+                    PlanItemType::Other => "other",
+                },
+                planned: Duration::from_secs(plan.planned_seconds_per_day),
+                tracked: trackers
+                    .iter()
+                    .filter(|t| covers(plan, t))
+                    .map(|t| t.duration)
+                    .sum(),
+            })
+            .collect();
+        let unplanned = trackers
+            .iter()
+            .filter(|t| !plans.iter().any(|plan| covers(plan, t)))
+            .map(|t| t.key.clone())
+            .collect();
+        Self {
+            plans: planned_items,
+            unplanned,
+        }
+    }
+
+    pub fn unplanned(&self) -> &[String] {
+        &self.unplanned
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use crate::jira_api::{JiraApi, JiraProject};
+use crate::upstream::UpstreamError;
+
+#[derive(Debug)]
+pub struct ProjectCache {
+    ttl: Duration,
+    projects: RwLock<Option<(Instant, Vec<JiraProject>)>>,
+}
+
+impl ProjectCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            projects: RwLock::new(None),
+        }
+    }
+
+    /// Returns all projects, refreshing them from Jira once the cache expired
+    pub async fn get(&self, jira: &JiraApi) -> Result<Vec<JiraProject>, UpstreamError> {
+        if let Some((fetched, projects)) = self.projects.read().unwrap().as_ref() {
+            if fetched.elapsed() < self.ttl {
+                return Ok(projects.clone());
+            }
+        }
+        let projects = jira.get_projects().await?;
+        *self.projects.write().unwrap() = Some((Instant::now(), projects.clone()));
+        Ok(projects)
+    }
+
+    /// Checks whether a project exists. Lookup failures count as existing, so that the issue
+    /// lookup itself decides. Every project exists in the sandbox.
+    pub async fn contains_project(&self, jira: &JiraApi, project_key: &str) -> bool {
+        if jira.is_sandbox() {
+            return true;
+        }
+        match self.get(jira).await {
+            Ok(projects) => projects.iter().any(|p| p.key == project_key),
+            Err(e) => {
+                tracing::warn!("could not load Jira projects: {}", e);
+                true
+            }
+        }
+    }
+}
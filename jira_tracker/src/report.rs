@@ -0,0 +1,331 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use domain::{ReportFormat, TrackerInformation};
+
+use crate::goals::GoalProgress;
+use crate::locale::{Label, Locale};
+use crate::rounding::DisplayRounding;
+
+struct Day<'a> {
+    date: NaiveDate,
+    trackers: Vec<&'a TrackerInformation>,
+}
+
+impl Day<'_> {
+    fn sum(&self, rounding: DisplayRounding) -> Duration {
+        self.trackers
+            .iter()
+            .map(|t| rounding.shown(t.duration))
+            .sum()
+    }
+}
+
+pub struct Report<'a> {
+    locale: Locale,
+    rounding: DisplayRounding,
+    title: String,
+    days: Vec<Day<'a>>,
+    goals: Vec<GoalProgress>,
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn escape_csv(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn week_start_of(date: NaiveDate, week_start: Weekday) -> NaiveDate {
+    let offset =
+        (7 + date.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+    date - Days::new(offset.into())
+}
+
+impl<'a> Report<'a> {
+    pub fn day(locale: Locale, date: NaiveDate, trackers: &'a [TrackerInformation]) -> Self {
+        Self {
+            locale,
+            title: format!(
+                "{}: {}",
+                locale.label(Label::Report),
+                locale.format_date(date)
+            ),
+            days: vec![Day {
+                date,
+                trackers: trackers
+                    .iter()
+                    .filter(|t| t.start_time.date_naive() == date)
+                    .collect(),
+            }],
+            goals: Vec::new(),
+            rounding: DisplayRounding::default(),
+        }
+    }
+
+    pub fn week(
+        locale: Locale,
+        week_start: Weekday,
+        date: NaiveDate,
+        trackers: &'a [TrackerInformation],
+    ) -> Self {
+        let first = week_start_of(date, week_start);
+        let last = first + Days::new(6);
+        let days = first
+            .iter_days()
+            .take(7)
+            .map(|date| Day {
+                date,
+                trackers: trackers
+                    .iter()
+                    .filter(|t| t.start_time.date_naive() == date)
+                    .collect(),
+            })
+            .collect();
+        Self {
+            locale,
+            title: format!(
+                "{}: {} – {}",
+                locale.label(Label::Week),
+                locale.format_short_date(first),
+                locale.format_short_date(last)
+            ),
+            days,
+            goals: Vec::new(),
+            rounding: DisplayRounding::default(),
+        }
+    }
+
+    /// Adds the progress of the goals, not part of CSV
+    pub fn with_goals(self, goals: Vec<GoalProgress>) -> Self {
+        Self { goals, ..self }
+    }
+
+    /// Rounding of the shown durations, CSV keeps the seconds
+    pub fn with_rounding(self, rounding: DisplayRounding) -> Self {
+        Self { rounding, ..self }
+    }
+
+    fn sum(&self) -> Duration {
+        self.days.iter().map(|d| d.sum(self.rounding)).sum()
+    }
+
+    fn non_empty_days(&self) -> impl Iterator<Item = &Day<'a>> {
+        let single = self.days.len() == 1;
+        self.days
+            .iter()
+            .filter(move |d| single || !d.trackers.is_empty())
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Text => self.render_text(),
+            ReportFormat::Markdown => self.render_markdown(),
+            ReportFormat::Html => self.render_html(),
+            ReportFormat::Csv => self.render_csv(),
+            ReportFormat::Json => serde_json::to_string(&self.trackers()).unwrap(),
+        }
+    }
+
+    /// Trackers of all days of the report, with their precise durations
+    pub fn trackers(&self) -> Vec<&'a TrackerInformation> {
+        self.days
+            .iter()
+            .flat_map(|day| day.trackers.iter().copied())
+            .collect()
+    }
+
+    fn render_text(&self) -> String {
+        let locale = self.locale;
+        let mut out = String::new();
+        writeln!(out, "{}", self.title).unwrap();
+        for day in self.non_empty_days() {
+            writeln!(out).unwrap();
+            writeln!(out, "{}", locale.format_date(day.date)).unwrap();
+            if day.trackers.is_empty() {
+                writeln!(out, "  {}", locale.label(Label::NoEntries)).unwrap();
+            }
+            for tracker in &day.trackers {
+                let description = tracker.description.as_deref().unwrap_or_default();
+                writeln!(
+                    out,
+                    "  {:<12} {:>8}  {}",
+                    tracker.key,
+                    format_duration(self.rounding.shown(tracker.duration)),
+                    description
+                )
+                .unwrap();
+            }
+        }
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "{}: {}",
+            locale.label(Label::Total),
+            format_duration(self.sum())
+        )
+        .unwrap();
+        if !self.goals.is_empty() {
+            writeln!(out).unwrap();
+            writeln!(out, "{}", locale.label(Label::Goals)).unwrap();
+            for goal in &self.goals {
+                writeln!(out, "  {}", goal.describe(self.rounding)).unwrap();
+            }
+        }
+        out
+    }
+
+    /// Machine readable, therefore independent of the locale
+    fn render_csv(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "date,key,external,description,duration_seconds").unwrap();
+        for day in &self.days {
+            for tracker in &day.trackers {
+                let description = tracker.description.as_deref().unwrap_or_default();
+                writeln!(
+                    out,
+                    "{},{},{},{},{}",
+                    day.date.format("%Y-%m-%d"),
+                    escape_csv(&tracker.key),
+                    tracker.external,
+                    escape_csv(description),
+                    tracker.duration.as_secs()
+                )
+                .unwrap();
+            }
+        }
+        out
+    }
+
+    fn render_markdown(&self) -> String {
+        let locale = self.locale;
+        let mut out = String::new();
+        writeln!(out, "# {}", self.title).unwrap();
+        for day in self.non_empty_days() {
+            writeln!(out).unwrap();
+            writeln!(out, "## {}", locale.format_date(day.date)).unwrap();
+            writeln!(out).unwrap();
+            if day.trackers.is_empty() {
+                writeln!(out, "_{}_", locale.label(Label::NoEntries)).unwrap();
+                continue;
+            }
+            writeln!(
+                out,
+                "| {} | {} | {} |",
+                locale.label(Label::Issue),
+                locale.label(Label::Description),
+                locale.label(Label::Duration)
+            )
+            .unwrap();
+            writeln!(out, "|---|---|---:|").unwrap();
+            for tracker in &day.trackers {
+                let description = tracker.description.as_deref().unwrap_or_default();
+                writeln!(
+                    out,
+                    "| {} | {} | {} |",
+                    tracker.key,
+                    description.replace('|', "\\|"),
+                    format_duration(self.rounding.shown(tracker.duration))
+                )
+                .unwrap();
+            }
+        }
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "**{}: {}**",
+            locale.label(Label::Total),
+            format_duration(self.sum())
+        )
+        .unwrap();
+        if !self.goals.is_empty() {
+            writeln!(out).unwrap();
+            writeln!(out, "## {}", locale.label(Label::Goals)).unwrap();
+            writeln!(out).unwrap();
+            for goal in &self.goals {
+                writeln!(out, "- {}", goal.describe(self.rounding)).unwrap();
+            }
+        }
+        out
+    }
+
+    fn render_html(&self) -> String {
+        let locale = self.locale;
+        let mut out = String::new();
+        writeln!(out, "<!DOCTYPE html>").unwrap();
+        writeln!(out, "<html lang=\"{}\">", locale.lang()).unwrap();
+        writeln!(
+            out,
+            "<head><meta charset=\"utf-8\"><title>{}</title></head>",
+            escape_html(&self.title)
+        )
+        .unwrap();
+        writeln!(out, "<body>").unwrap();
+        writeln!(out, "<h1>{}</h1>", escape_html(&self.title)).unwrap();
+        for day in self.non_empty_days() {
+            writeln!(out, "<h2>{}</h2>", locale.format_date(day.date)).unwrap();
+            if day.trackers.is_empty() {
+                writeln!(out, "<p>{}</p>", locale.label(Label::NoEntries)).unwrap();
+                continue;
+            }
+            writeln!(out, "<table>").unwrap();
+            writeln!(
+                out,
+                "<tr><th>{}</th><th>{}</th><th>{}</th></tr>",
+                locale.label(Label::Issue),
+                locale.label(Label::Description),
+                locale.label(Label::Duration)
+            )
+            .unwrap();
+            for tracker in &day.trackers {
+                let description = tracker.description.as_deref().unwrap_or_default();
+                writeln!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    escape_html(&tracker.key),
+                    escape_html(description),
+                    format_duration(self.rounding.shown(tracker.duration))
+                )
+                .unwrap();
+            }
+            writeln!(out, "</table>").unwrap();
+        }
+        writeln!(
+            out,
+            "<p><strong>{}: {}</strong></p>",
+            locale.label(Label::Total),
+            format_duration(self.sum())
+        )
+        .unwrap();
+        if !self.goals.is_empty() {
+            writeln!(out, "<h2>{}</h2>", locale.label(Label::Goals)).unwrap();
+            writeln!(out, "<ul>").unwrap();
+            for goal in &self.goals {
+                writeln!(
+                    out,
+                    "<li>{}</li>",
+                    escape_html(&goal.describe(self.rounding))
+                )
+                .unwrap();
+            }
+            writeln!(out, "</ul>").unwrap();
+        }
+        writeln!(out, "</body>").unwrap();
+        writeln!(out, "</html>").unwrap();
+        out
+    }
+}
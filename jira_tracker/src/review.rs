@@ -0,0 +1,224 @@
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use futures::future::join_all;
+use humantime_serde::re::humantime::format_duration;
+use serde::Serialize;
+
+use domain::TrackerInformation;
+
+use crate::jira_api::JiraIssue;
+use crate::rounding::DisplayRounding;
+use crate::tempo_api::{TempoApi, MINIMUM_DURATION};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Will not be submitted
+    Info,
+    /// Will be submitted, but probably not as intended
+    Warning,
+    /// Prevents the submission
+    Blocking,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewItem {
+    key: String,
+    check: &'static str,
+    severity: Severity,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubmitReview {
+    ready: bool,
+    items: Vec<ReviewItem>,
+}
+
+impl SubmitReview {
+    /// Each tracker is paired with its freshly looked up Jira issue, if the lookup succeeded.
+    /// `tempo` are the items of [`check_tempo`].
+    pub fn new(
+        trackers: &[(TrackerInformation, Option<JiraIssue>)],
+        tempo: Vec<ReviewItem>,
+        display: DisplayRounding,
+    ) -> Self {
+        let items: Vec<_> = trackers
+            .iter()
+            .flat_map(|(tracker, issue)| review(tracker, issue.as_ref(), display))
+            .chain(tempo)
+            .collect();
+        Self {
+            ready: items.iter().all(|i| i.severity != Severity::Blocking),
+            items,
+        }
+    }
+
+    /// No blocking items were found
+    pub fn ready(&self) -> bool {
+        self.ready
+    }
+}
+
+fn review(
+    tracker: &TrackerInformation,
+    issue: Option<&JiraIssue>,
+    display: DisplayRounding,
+) -> Vec<ReviewItem> {
+    let mut items = Vec::new();
+    let mut push = |check, severity, message: String| {
+        items.push(ReviewItem {
+            key: tracker.key.clone(),
+            check,
+            severity,
+            message,
+        })
+    };
+
+    if tracker.external {
+        push(
+            "external",
+            Severity::Info,
+            "external tracker, will not be submitted".to_string(),
+        );
+        return items;
+    }
+    if tracker.duration < MINIMUM_DURATION {
+        push(
+            "short_duration",
+            Severity::Info,
+            format!(
+                "less than {}, will be skipped",
+                format_duration(MINIMUM_DURATION)
+            ),
+        );
+    }
+    if tracker.running {
+        push(
+            "running",
+            Severity::Warning,
+            "tracker is still running".to_string(),
+        );
+    }
+    if tracker.description.is_none() {
+        push(
+            "empty_description",
+            Severity::Warning,
+            "worklog has no description".to_string(),
+        );
+    }
+
+    let Some(issue) = issue else {
+        push(
+            "issue_lookup",
+            Severity::Blocking,
+            "issue could not be looked up in Jira".to_string(),
+        );
+        return items;
+    };
+    let fields = &issue.fields;
+    if let Some(status) = fields.status.as_ref().filter(|s| s.is_done()) {
+        push(
+            "closed_issue",
+            Severity::Warning,
+            format!("issue is in status {}", status.name),
+        );
+    }
+    if let Some(estimate) = fields.original_estimate.filter(|e| *e > 0) {
+        let spent = Duration::from_secs(fields.time_spent.unwrap_or_default()) + tracker.duration;
+        let estimate = Duration::from_secs(estimate);
+        if spent > estimate {
+            push(
+                "estimate_exceeded",
+                Severity::Warning,
+                format!(
+                    "{} logged in total exceeds the estimate of {}",
+                    format_duration(display.shown(spent)),
+                    format_duration(display.shown(estimate))
+                ),
+            );
+        }
+    }
+    items
+}
+
+/// Checks the trackers to submit against Tempo: the timesheets of their days must still be open
+/// and the accounts `SUBMIT_RULES` log them on must exist and be open. Each day and account is
+/// looked up once.
+pub async fn check_tempo(api: &TempoApi, trackers: &[TrackerInformation]) -> Vec<ReviewItem> {
+    let skipped = api.skipped(trackers);
+    let submitted: Vec<_> = trackers
+        .iter()
+        .filter(|tracker| !tracker.external && !skipped.contains(&tracker.key))
+        .collect();
+    let dates: BTreeSet<NaiveDate> = submitted
+        .iter()
+        .map(|tracker| tracker.start_time.date_naive())
+        .collect();
+    let statuses: HashMap<_, _> = dates
+        .iter()
+        .copied()
+        .zip(join_all(dates.iter().map(|date| api.get_timesheet_status(*date))).await)
+        .collect();
+    let accounts: BTreeSet<&str> = submitted
+        .iter()
+        .filter_map(|tracker| api.account_for(&tracker.key))
+        .collect();
+    let lookups: HashMap<_, _> = accounts
+        .iter()
+        .copied()
+        .zip(join_all(accounts.iter().map(|account| api.get_account(account))).await)
+        .collect();
+
+    let mut items = Vec::new();
+    for tracker in submitted {
+        let mut push = |check, severity, message: String| {
+            items.push(ReviewItem {
+                key: tracker.key.clone(),
+                check,
+                severity,
+                message,
+            })
+        };
+        let date = tracker.start_time.date_naive();
+        match &statuses[&date] {
+            Ok(status) if status != "OPEN" => push(
+                "locked_period",
+                Severity::Blocking,
+                format!(
+                    "timesheet of {} is {}, no worklogs can be added",
+                    date, status
+                ),
+            ),
+            Ok(_) => {}
+            Err(e) => push(
+                "locked_period",
+                Severity::Warning,
+                format!("timesheet of {} could not be checked: {}", date, e),
+            ),
+        }
+        let Some(account) = api.account_for(&tracker.key) else {
+            continue;
+        };
+        match &lookups[account] {
+            Ok(found) if !found.is_open() => push(
+                "invalid_account",
+                Severity::Blocking,
+                format!(
+                    "account {} is {}",
+                    account,
+                    found.status.as_deref().unwrap_or_default()
+                ),
+            ),
+            Ok(_) => {}
+            Err(e) => push(
+                "invalid_account",
+                Severity::Blocking,
+                format!("account {} could not be looked up: {}", account, e),
+            ),
+        }
+    }
+    items
+}
@@ -0,0 +1,78 @@
+//! Rounding of tracked time. Durations are kept with full precision in the state and only rounded
+//! where they leave the tracker: to whole seconds in responses, to minutes in reports and to
+//! `ROUNDING_STEP` per `ROUNDING_POLICY` when closing the day and submitting worklogs. Reports, HTML
+//! pages and chat replies show them to `DISPLAY_ROUNDING_STEP` independently of that. Shown sums
+//! add up the rounded durations, so that they match the shown rows.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How tracked time is rounded to `ROUNDING_STEP`
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingPolicy {
+    Up,
+    #[default]
+    Nearest,
+    Down,
+}
+
+impl RoundingPolicy {
+    pub fn round(&self, duration: Duration, step: Duration) -> Duration {
+        if step.is_zero() {
+            return duration;
+        }
+        let step_nanos = step.as_nanos();
+        let steps = duration.as_nanos() / step_nanos;
+        let remainder = duration.as_nanos() % step_nanos;
+        let round_up = match self {
+            RoundingPolicy::Up => remainder > 0,
+            RoundingPolicy::Nearest => remainder * 2 >= step_nanos,
+            RoundingPolicy::Down => false,
+        };
+        step * (steps + u128::from(round_up)) as u32
+    }
+}
+
+/// Whole seconds of `duration`, as shown in responses
+pub fn seconds(duration: Duration) -> Duration {
+    Duration::from_secs(duration.as_secs())
+}
+
+/// Whole minutes of `duration`, as shown in reports
+pub fn minutes(duration: Duration) -> Duration {
+    Duration::from_secs(duration.as_secs() / 60 * 60)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rounding {
+    pub step: Option<Duration>,
+    pub policy: RoundingPolicy,
+}
+
+impl Rounding {
+    /// Duration of a worklog: rounded to the step if one is configured, whole seconds otherwise
+    pub fn worklog(&self, duration: Duration) -> Duration {
+        match self.step {
+            Some(step) => seconds(self.policy.round(duration, step)),
+            None => seconds(duration),
+        }
+    }
+}
+
+/// Rounding of durations shown to people, in reports, HTML pages and chat replies
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DisplayRounding {
+    pub step: Option<Duration>,
+}
+
+impl DisplayRounding {
+    /// Rounded to the nearest step if one is configured, whole minutes otherwise
+    pub fn shown(&self, duration: Duration) -> Duration {
+        match self.step {
+            Some(step) => minutes(RoundingPolicy::Nearest.round(duration, step)),
+            None => minutes(duration),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Rules routing the trackers of projects on submission, e.g.
+//! `SUBMIT_RULES=INT=skip,CLIENT=account:ACME` never submits `INT-*` trackers and logs `CLIENT-*`
+//! worklogs on the Tempo account `ACME`.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmitRoute {
+    /// Not submitted at all
+    Skip,
+    /// Logged on the Tempo account with this key
+    Account(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmitRule {
+    project: String,
+    pub route: SubmitRoute,
+}
+
+impl Display for SubmitRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.route {
+            SubmitRoute::Skip => write!(f, "{}=skip", self.project),
+            SubmitRoute::Account(account) => write!(f, "{}=account:{}", self.project, account),
+        }
+    }
+}
+
+impl SubmitRule {
+    /// Parses `PROJECT=skip` or `PROJECT=account:KEY`, the project may be given as `PROJECT-*`
+    fn parse(rule: &str) -> Option<Self> {
+        let (project, route) = rule.split_once('=')?;
+        let project = project.trim();
+        let project = project.strip_suffix("-*").unwrap_or(project).to_uppercase();
+        let route = match route.trim() {
+            "skip" => SubmitRoute::Skip,
+            route => SubmitRoute::Account(route.strip_prefix("account:")?.trim().to_string()),
+        };
+        (!project.is_empty()).then_some(Self { project, route })
+    }
+}
+
+/// Rules in the configured order, the first one of the project of a tracker applies
+#[derive(Debug, Clone, Default)]
+pub struct SubmitRules(Vec<SubmitRule>);
+
+impl SubmitRules {
+    pub fn route(&self, key: &str) -> Option<&SubmitRule> {
+        let project = key.split_once('-').map_or(key, |(project, _)| project);
+        self.0
+            .iter()
+            .find(|rule| rule.project.eq_ignore_ascii_case(project))
+    }
+
+    /// Whether the tracker is not submitted at all
+    pub fn skips(&self, key: &str) -> bool {
+        self.route(key)
+            .is_some_and(|rule| rule.route == SubmitRoute::Skip)
+    }
+}
+
+impl<'de> Deserialize<'de> for SubmitRules {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        string
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|rule| {
+                SubmitRule::parse(rule).ok_or_else(|| {
+                    serde::de::Error::custom(format!("invalid submit rule '{}'", rule))
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(SubmitRules)
+    }
+}
+
+/// In the format of `SUBMIT_RULES`
+impl Serialize for SubmitRules {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let rules: Vec<_> = self.0.iter().map(ToString::to_string).collect();
+        serializer.serialize_str(&rules.join(","))
+    }
+}
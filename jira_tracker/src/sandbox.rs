@@ -0,0 +1,86 @@
+//! Sandbox mode with `SANDBOX=true`: Jira and Tempo are replaced by fakes, so that the whole API
+//! can be tried out without credentials. Every issue key exists, and submitted worklogs are only
+//! appended to `sandbox.jsonl` next to the state file instead of being sent to Tempo.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::clock;
+use crate::jira_api::{IssueFields, JiraIssue};
+use crate::upstream::{Service, UpstreamError};
+
+/// Account id of the own user if `JIRA_ACCOUNT_ID` is not set
+pub const ACCOUNT_ID: &str = "sandbox";
+
+/// Issue named after its key, the key also stands in for the issue id
+pub fn issue(key: &str) -> JiraIssue {
+    JiraIssue {
+        id: key.to_string(),
+        key: key.to_string(),
+        fields: IssueFields {
+            summary: format!("Sandbox issue {}", key),
+            status: None,
+            issue_type: None,
+            priority: None,
+            project: None,
+            original_estimate: None,
+            time_spent: None,
+            assignee: None,
+        },
+    }
+}
+
+/// Line of `sandbox.jsonl`
+#[derive(Debug, Serialize)]
+struct RecordedWorklog<'a, T> {
+    tempo_worklog_id: u64,
+    submitted_at: DateTime<Local>,
+    /// Body as it would have been posted to Tempo
+    payload: &'a T,
+}
+
+#[derive(Debug)]
+pub struct Sandbox {
+    file: PathBuf,
+    next_id: AtomicU64,
+}
+
+impl Sandbox {
+    /// Worklog ids continue after the ones already recorded in `file`
+    pub fn new(file: PathBuf) -> Self {
+        let recorded = fs::read_to_string(&file)
+            .map(|content| content.lines().count() as u64)
+            .unwrap_or(0);
+        Self {
+            file,
+            next_id: AtomicU64::new(recorded + 1),
+        }
+    }
+
+    /// Appends the worklog to the file instead of submitting it, returns its fake Tempo id
+    pub fn record<T: Serialize>(&self, payload: &T) -> Result<u64, UpstreamError> {
+        let tempo_worklog_id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let record = RecordedWorklog {
+            tempo_worklog_id,
+            submitted_at: clock::local_now(),
+            payload,
+        };
+        let line = serde_json::to_string(&record).unwrap();
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.file)
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|e| UpstreamError::Unexpected {
+                service: Service::Tempo,
+                status: 500,
+                detail: format!("could not record sandbox worklog: {}", e),
+            })?;
+        Ok(tempo_worklog_id)
+    }
+}
@@ -0,0 +1,57 @@
+//! Required working time per day from the user schedule in Tempo, so that part-time days and
+//! holidays are respected. Days Tempo does not know, e.g. while it is unreachable, fall back to
+//! `WORKING_HOURS` on weekdays.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+
+use crate::tempo_api::TempoApi;
+
+#[derive(Debug)]
+pub struct Schedule {
+    required: HashMap<NaiveDate, Duration>,
+    working_hours: Duration,
+}
+
+impl Schedule {
+    /// Schedule from `from` to `to`, both inclusive
+    pub async fn fetch(
+        tempo: &TempoApi,
+        from: NaiveDate,
+        to: NaiveDate,
+        working_hours: Duration,
+    ) -> Self {
+        let days = tempo.get_user_schedule(from, to).await.unwrap_or_else(|e| {
+            tracing::warn!(
+                "could not get schedule from Tempo, using WORKING_HOURS: {}",
+                e
+            );
+            Vec::new()
+        });
+        Self {
+            required: days
+                .into_iter()
+                .map(|day| (day.date, Duration::from_secs(day.required_seconds)))
+                .collect(),
+            working_hours,
+        }
+    }
+
+    pub fn required(&self, date: NaiveDate) -> Duration {
+        match self.required.get(&date) {
+            Some(required) => *required,
+            None if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) => Duration::ZERO,
+            None => self.working_hours,
+        }
+    }
+
+    /// Sum from `from` to `to`, both inclusive
+    pub fn required_between(&self, from: NaiveDate, to: NaiveDate) -> Duration {
+        from.iter_days()
+            .take_while(|day| *day <= to)
+            .map(|day| self.required(day))
+            .sum()
+    }
+}
@@ -0,0 +1,196 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+use crate::files;
+use crate::lease::WriterLease;
+
+/// Outcome of a job run, with a short message or the error
+pub type JobResult = Result<String, String>;
+
+type JobFn = dyn Fn() -> BoxFuture<'static, JobResult> + Send + Sync;
+
+/// Result of the last run of a job, persisted across restarts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRun {
+    at: DateTime<Local>,
+    #[serde(with = "humantime_serde")]
+    took: Duration,
+    result: JobResult,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    id: String,
+    #[serde(with = "humantime_serde")]
+    interval: Duration,
+    running: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_run: Option<JobRun>,
+}
+
+impl JobStatus {
+    pub fn running(&self) -> bool {
+        self.running
+    }
+}
+
+struct Job {
+    run: Arc<JobFn>,
+    status: JobStatus,
+}
+
+/// Job marked as running, marked as stopped again if its run is cancelled, e.g. by the client of
+/// `POST /jobs/:id/run-now` disconnecting, or panics before it is finished
+struct RunningJob<'a> {
+    jobs: &'a RwLock<IndexMap<String, Job>>,
+    id: &'a str,
+    finished: bool,
+}
+
+impl RunningJob<'_> {
+    fn finish(mut self, run: JobRun) -> Option<JobStatus> {
+        self.finished = true;
+        let mut jobs = self.jobs.write().unwrap();
+        let job = jobs.get_mut(self.id)?;
+        job.status.running = false;
+        job.status.last_run = Some(run);
+        Some(job.status.clone())
+    }
+}
+
+impl Drop for RunningJob<'_> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        if let Some(job) = self.jobs.write().unwrap().get_mut(self.id) {
+            job.status.running = false;
+        }
+    }
+}
+
+/// Runs registered jobs periodically, see `GET /jobs`
+pub struct Scheduler {
+    jobs: RwLock<IndexMap<String, Job>>,
+    /// Last runs by job id
+    path: PathBuf,
+    /// Jobs only run periodically on the instance holding the lease
+    lease: Arc<WriterLease>,
+}
+
+impl Scheduler {
+    pub fn new<P: Into<PathBuf>>(path: P, lease: Arc<WriterLease>) -> Self {
+        Self {
+            jobs: RwLock::new(IndexMap::new()),
+            path: path.into(),
+            lease,
+        }
+    }
+
+    /// Registers a job running every `interval`, the first time right after [`Scheduler::start`]
+    pub fn add<F, Fut>(&self, id: &str, interval: Duration, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = JobResult> + Send + 'static,
+    {
+        let last_runs: IndexMap<String, JobRun> = files::read_file(&self.path).unwrap_or_default();
+        let status = JobStatus {
+            id: id.to_string(),
+            interval,
+            running: false,
+            last_run: last_runs.get(id).cloned(),
+        };
+        let run: Arc<JobFn> = Arc::new(move || job().boxed());
+        self.jobs
+            .write()
+            .unwrap()
+            .insert(id.to_string(), Job { run, status });
+    }
+
+    pub fn list(&self) -> Vec<JobStatus> {
+        self.jobs
+            .read()
+            .unwrap()
+            .values()
+            .map(|job| job.status.clone())
+            .collect()
+    }
+
+    /// Runs the job immediately, `None` if there is no job with the id. A job that is still
+    /// running is not started again, its status is returned as is.
+    pub async fn run(&self, id: &str) -> Option<JobStatus> {
+        let run = {
+            let mut jobs = self.jobs.write().unwrap();
+            let job = jobs.get_mut(id)?;
+            if job.status.running {
+                tracing::debug!("job {} is still running, skipping", id);
+                return Some(job.status.clone());
+            }
+            job.status.running = true;
+            job.run.clone()
+        };
+        let running = RunningJob {
+            jobs: &self.jobs,
+            id,
+            finished: false,
+        };
+        let at = clock::local_now();
+        let started = std::time::Instant::now();
+        let result = run().await;
+        if let Err(e) = &result {
+            tracing::warn!("job {} failed: {}", id, e);
+        }
+        let status = running.finish(JobRun {
+            at,
+            took: started.elapsed(),
+            result,
+        })?;
+        self.persist();
+        Some(status)
+    }
+
+    fn persist(&self) {
+        let last_runs: IndexMap<String, JobRun> = self
+            .jobs
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|(id, job)| job.status.last_run.clone().map(|run| (id.clone(), run)))
+            .collect();
+        if let Err(e) = files::write_file(&self.path, &last_runs) {
+            tracing::warn!("could not persist job runs: {:?}", e);
+        }
+    }
+
+    /// Spawns one task per registered job
+    pub fn start(self: &Arc<Self>) {
+        let jobs: Vec<(String, Duration)> = self
+            .jobs
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(id, job)| (id.clone(), job.status.interval))
+            .collect();
+        for (id, interval) in jobs {
+            let scheduler = self.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(interval);
+                loop {
+                    interval.tick().await;
+                    if scheduler.lease.held() {
+                        scheduler.run(&id).await;
+                    }
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! `?fields=key,duration,running` on list and get endpoints, trimming the objects of the response
+//! to these fields for widgets polling often. Unknown fields are ignored.
+
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Deserialize)]
+pub struct FieldsQuery {
+    /// Comma separated, all fields if missing
+    fields: Option<String>,
+}
+
+impl FieldsQuery {
+    pub fn select<T: Serialize>(&self, value: T) -> Selected<T> {
+        Selected {
+            value,
+            fields: self.fields.as_ref().map(|fields| {
+                fields
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|field| !field.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// JSON response with only the selected fields of its objects, or of the objects it lists
+pub struct Selected<T> {
+    value: T,
+    fields: Option<Vec<String>>,
+}
+
+fn trim(value: Value, fields: &[String]) -> Value {
+    match value {
+        Value::Object(mut map) => {
+            map.retain(|name, _| fields.contains(name));
+            Value::Object(map)
+        }
+        Value::Array(items) => items.into_iter().map(|item| trim(item, fields)).collect(),
+        value => value,
+    }
+}
+
+impl<T: Serialize> IntoResponse for Selected<T> {
+    fn into_response(self) -> Response {
+        let Some(fields) = self.fields else {
+            return Json(self.value).into_response();
+        };
+        match serde_json::to_value(&self.value) {
+            Ok(value) => Json(trim(value, &fields)).into_response(),
+            // responds with the serialization error
+            Err(_) => Json(self.value).into_response(),
+        }
+    }
+}
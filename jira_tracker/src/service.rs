@@ -0,0 +1,99 @@
+//! Windows service integration, see `--install-service` and `--service`
+
+use std::ffi::OsString;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use windows_service::service::{
+    ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+    ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+
+const SERVICE_NAME: &str = "jira_tracker";
+const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
+
+pub fn install() -> windows_service::Result<()> {
+    let manager_access = ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE;
+    let service_manager = ServiceManager::local_computer(None::<&str>, manager_access)?;
+
+    let service_info = ServiceInfo {
+        name: OsString::from(SERVICE_NAME),
+        display_name: OsString::from("Jira Tracker"),
+        service_type: SERVICE_TYPE,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe().unwrap(),
+        launch_arguments: vec![OsString::from("--service")],
+        dependencies: vec![],
+        account_name: None,
+        account_password: None,
+    };
+    let service = service_manager.create_service(&service_info, ServiceAccess::CHANGE_CONFIG)?;
+    service.set_description("Tracks time on Jira issues and submits it to Tempo")?;
+    Ok(())
+}
+
+pub fn uninstall() -> windows_service::Result<()> {
+    let service_manager =
+        ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+    let service_access = ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE;
+    let service = service_manager.open_service(SERVICE_NAME, service_access)?;
+    // stopped first, as a deleted service that still runs stays registered until it stops
+    if service.query_status()?.current_state != ServiceState::Stopped {
+        service.stop()?;
+    }
+    service.delete()
+}
+
+/// Blocks until the service is stopped by the service control manager
+pub fn run() -> windows_service::Result<()> {
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+}
+
+define_windows_service!(ffi_service_main, service_main);
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        tracing::error!("service failed: {}", e);
+    }
+}
+
+fn status(state: ServiceState, controls_accepted: ServiceControlAccept) -> ServiceStatus {
+    ServiceStatus {
+        service_type: SERVICE_TYPE,
+        current_state: state,
+        controls_accepted,
+        exit_code: ServiceExitCode::Win32(0),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    }
+}
+
+fn run_service() -> windows_service::Result<()> {
+    let (shutdown_sender, shutdown_receiver) = oneshot::channel();
+    let mut shutdown_sender = Some(shutdown_sender);
+
+    let event_handler = move |control_event| match control_event {
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        ServiceControl::Stop => {
+            if let Some(sender) = shutdown_sender.take() {
+                let _ = sender.send(());
+            }
+            ServiceControlHandlerResult::NoError
+        }
+        _ => ServiceControlHandlerResult::NotImplemented,
+    };
+    let status_handle = service_control_handler::register(SERVICE_NAME, event_handler)?;
+    status_handle.set_service_status(status(ServiceState::Running, ServiceControlAccept::STOP))?;
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(crate::serve(true, async {
+        let _ = shutdown_receiver.await;
+    }));
+
+    status_handle.set_service_status(status(ServiceState::Stopped, ServiceControlAccept::empty()))
+}
@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate};
+use serde::Serialize;
+
+use domain::TrackerInformation;
+
+use crate::jira_api::{Sprint, SprintIssue};
+use crate::schedule::Schedule;
+
+#[derive(Debug, Serialize)]
+pub struct CommittedIssue {
+    key: String,
+    #[serde(with = "humantime_serde")]
+    remaining: Duration,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SprintCapacity {
+    sprint: Option<String>,
+    end: Option<NaiveDate>,
+    /// Remaining estimates of all unfinished issues
+    #[serde(with = "humantime_serde")]
+    committed: Duration,
+    /// Required working time left until the end of the sprint, see `schedule.rs`
+    #[serde(with = "humantime_serde")]
+    available: Duration,
+    /// Whether the commitment exceeds the available working time
+    at_risk: bool,
+    issues: Vec<CommittedIssue>,
+}
+
+fn active_sprint(issues: &[SprintIssue], sprint_field: &str) -> Option<Sprint> {
+    issues
+        .iter()
+        .flat_map(|issue| issue.sprints(sprint_field))
+        .find(|sprint| sprint.state == "active")
+}
+
+fn end_of(sprint: &Sprint) -> Option<NaiveDate> {
+    sprint
+        .end_date
+        .map(|end| end.with_timezone(&Local).date_naive())
+}
+
+/// Last day of the active sprint, if it has an end
+pub fn active_sprint_end(issues: &[SprintIssue], sprint_field: &str) -> Option<NaiveDate> {
+    active_sprint(issues, sprint_field)
+        .as_ref()
+        .and_then(end_of)
+}
+
+impl SprintCapacity {
+    /// `tracked_today` is subtracted from today's required working time
+    pub fn new(
+        issues: &[SprintIssue],
+        sprint_field: &str,
+        schedule: &Schedule,
+        today: NaiveDate,
+        tracked_today: &[TrackerInformation],
+    ) -> Self {
+        let sprint = active_sprint(issues, sprint_field);
+        let end = sprint.as_ref().and_then(end_of);
+
+        let issues: Vec<_> = issues
+            .iter()
+            .filter(|issue| !issue.fields.status.as_ref().is_some_and(|s| s.is_done()))
+            .map(|issue| CommittedIssue {
+                key: issue.key.clone(),
+                remaining: Duration::from_secs(issue.fields.remaining_estimate.unwrap_or(0)),
+            })
+            .collect();
+        let committed = issues.iter().map(|issue| issue.remaining).sum();
+
+        let available = end.map_or(Duration::ZERO, |end| {
+            let tracked: Duration = tracked_today.iter().map(|t| t.duration).sum();
+            schedule
+                .required_between(today, end)
+                .saturating_sub(tracked.min(schedule.required(today)))
+        });
+
+        Self {
+            sprint: sprint.map(|sprint| sprint.name),
+            end,
+            committed,
+            available,
+            at_risk: committed > available,
+            issues,
+        }
+    }
+}
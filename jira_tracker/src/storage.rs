@@ -0,0 +1,760 @@
+//! Persistence of the tracker state, selected with `STORAGE`
+
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::app_data;
+use crate::app_data::InnerAppData;
+use crate::clock;
+use crate::config::AppConfig;
+use crate::files;
+use crate::files::{FileError, FileKey};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// Everything in `JSON_FILE`
+    #[default]
+    Json,
+    /// One row per tracker and state section in `state.sqlite` next to `JSON_FILE`
+    Sqlite,
+}
+
+/// Encoding of the state file, detected when loading so that it can be changed at any time
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StateFormat {
+    /// Pretty-printed, to be edited by hand
+    #[default]
+    Json,
+    Cbor,
+    #[serde(alias = "messagepack")]
+    Msgpack,
+}
+
+impl StateFormat {
+    /// Format of a stored state by the first byte, the start of a map in all formats
+    fn detect(bytes: &[u8]) -> Self {
+        match bytes.first() {
+            Some(0xa0..=0xbf) => StateFormat::Cbor,
+            Some(0x80..=0x8f | 0xde | 0xdf) => StateFormat::Msgpack,
+            _ => StateFormat::Json,
+        }
+    }
+
+    fn encode(&self, data: &InnerAppData) -> Result<Vec<u8>, StorageError> {
+        match self {
+            StateFormat::Json => Ok(serde_json::to_vec_pretty(data)?),
+            StateFormat::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(data, &mut bytes)
+                    .map_err(|e| StorageError::Encoding(e.to_string()))?;
+                Ok(bytes)
+            }
+            // with field names, so that migrations can work on the decoded value
+            StateFormat::Msgpack => {
+                rmp_serde::to_vec_named(data).map_err(|e| StorageError::Encoding(e.to_string()))
+            }
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Value, StorageError> {
+        match self {
+            StateFormat::Json => Ok(serde_json::from_slice(bytes)?),
+            StateFormat::Cbor => {
+                ciborium::from_reader(bytes).map_err(|e| StorageError::Encoding(e.to_string()))
+            }
+            StateFormat::Msgpack => {
+                rmp_serde::from_slice(bytes).map_err(|e| StorageError::Encoding(e.to_string()))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    File(FileError),
+    Sqlite(rusqlite::Error),
+    Serde(serde_json::Error),
+    /// Invalid CBOR or MessagePack
+    Encoding(String),
+    /// The storage cannot be encrypted, but `STATE_ENCRYPTION_KEY` is set
+    Unencrypted(StorageBackend),
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::File(e) => write!(f, "state file: {}", e),
+            StorageError::Sqlite(e) => write!(f, "SQLite: {}", e),
+            StorageError::Serde(e) => write!(f, "invalid state: {}", e),
+            StorageError::Encoding(e) => write!(f, "invalid state: {}", e),
+            StorageError::Unencrypted(backend) => {
+                write!(
+                    f,
+                    "{:?} cannot be encrypted with STATE_ENCRYPTION_KEY",
+                    backend
+                )
+            }
+        }
+    }
+}
+
+impl Error for StorageError {}
+
+impl From<FileError> for StorageError {
+    fn from(e: FileError) -> Self {
+        StorageError::File(e)
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        StorageError::Sqlite(e)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(e: serde_json::Error) -> Self {
+        StorageError::Serde(e)
+    }
+}
+
+/// State serialized by [`Storage::encode`]
+pub enum Encoded {
+    /// Unencrypted content of the state file
+    File(StateFormat, Vec<u8>),
+    /// Sections and trackers, see [`InnerAppData::to_parts`]
+    Parts(Vec<(String, Value)>, Vec<(String, String)>),
+}
+
+impl Encoded {
+    /// The state again, e.g. as base of a merge
+    pub fn decode(&self) -> Result<InnerAppData, StorageError> {
+        match self {
+            Encoded::File(format, bytes) => Ok(serde_json::from_value(format.decode(bytes)?)?),
+            Encoded::Parts(sections, trackers) => Ok(InnerAppData::from_parts(
+                sections.iter().cloned().collect(),
+                trackers.clone(),
+            )?),
+        }
+    }
+}
+
+pub trait Storage: Send + Sync {
+    fn backend(&self) -> StorageBackend;
+
+    /// Stored state, `None` if nothing was stored yet
+    fn load(&self) -> Result<Option<InnerAppData>, StorageError>;
+
+    /// Serializes the state for [`Storage::write`], so that it need not be locked while written
+    fn encode(&self, data: &InnerAppData) -> Result<Encoded, StorageError>;
+
+    /// Stores a state of [`Storage::encode`]
+    fn write(&self, encoded: &Encoded) -> Result<(), StorageError>;
+
+    fn save(&self, data: &InnerAppData) -> Result<(), StorageError> {
+        self.write(&self.encode(data)?)
+    }
+
+    /// Stored state if it was changed by someone else since the last save, e.g. by hand
+    fn load_changed(&self) -> Result<Option<InnerAppData>, StorageError> {
+        self.load()
+    }
+
+    /// File that is changed by editing the state by hand, to be reloaded then
+    fn watched_file(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Stored state like [`Storage::load`], but a corrupt state is replaced by a backup or a new
+    /// state, with a description of what happened. Only with `write` the corrupt state is set
+    /// aside and the replacement stored.
+    fn load_recovering(
+        &self,
+        _write: bool,
+    ) -> Result<(Option<InnerAppData>, Option<String>), StorageError> {
+        self.load().map(|data| (data, None))
+    }
+}
+
+pub struct JsonStorage {
+    path: PathBuf,
+    /// Format of writes, reads detect the format
+    format: StateFormat,
+    /// Encrypts writes if given, reads detect encryption
+    key: Option<FileKey>,
+    /// Fingerprint of the last loaded or saved content, to tell own writes from those of others
+    written: Mutex<Option<u64>>,
+    /// Whether the state as loaded was kept as backup already
+    backed_up: AtomicBool,
+}
+
+impl JsonStorage {
+    pub fn new<P: Into<PathBuf>>(path: P, format: StateFormat, key: Option<FileKey>) -> Self {
+        Self {
+            path: path.into(),
+            format,
+            key,
+            written: Mutex::new(None),
+            backed_up: AtomicBool::new(false),
+        }
+    }
+}
+
+fn fingerprint(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// `path` with `suffix` appended to its file name, e.g. `state.json.bak`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+impl JsonStorage {
+    /// Copy of the state as loaded before the first save
+    fn backup_path(&self) -> PathBuf {
+        with_suffix(&self.path, ".bak")
+    }
+
+    /// Decoded state and the fingerprint of its file
+    fn load_from(&self, path: &Path) -> Result<Option<(InnerAppData, u64)>, StorageError> {
+        let bytes = match fs::read(path).map_err(FileError::IO) {
+            Ok(bytes) => bytes,
+            Err(e) if e.is_not_found() => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let stored = fingerprint(&bytes);
+        let bytes = files::decrypt(bytes, self.key.as_ref())?;
+        let mut value = StateFormat::detect(&bytes).decode(&bytes)?;
+        files::migrate(&mut value, app_data::MIGRATIONS)?;
+        Ok(Some((serde_json::from_value(value)?, stored)))
+    }
+
+    /// Keeps the stored state as backup before it is replaced the first time, if it is the one
+    /// loaded or saved last. A state changed by someone else may be corrupt and leaves the
+    /// previous backup in place. Later saves don't touch the backup, so that it stays a state that
+    /// could be loaded.
+    fn back_up(&self) -> Result<(), StorageError> {
+        if self.backed_up.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let written = *self.written.lock().unwrap();
+        match fs::read(&self.path) {
+            Ok(bytes) if written == Some(fingerprint(&bytes)) => {
+                files::write_bytes(self.backup_path(), &bytes)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Storage for JsonStorage {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Json
+    }
+
+    fn load(&self) -> Result<Option<InnerAppData>, StorageError> {
+        let Some((data, stored)) = self.load_from(&self.path)? else {
+            return Ok(None);
+        };
+        *self.written.lock().unwrap() = Some(stored);
+        Ok(Some(data))
+    }
+
+    fn encode(&self, data: &InnerAppData) -> Result<Encoded, StorageError> {
+        Ok(Encoded::File(self.format, self.format.encode(data)?))
+    }
+
+    fn write(&self, encoded: &Encoded) -> Result<(), StorageError> {
+        let Encoded::File(_, bytes) = encoded else {
+            unreachable!("encoded by this storage");
+        };
+        let bytes = match &self.key {
+            Some(key) => Cow::Owned(key.encrypt(bytes)),
+            None => Cow::Borrowed(bytes),
+        };
+        self.back_up()?;
+        files::write_bytes(&self.path, &bytes)?;
+        *self.written.lock().unwrap() = Some(fingerprint(&bytes));
+        Ok(())
+    }
+
+    fn load_changed(&self) -> Result<Option<InnerAppData>, StorageError> {
+        let written = *self.written.lock().unwrap();
+        match fs::read(&self.path) {
+            Ok(bytes) if written == Some(fingerprint(&bytes)) => Ok(None),
+            _ => self.load(),
+        }
+    }
+
+    fn watched_file(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    /// Only undecodable states count as corrupt, not those of a newer version or another key
+    fn load_recovering(
+        &self,
+        write: bool,
+    ) -> Result<(Option<InnerAppData>, Option<String>), StorageError> {
+        let error = match self.load() {
+            Err(e @ (StorageError::Serde(_) | StorageError::Encoding(_))) => e,
+            result => return result.map(|data| (data, None)),
+        };
+        let set_aside = if write {
+            let timestamp = clock::local_now().format("%Y%m%d%H%M%S");
+            let quarantined = with_suffix(&self.path, &format!(".corrupt-{}", timestamp));
+            fs::rename(&self.path, &quarantined).map_err(FileError::IO)?;
+            format!("moved it to {}", quarantined.display())
+        } else {
+            "left it to the writer".to_string()
+        };
+        let (data, fallback) = match self.load_from(&self.backup_path()) {
+            Ok(Some((data, _))) => (Some(data), "restored the backup".to_string()),
+            Ok(None) => (None, "started with a new state".to_string()),
+            Err(e) => (
+                None,
+                format!("started with a new state, the backup failed: {}", e),
+            ),
+        };
+        if let Some(data) = data.as_ref().filter(|_| write) {
+            // the restored backup must not be replaced by the first save
+            self.backed_up.store(true, Ordering::Release);
+            self.save(data)?;
+        }
+        let warning = format!(
+            "{} was corrupt ({}), {} and {}",
+            self.path.display(),
+            error,
+            set_aside,
+            fallback
+        );
+        tracing::error!("{}", warning);
+        Ok((data, Some(warning)))
+    }
+}
+
+/// Rows as last loaded or saved, so that only changed rows are written
+#[derive(Default)]
+struct Rows {
+    trackers: HashMap<String, (usize, String)>,
+    sections: HashMap<String, String>,
+}
+
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+    written: Mutex<Rows>,
+}
+
+impl SqliteStorage {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StorageError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(FileError::IO)?;
+        }
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS trackers (
+                 key TEXT PRIMARY KEY,
+                 position INTEGER NOT NULL,
+                 data TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS sections (
+                 name TEXT PRIMARY KEY,
+                 data TEXT NOT NULL
+             );",
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            written: Mutex::new(Rows::default()),
+        })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Sqlite
+    }
+
+    fn load(&self) -> Result<Option<InnerAppData>, StorageError> {
+        let connection = self.connection.lock().unwrap();
+        let mut rows = Rows::default();
+        let mut statement =
+            connection.prepare("SELECT key, data FROM trackers ORDER BY position")?;
+        let trackers = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<(String, String)>, _>>()?;
+        let mut statement = connection.prepare("SELECT name, data FROM sections")?;
+        let sections = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<(String, String)>, _>>()?;
+        if trackers.is_empty() && sections.is_empty() {
+            return Ok(None);
+        }
+
+        let mut state = Map::new();
+        for (name, data) in sections {
+            state.insert(name.clone(), serde_json::from_str(&data)?);
+            rows.sections.insert(name.clone(), data);
+        }
+        let mut stored = Map::new();
+        for (position, (key, data)) in trackers.iter().enumerate() {
+            stored.insert(key.clone(), serde_json::from_str(data)?);
+            rows.trackers.insert(key.clone(), (position, data.clone()));
+        }
+        // migrated as a whole like the state file, the rows are rewritten on the next save
+        state.insert("trackers".to_string(), Value::Object(stored));
+        let mut state = Value::Object(state);
+        files::migrate(&mut state, app_data::MIGRATIONS)?;
+        let Value::Object(mut state) = state else {
+            unreachable!("built as an object");
+        };
+        let Some(Value::Object(mut migrated)) = state.remove("trackers") else {
+            unreachable!("inserted above");
+        };
+        // in the order of the rows, the map may be sorted by key
+        let trackers = trackers
+            .into_iter()
+            .filter_map(|(key, _)| {
+                let tracker = migrated.remove(&key)?.to_string();
+                Some((key, tracker))
+            })
+            .collect();
+        let data = InnerAppData::from_parts(state, trackers)?;
+        *self.written.lock().unwrap() = rows;
+        Ok(Some(data))
+    }
+
+    fn encode(&self, data: &InnerAppData) -> Result<Encoded, StorageError> {
+        let (sections, trackers) = data.to_parts()?;
+        Ok(Encoded::Parts(sections, trackers))
+    }
+
+    fn write(&self, encoded: &Encoded) -> Result<(), StorageError> {
+        let Encoded::Parts(sections, trackers) = encoded else {
+            unreachable!("encoded by this storage");
+        };
+        let mut connection = self.connection.lock().unwrap();
+        let mut written = self.written.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        let mut rows = Rows::default();
+        for (name, value) in sections {
+            let data = value.to_string();
+            if written.sections.get(name) != Some(&data) {
+                transaction.execute(
+                    "INSERT OR REPLACE INTO sections (name, data) VALUES (?1, ?2)",
+                    params![name, data],
+                )?;
+            }
+            rows.sections.insert(name.clone(), data);
+        }
+        for name in written.sections.keys() {
+            if !rows.sections.contains_key(name) {
+                transaction.execute("DELETE FROM sections WHERE name = ?1", params![name])?;
+            }
+        }
+        for (position, (key, data)) in trackers.iter().enumerate() {
+            let row = (position, data.clone());
+            if written.trackers.get(key) != Some(&row) {
+                transaction.execute(
+                    "INSERT OR REPLACE INTO trackers (key, position, data) VALUES (?1, ?2, ?3)",
+                    params![key, row.0, row.1],
+                )?;
+            }
+            rows.trackers.insert(key.clone(), row);
+        }
+        for key in written.trackers.keys() {
+            if !rows.trackers.contains_key(key) {
+                transaction.execute("DELETE FROM trackers WHERE key = ?1", params![key])?;
+            }
+        }
+        transaction.commit()?;
+        *written = rows;
+        Ok(())
+    }
+}
+
+/// Storage of the configured backend. A new SQLite database starts with the state of `JSON_FILE`.
+pub fn open(config: &AppConfig) -> Result<Box<dyn Storage>, StorageError> {
+    let key = match &config.state_encryption_key {
+        Some(encoded) => Some(FileKey::from_base64(encoded).ok_or(FileError::Encryption)?),
+        None => None,
+    };
+    let encrypted = key.is_some();
+    let json = JsonStorage::new(&config.json_file, config.state_format, key);
+    match config.storage {
+        // left behind by `POST /maintenance/migrate-storage`
+        StorageBackend::Json if !config.json_file.exists() && config.sqlite_file().exists() => {
+            if encrypted {
+                return Err(StorageError::Unencrypted(StorageBackend::Sqlite));
+            }
+            tracing::warn!(
+                "{} was migrated to SQLite, using {}, set STORAGE=sqlite",
+                config.json_file.display(),
+                config.sqlite_file().display()
+            );
+            Ok(Box::new(SqliteStorage::open(config.sqlite_file())?))
+        }
+        StorageBackend::Json => {
+            tracing::info!("state kept in {}", config.json_file.display());
+            Ok(Box::new(json))
+        }
+        StorageBackend::Sqlite => {
+            tracing::info!("state kept in {}", config.sqlite_file().display());
+            let sqlite = SqliteStorage::open(config.sqlite_file())?;
+            if sqlite.load()?.is_none() {
+                if let Some(data) = json.load()? {
+                    tracing::info!("importing {} into SQLite", config.json_file.display());
+                    sqlite.save(&data)?;
+                }
+            }
+            Ok(Box::new(sqlite))
+        }
+    }
+}
+
+/// Why `POST /maintenance/migrate-storage` did not switch the storage
+#[derive(Debug)]
+pub enum MigrationError {
+    Unsupported {
+        from: StorageBackend,
+        to: StorageBackend,
+    },
+    /// The target exists already and is not overwritten
+    Exists(PathBuf),
+    /// The state is encrypted, the target could not be
+    Unencrypted(StorageBackend),
+    /// The copy does not match the state, it was removed again
+    Mismatch(Vec<String>),
+    Storage(StorageError),
+}
+
+impl From<StorageError> for MigrationError {
+    fn from(e: StorageError) -> Self {
+        MigrationError::Storage(e)
+    }
+}
+
+impl From<FileError> for MigrationError {
+    fn from(e: FileError) -> Self {
+        MigrationError::Storage(e.into())
+    }
+}
+
+impl IntoResponse for MigrationError {
+    fn into_response(self) -> Response {
+        match self {
+            MigrationError::Unsupported { from, to } => (
+                StatusCode::BAD_REQUEST,
+                format!("cannot migrate from {:?} to {:?}", from, to),
+            )
+                .into_response(),
+            MigrationError::Exists(path) => (
+                StatusCode::CONFLICT,
+                format!("{} exists already", path.display()),
+            )
+                .into_response(),
+            MigrationError::Unencrypted(to) => (
+                StatusCode::BAD_REQUEST,
+                format!("{:?} cannot be encrypted with STATE_ENCRYPTION_KEY", to),
+            )
+                .into_response(),
+            MigrationError::Mismatch(problems) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(problems)).into_response()
+            }
+            MigrationError::Storage(e) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+        }
+    }
+}
+
+/// Response of `POST /maintenance/migrate-storage`, as verified in the new storage
+#[derive(Debug, Serialize)]
+pub struct MigratedStorage {
+    backend: StorageBackend,
+    trackers: usize,
+    /// State sections besides the trackers, e.g. the archive
+    sections: usize,
+    /// Time of all trackers
+    #[serde(with = "humantime_serde")]
+    duration: Duration,
+    /// Where the old state file was moved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archived_file: Option<PathBuf>,
+}
+
+/// Differences of the copy from the state
+fn verify(data: &InnerAppData, copy: &InnerAppData) -> Result<Vec<String>, StorageError> {
+    let (sections, trackers) = data.to_parts()?;
+    let (copied_sections, copied_trackers) = copy.to_parts()?;
+    let mut problems = Vec::new();
+    if trackers.len() != copied_trackers.len() {
+        problems.push(format!(
+            "{} trackers, {} copied",
+            trackers.len(),
+            copied_trackers.len()
+        ));
+    }
+    if sections.len() != copied_sections.len() {
+        problems.push(format!(
+            "{} sections, {} copied",
+            sections.len(),
+            copied_sections.len()
+        ));
+    }
+    let copied_sections: HashMap<_, _> = copied_sections.into_iter().collect();
+    for (name, value) in &sections {
+        if copied_sections.get(name) != Some(value) {
+            problems.push(format!("section {} differs", name));
+        }
+    }
+    let copied_durations: HashMap<_, _> = copy.durations().into_iter().collect();
+    for (key, duration) in data.durations() {
+        match copied_durations.get(&key) {
+            Some(copied) if *copied == duration => {}
+            Some(copied) => problems.push(format!("{}: {:?}, copied {:?}", key, duration, copied)),
+            None => problems.push(format!("{} was not copied", key)),
+        }
+    }
+    Ok(problems)
+}
+
+/// Copies the state into the storage `to`, verifies the copy and moves the old state file aside.
+/// Returns the new storage.
+pub fn migrate(
+    config: &AppConfig,
+    current: &dyn Storage,
+    to: StorageBackend,
+    data: &InnerAppData,
+) -> Result<(Box<dyn Storage>, MigratedStorage), MigrationError> {
+    let from = current.backend();
+    if (from, to) != (StorageBackend::Json, StorageBackend::Sqlite) {
+        return Err(MigrationError::Unsupported { from, to });
+    }
+    if config.state_encryption_key.is_some() {
+        return Err(MigrationError::Unencrypted(to));
+    }
+    let path = config.sqlite_file();
+    if path.exists() {
+        return Err(MigrationError::Exists(path));
+    }
+    let sqlite = SqliteStorage::open(&path)?;
+    sqlite.save(data)?;
+    let problems = match sqlite.load()? {
+        Some(copy) => verify(data, &copy)?,
+        None => vec!["nothing was copied".to_string()],
+    };
+    if !problems.is_empty() {
+        drop(sqlite);
+        fs::remove_file(&path).map_err(FileError::IO)?;
+        return Err(MigrationError::Mismatch(problems));
+    }
+
+    let archived_file = if config.json_file.exists() {
+        let timestamp = clock::local_now().format("%Y%m%d%H%M%S");
+        let archived = with_suffix(&config.json_file, &format!(".migrated-{}", timestamp));
+        fs::rename(&config.json_file, &archived).map_err(FileError::IO)?;
+        Some(archived)
+    } else {
+        None
+    };
+    let (sections, trackers) = data.to_parts().map_err(StorageError::from)?;
+    tracing::info!("migrated the state to {}", path.display());
+    let migrated = MigratedStorage {
+        backend: to,
+        trackers: trackers.len(),
+        sections: sections.len(),
+        duration: data.durations().into_iter().map(|(_, d)| d).sum(),
+        archived_file,
+    };
+    Ok((Box::new(sqlite), migrated))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+
+    #[test]
+    fn sqlite_rows_of_older_versions_are_migrated() {
+        // tracked time was summed up in `duration` before version 2
+        let tracker = r#"{"id": "10001", "duration": {"secs": 3600, "nanos": 0},
+            "start_time": "2025-01-06T09:00:00Z"}"#;
+        let storage = SqliteStorage::open(":memory:").unwrap();
+        {
+            let connection = storage.connection.lock().unwrap();
+            connection
+                .execute(
+                    "INSERT INTO sections (name, data) VALUES ('version', '1')",
+                    [],
+                )
+                .unwrap();
+            connection
+                .execute(
+                    "INSERT INTO trackers (key, position, data) VALUES ('ABC-1', 0, ?1)",
+                    params![tracker],
+                )
+                .unwrap();
+        }
+
+        let data = storage.load().unwrap().unwrap();
+
+        let state = serde_json::to_value(&data).unwrap();
+        let session = &state["trackers"]["ABC-1"]["sessions"][0];
+        let start: DateTime<Utc> = serde_json::from_value(session["start"].clone()).unwrap();
+        let end: DateTime<Utc> = serde_json::from_value(session["end"].clone()).unwrap();
+        assert_eq!(
+            start,
+            "2025-01-06T09:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            end,
+            "2025-01-06T10:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(state["version"], app_data::MIGRATIONS.len());
+    }
+
+    #[test]
+    fn sqlite_rows_of_newer_versions_are_refused() {
+        let storage = SqliteStorage::open(":memory:").unwrap();
+        storage
+            .connection
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO sections (name, data) VALUES ('version', '99')",
+                [],
+            )
+            .unwrap();
+
+        assert!(matches!(
+            storage.load(),
+            Err(StorageError::File(FileError::UnknownVersion(99)))
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use futures::future::join_all;
+use reqwest::header::AUTHORIZATION;
+use serde::Serialize;
+
+use crate::clock;
+use crate::config::AppConfig;
+use domain::TrackerInformation;
+
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+struct Peer {
+    name: String,
+    url: String,
+    token: Option<String>,
+}
+
+/// Read-only view on the trackers of teammates' instances
+#[derive(Debug)]
+pub struct Team {
+    client: reqwest::Client,
+    peers: Vec<Peer>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TeamMember {
+    name: String,
+    reachable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current: Option<TrackerInformation>,
+    #[serde(with = "humantime_serde")]
+    today: Duration,
+}
+
+impl TeamMember {
+    fn new(name: &str, trackers: Option<Vec<TrackerInformation>>, today: NaiveDate) -> Self {
+        let reachable = trackers.is_some();
+        let trackers = trackers.unwrap_or_default();
+        Self {
+            name: name.to_string(),
+            reachable,
+            today: trackers
+                .iter()
+                .filter(|t| t.start_time.date_naive() == today)
+                .map(|t| t.duration)
+                .sum(),
+            current: trackers.into_iter().find(|t| t.running),
+        }
+    }
+}
+
+impl Team {
+    async fn trackers(&self, peer: &Peer) -> Result<Vec<TrackerInformation>, reqwest::Error> {
+        let mut request = self
+            .client
+            .get(format!("{}/trackers", peer.url.trim_end_matches('/')));
+        if let Some(token) = &peer.token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        request.send().await?.error_for_status()?.json().await
+    }
+
+    /// Current tracker and today's sum of every peer, ordered by name
+    pub async fn members(&self) -> Vec<TeamMember> {
+        let today = clock::local_now().date_naive();
+        join_all(self.peers.iter().map(|peer| async move {
+            let trackers = self
+                .trackers(peer)
+                .await
+                .map_err(|e| tracing::debug!("could not reach {}: {}", peer.name, e))
+                .ok();
+            TeamMember::new(&peer.name, trackers, today)
+        }))
+        .await
+    }
+}
+
+impl From<&AppConfig> for Team {
+    fn from(config: &AppConfig) -> Self {
+        let mut peers: Vec<Peer> = config
+            .team_peers
+            .iter()
+            .map(|(name, url)| Peer {
+                name: name.clone(),
+                url: url.clone(),
+                token: config.team_tokens.get(name).cloned(),
+            })
+            .collect();
+        peers.sort_by(|a, b| a.name.cmp(&b.name));
+        let client = reqwest::Client::builder()
+            .timeout(PEER_TIMEOUT)
+            .build()
+            .unwrap();
+        Self { client, peers }
+    }
+}
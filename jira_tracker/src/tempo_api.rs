@@ -1,15 +1,34 @@
+use std::collections::BTreeMap;
 use std::time::Duration;
 
-use futures::future::try_join_all;
+use chrono::{DateTime, Local, NaiveDate};
+use futures::future::join_all;
+use futures::FutureExt;
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::config::AppConfig;
+use crate::config::{AppConfig, PairingMode};
+use crate::jira_api;
+use crate::rounding::Rounding;
+use crate::routing::{SubmitRoute, SubmitRule, SubmitRules};
+use crate::sandbox::Sandbox;
+use crate::upstream;
+use crate::upstream::{Service, UpstreamError};
 use domain::TrackerInformation;
 
+/// Trackers below this duration are skipped on submission
+pub const MINIMUM_DURATION: Duration = Duration::from_secs(60);
+
 pub struct TempoApi {
     client: reqwest::Client,
     jira_account_id: String,
+    pairing_mode: PairingMode,
+    pairing_attribute: String,
+    account_attribute: String,
+    rounding: Rounding,
+    rules: SubmitRules,
+    /// Records worklogs instead of submitting them, see `sandbox.rs`
+    sandbox: Option<Sandbox>,
 }
 
 #[derive(Debug, Serialize)]
@@ -25,6 +44,52 @@ struct SubmitWorklogBody {
     description: Option<String>,
     #[serde(rename = "authorAccountId")]
     author_account_id: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attributes: Vec<WorkAttribute>,
+}
+
+/// Worklog as it would be submitted, see `POST /submit?dry_run=true`
+#[derive(Debug, Serialize)]
+pub struct PlannedWorklog {
+    key: String,
+    #[serde(with = "humantime_serde")]
+    duration: Duration,
+    start_time: DateTime<Local>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    author_account_id: String,
+    /// Rule of `SUBMIT_RULES` that applies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule: Option<String>,
+}
+
+/// Replaces `{name}` by the value of the custom field `name`, e.g. `Review of PR {pr}`
+fn expand_fields(description: String, fields: &BTreeMap<String, String>) -> String {
+    fields
+        .iter()
+        .fold(description, |description, (name, value)| {
+            description.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+/// Worklog to create for a tracker
+struct Worklog<'a> {
+    tracker: TrackerInformation,
+    /// Own account if not given
+    author: Option<String>,
+    rule: Option<&'a SubmitRule>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WorkAttribute {
+    key: String,
+    value: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct WorkAttributes {
+    #[serde(default)]
+    values: Vec<WorkAttribute>,
 }
 
 impl<ID> From<(TrackerInformation, ID)> for SubmitWorklogBody
@@ -39,12 +104,146 @@ where
             start_time: tracker.start_time.format("%H:%M:%S").to_string(),
             description: tracker.description,
             author_account_id: author_account_id.into(),
+            attributes: Vec::new(),
         }
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct SubmittedWorklog {
+    #[serde(rename = "tempoWorklogId")]
+    tempo_worklog_id: u64,
+    #[serde(rename = "jiraWorklogId")]
+    jira_worklog_id: Option<u64>,
+    #[serde(default)]
+    attributes: WorkAttributes,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountCategoryType {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountCategory {
+    pub key: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub category_type: Option<AccountCategoryType>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TempoAccount {
+    pub key: String,
+    pub name: String,
+    pub category: Option<AccountCategory>,
+    /// `OPEN`, `CLOSED` or `ARCHIVED`, only open accounts accept worklogs
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+impl TempoAccount {
+    pub fn is_open(&self) -> bool {
+        self.status.as_deref().is_none_or(|status| status == "OPEN")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TimesheetApproval {
+    status: TimesheetStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimesheetStatus {
+    /// `OPEN`, `IN_REVIEW` or `APPROVED`
+    key: String,
+}
+
+/// Worklog Tempo did not create on submission
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedWorklog {
+    pub key: String,
+    pub error: String,
+}
+
+/// Proof of a single worklog created on submission
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorklogReceipt {
+    pub key: String,
+    #[serde(with = "humantime_serde")]
+    pub duration: Duration,
+    pub tempo_worklog_id: u64,
+    /// Start of the worklog, missing in receipts of older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// The description was generated on submission
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub generated_description: bool,
+    /// Key of the Tempo account the worklog was logged on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// Rule of `SUBMIT_RULES` that routed the worklog
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    /// Custom fields of the tracker
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlansResponse {
+    results: Vec<TempoPlan>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TempoPlan {
+    #[serde(rename = "planItem")]
+    pub plan_item: PlanItem,
+    #[serde(rename = "plannedSecondsPerDay", default)]
+    pub planned_seconds_per_day: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlanItem {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub item_type: PlanItemType,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PlanItemType {
+    Issue,
+    Project,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduleResponse {
+    results: Vec<ScheduleDay>,
+}
+
+/// Day of the user's work schedule in Tempo
+#[derive(Debug, Deserialize)]
+pub struct ScheduleDay {
+    pub date: NaiveDate,
+    #[serde(rename = "requiredSeconds", default)]
+    pub required_seconds: u64,
+}
+
 impl TempoApi {
-    fn new<ID: Into<String>>(tempo_api_token: &str, jira_account_id: ID) -> Self {
+    fn new<ID: Into<String>>(
+        tempo_api_token: &str,
+        jira_account_id: ID,
+        pairing_mode: PairingMode,
+        pairing_attribute: String,
+        account_attribute: String,
+        rounding: Rounding,
+        rules: SubmitRules,
+    ) -> Self {
         let mut authorization_value: HeaderValue =
             format!("Bearer {}", tempo_api_token).parse().unwrap();
         authorization_value.set_sensitive(true);
@@ -60,29 +259,280 @@ impl TempoApi {
         Self {
             client,
             jira_account_id: jira_account_id.into(),
+            pairing_mode,
+            pairing_attribute,
+            account_attribute,
+            rounding,
+            rules,
+            sandbox: None,
         }
     }
 
-    pub async fn submit(&self, tracker: TrackerInformation) -> Result<(), reqwest::Error> {
-        let request: SubmitWorklogBody = (tracker, &self.jira_account_id).into();
-        let builder = self
+    pub fn account_id(&self) -> &str {
+        &self.jira_account_id
+    }
+
+    /// Submits the worklog for its author, or for the own account if none is given
+    async fn submit(&self, worklog: Worklog<'_>) -> Result<WorklogReceipt, UpstreamError> {
+        let Worklog {
+            tracker,
+            author,
+            rule,
+        } = worklog;
+        let author = author.as_deref().unwrap_or(&self.jira_account_id);
+        let key = tracker.key.clone();
+        let duration = tracker.duration;
+        let start_time = tracker.start_time;
+        let paired_with = tracker.paired_with.clone();
+        let fields = tracker.fields.clone();
+        let mut request: SubmitWorklogBody = (tracker, author).into();
+        if let (PairingMode::Attribute, Some(paired_with)) = (self.pairing_mode, paired_with) {
+            request.attributes.push(WorkAttribute {
+                key: self.pairing_attribute.clone(),
+                value: paired_with,
+            });
+        }
+        if let Some(SubmitRoute::Account(account)) = rule.map(|rule| &rule.route) {
+            request.attributes.push(WorkAttribute {
+                key: self.account_attribute.clone(),
+                value: account.clone(),
+            });
+        }
+        let worklog: SubmittedWorklog = match &self.sandbox {
+            Some(sandbox) => SubmittedWorklog {
+                tempo_worklog_id: sandbox.record(&request)?,
+                jira_worklog_id: None,
+                attributes: WorkAttributes {
+                    values: request.attributes,
+                },
+            },
+            None => {
+                let builder = self
+                    .client
+                    .post("https://api.tempo.io/4/worklogs")
+                    .json(&request);
+                upstream::send_json(Service::Tempo, builder).await?
+            }
+        };
+        let account = self.account_of(&worklog);
+        Ok(WorklogReceipt {
+            url: worklog
+                .jira_worklog_id
+                .map(|id| jira_api::worklog_url(&key, id)),
+            key,
+            duration,
+            tempo_worklog_id: worklog.tempo_worklog_id,
+            start_time: Some(start_time),
+            generated_description: false,
+            account,
+            rule: rule.map(ToString::to_string),
+            fields,
+        })
+    }
+
+    fn account_of(&self, worklog: &SubmittedWorklog) -> Option<String> {
+        worklog
+            .attributes
+            .values
+            .iter()
+            .find(|attribute| attribute.key == self.account_attribute)
+            .map(|attribute| attribute.value.clone())
+    }
+
+    /// Key of the Tempo account of an existing worklog
+    pub async fn get_worklog_account(
+        &self,
+        tempo_worklog_id: u64,
+    ) -> Result<Option<String>, UpstreamError> {
+        if self.sandbox.is_some() {
+            return Ok(None);
+        }
+        let url = format!("https://api.tempo.io/4/worklogs/{}", tempo_worklog_id);
+        let request = self.client.get(url);
+        let worklog: SubmittedWorklog = upstream::send_json(Service::Tempo, request).await?;
+        Ok(self.account_of(&worklog))
+    }
+
+    pub async fn get_account(&self, key: &str) -> Result<TempoAccount, UpstreamError> {
+        if self.sandbox.is_some() {
+            return Ok(TempoAccount {
+                key: key.to_string(),
+                name: key.to_string(),
+                category: None,
+                status: None,
+            });
+        }
+        let url = format!("https://api.tempo.io/4/accounts/{}", key);
+        let request = self.client.get(url);
+        upstream::send_json(Service::Tempo, request).await
+    }
+
+    /// Approval status of the own timesheet of the period containing `date`, worklogs can only
+    /// be added while it is `OPEN`
+    pub async fn get_timesheet_status(&self, date: NaiveDate) -> Result<String, UpstreamError> {
+        if self.sandbox.is_some() {
+            return Ok("OPEN".to_string());
+        }
+        let url = format!(
+            "https://api.tempo.io/4/timesheet-approvals/user/{}",
+            self.jira_account_id
+        );
+        let date = date.format("%Y-%m-%d").to_string();
+        let request = self
             .client
-            .post("https://api.tempo.io/4/worklogs")
-            .json(&request);
-        builder.send().await?.error_for_status()?;
-        Ok(())
+            .get(url)
+            .query(&[("from", date.clone()), ("to", date)]);
+        let approval: TimesheetApproval = upstream::send_json(Service::Tempo, request).await?;
+        Ok(approval.status.key)
+    }
+
+    /// Key of the Tempo account `SUBMIT_RULES` log the tracker on
+    pub fn account_for(&self, key: &str) -> Option<&str> {
+        match self.rules.route(key).map(|rule| &rule.route) {
+            Some(SubmitRoute::Account(account)) => Some(account),
+            _ => None,
+        }
+    }
+
+    pub async fn get_plans(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<TempoPlan>, UpstreamError> {
+        if self.sandbox.is_some() {
+            return Ok(Vec::new());
+        }
+        let url = format!("https://api.tempo.io/4/plans/user/{}", self.jira_account_id);
+        let request = self.client.get(url).query(&[
+            ("from", from.format("%Y-%m-%d").to_string()),
+            ("to", to.format("%Y-%m-%d").to_string()),
+        ]);
+        let plans: PlansResponse = upstream::send_json(Service::Tempo, request).await?;
+        Ok(plans.results)
     }
 
+    /// Required working time per day, e.g. less on part-time days and none on holidays
+    pub async fn get_user_schedule(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<ScheduleDay>, UpstreamError> {
+        if self.sandbox.is_some() {
+            return Ok(Vec::new());
+        }
+        let url = format!(
+            "https://api.tempo.io/4/user-schedule/{}",
+            self.jira_account_id
+        );
+        let request = self.client.get(url).query(&[
+            ("from", from.format("%Y-%m-%d").to_string()),
+            ("to", to.format("%Y-%m-%d").to_string()),
+        ]);
+        let schedule: ScheduleResponse = upstream::send_json(Service::Tempo, request).await?;
+        Ok(schedule.results)
+    }
+
+    /// Worklogs to submit for the tracker with their authors, halving paired trackers in
+    /// [`PairingMode::Split`]
+    fn split_pairing(
+        &self,
+        tracker: TrackerInformation,
+        author_account_id: Option<&str>,
+    ) -> Vec<(TrackerInformation, Option<String>)> {
+        let author_account_id = author_account_id.map(str::to_string);
+        match (self.pairing_mode, &tracker.paired_with) {
+            (PairingMode::Split, Some(_)) => {
+                let mut own = tracker;
+                own.duration /= 2;
+                let mut colleague = own.clone();
+                let partner = own.paired_with.take();
+                colleague.paired_with = None;
+                vec![(own, author_account_id), (colleague, partner)]
+            }
+            _ => vec![(tracker, author_account_id)],
+        }
+    }
+
+    /// Worklogs to submit, skipping external trackers and those skipped by `SUBMIT_RULES`,
+    /// splitting paired ones and rounding their durations, skipping those short after rounding
+    fn planned(
+        &self,
+        trackers: Vec<TrackerInformation>,
+        author_account_id: Option<&str>,
+    ) -> Vec<Worklog<'_>> {
+        trackers
+            .into_iter()
+            .filter(|tracker| !tracker.external)
+            .filter(|tracker| !self.rules.skips(&tracker.key))
+            .flat_map(|tracker| self.split_pairing(tracker, author_account_id))
+            .map(|(mut tracker, author)| {
+                tracker.duration = self.rounding.worklog(tracker.duration);
+                (tracker, author)
+            })
+            .filter(|(tracker, _)| tracker.duration >= MINIMUM_DURATION)
+            .map(|(mut tracker, author)| {
+                tracker.description = tracker
+                    .description
+                    .map(|description| expand_fields(description, &tracker.fields));
+                Worklog {
+                    rule: self.rules.route(&tracker.key),
+                    tracker,
+                    author,
+                }
+            })
+            .collect()
+    }
+
+    /// Keys of the trackers that `SUBMIT_RULES` keep from being submitted
+    pub fn skipped(&self, trackers: &[TrackerInformation]) -> Vec<String> {
+        let mut skipped: Vec<String> = Vec::new();
+        for tracker in trackers {
+            if !tracker.external
+                && self.rules.skips(&tracker.key)
+                && !skipped.contains(&tracker.key)
+            {
+                skipped.push(tracker.key.clone());
+            }
+        }
+        skipped
+    }
+
+    /// Worklogs [`TempoApi::submit_all`] would create
+    pub fn preview(
+        &self,
+        trackers: Vec<TrackerInformation>,
+        author_account_id: Option<&str>,
+    ) -> Vec<PlannedWorklog> {
+        self.planned(trackers, author_account_id)
+            .into_iter()
+            .map(|worklog| PlannedWorklog {
+                rule: worklog.rule.map(ToString::to_string),
+                key: worklog.tracker.key,
+                duration: worklog.tracker.duration,
+                start_time: worklog.tracker.start_time,
+                description: worklog.tracker.description,
+                author_account_id: worklog
+                    .author
+                    .unwrap_or_else(|| self.jira_account_id.clone()),
+            })
+            .collect()
+    }
+
+    /// Creates all worklogs, returning the outcome of each with the key of its tracker
     pub async fn submit_all(
         &self,
         trackers: Vec<TrackerInformation>,
-    ) -> Result<(), reqwest::Error> {
-        let results: Vec<_> = trackers
+        author_account_id: Option<&str>,
+    ) -> Vec<(String, Result<WorklogReceipt, UpstreamError>)> {
+        let results: Vec<_> = self
+            .planned(trackers, author_account_id)
             .into_iter()
-            .filter(|tracker| tracker.duration >= Duration::from_secs(60))
-            .map(|tracker| self.submit(tracker))
+            .map(|worklog| {
+                let key = worklog.tracker.key.clone();
+                self.submit(worklog).map(|result| (key, result))
+            })
             .collect();
-        try_join_all(results).await.map(|_| ())
+        join_all(results).await
     }
 }
 
@@ -91,6 +541,18 @@ where
     ID: Into<String>,
 {
     fn from((config, jira_account_id): (&AppConfig, ID)) -> Self {
-        TempoApi::new(&config.tempo_api_token, jira_account_id.into())
+        let mut api = TempoApi::new(
+            &config.tempo_api_token,
+            jira_account_id.into(),
+            config.pairing_mode,
+            config.pairing_attribute.clone(),
+            config.tempo_account_attribute.clone(),
+            config.rounding(),
+            config.submit_rules.clone(),
+        );
+        if config.sandbox {
+            api.sandbox = Some(Sandbox::new(config.sandbox_file()));
+        }
+        api
     }
 }
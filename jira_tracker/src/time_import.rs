@@ -0,0 +1,135 @@
+//! Import of time tracked in other tools, see `toggl.rs` and `clockify.rs`. The issue key is taken
+//! from the description of each entry, e.g. `ABC-1 Fix login`, and the entries become sessions of
+//! the tracker of their key.
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Local};
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use domain::Detail;
+
+use crate::app_data::{AppData, TrackerError};
+use crate::issue_cache::IssueCache;
+use crate::jira_api;
+use crate::jira_api::JiraApi;
+use crate::rounding;
+
+#[derive(Debug)]
+pub enum TimeImportError {
+    /// The body is not an export of the tool
+    Format(String),
+    /// An entry could not be added to its tracker
+    Tracker(TrackerError),
+}
+
+impl IntoResponse for TimeImportError {
+    fn into_response(self) -> Response {
+        match self {
+            TimeImportError::Format(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+            TimeImportError::Tracker(e) => e.into_response(),
+        }
+    }
+}
+
+/// Entry of an export with the issue key of its description
+#[derive(Debug)]
+pub struct ExternalEntry {
+    key: String,
+    /// Rest of the description
+    description: Option<String>,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+}
+
+impl ExternalEntry {
+    pub fn parse(
+        description: &str,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Result<Self, String> {
+        let key = jira_api::issue_key_pattern()
+            .find(description)
+            .ok_or_else(|| format!("no issue key in '{}'", description))?;
+        let rest = format!(
+            "{} {}",
+            &description[..key.start()],
+            &description[key.end()..]
+        );
+        let rest = rest
+            .trim()
+            .trim_start_matches([':', '-'])
+            .trim()
+            .to_string();
+        Ok(Self {
+            key: key.as_str().to_string(),
+            description: (!rest.is_empty()).then_some(rest),
+            start,
+            end,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportedTracker {
+    key: String,
+    /// Time added to the tracker, without entries overlapping time that was already tracked
+    #[serde(with = "humantime_serde")]
+    duration: Duration,
+}
+
+/// Response of `POST /import/toggl` and `POST /import/clockify`
+#[derive(Debug, Default, Serialize)]
+pub struct TimeImport {
+    imported: Vec<ImportedTracker>,
+    /// Entries that could not be imported, with the reason
+    skipped: Vec<String>,
+}
+
+/// Adds the entries to the trackers of their keys, creating missing ones. Entries that could not
+/// be parsed are listed as skipped with the reason.
+pub async fn import(
+    data: &AppData,
+    cache: &IssueCache,
+    jira: &JiraApi,
+    parsed: Vec<Result<ExternalEntry, String>>,
+) -> Result<TimeImport, TimeImportError> {
+    let mut result = TimeImport::default();
+    let mut entries: IndexMap<String, Vec<ExternalEntry>> = IndexMap::new();
+    for entry in parsed {
+        match entry {
+            Ok(entry) => entries.entry(entry.key.clone()).or_default().push(entry),
+            Err(reason) => result.skipped.push(reason),
+        }
+    }
+
+    for (key, entries) in entries {
+        if data.get_tracker(&key, Detail::Summary).is_err() {
+            match cache.get_or_fetch(jira, &key).await {
+                Ok(issue) => {
+                    // fails only if the tracker was created in the meantime
+                    let _ = data.create_tracker(&key, &issue.id, Some((&issue).into()));
+                }
+                Err(e) => {
+                    result
+                        .skipped
+                        .push(format!("{} ({} entries): {}", key, entries.len(), e));
+                    continue;
+                }
+            }
+        }
+        let sessions: Vec<_> = entries.iter().map(|e| (e.start, e.end)).collect();
+        let description = entries.iter().find_map(|e| e.description.clone());
+        let duration = data
+            .import_sessions(&key, &sessions, description)
+            .map_err(TimeImportError::Tracker)?;
+        result.imported.push(ImportedTracker {
+            key,
+            duration: rounding::seconds(duration),
+        });
+    }
+    Ok(result)
+}
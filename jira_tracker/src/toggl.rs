@@ -0,0 +1,52 @@
+//! Toggl CSV export for `POST /import/toggl`, the detailed report of Toggl Track
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use serde::Deserialize;
+
+use crate::time_import::{ExternalEntry, TimeImportError};
+
+/// Row of the CSV export, other columns are ignored
+#[derive(Debug, Deserialize)]
+struct TogglRow {
+    #[serde(rename = "Description")]
+    description: String,
+    #[serde(rename = "Start date", alias = "Start Date")]
+    start_date: NaiveDate,
+    #[serde(rename = "Start time", alias = "Start Time")]
+    start_time: NaiveTime,
+    #[serde(rename = "End date", alias = "End Date")]
+    end_date: NaiveDate,
+    #[serde(rename = "End time", alias = "End Time")]
+    end_time: NaiveTime,
+}
+
+fn local(date: NaiveDate, time: NaiveTime) -> Option<DateTime<Local>> {
+    date.and_time(time).and_local_timezone(Local).earliest()
+}
+
+impl TogglRow {
+    fn entry(&self) -> Result<ExternalEntry, String> {
+        let (Some(start), Some(end)) = (
+            local(self.start_date, self.start_time),
+            local(self.end_date, self.end_time),
+        ) else {
+            return Err(format!("invalid time of '{}'", self.description));
+        };
+        ExternalEntry::parse(&self.description, start, end)
+    }
+}
+
+/// Entries started on `first` or later
+pub fn parse(
+    csv: &str,
+    first: NaiveDate,
+) -> Result<Vec<Result<ExternalEntry, String>>, TimeImportError> {
+    let mut entries = Vec::new();
+    for row in csv::Reader::from_reader(csv.as_bytes()).deserialize() {
+        let row: TogglRow = row.map_err(|e| TimeImportError::Format(e.to_string()))?;
+        if row.start_date >= first {
+            entries.push(row.entry());
+        }
+    }
+    Ok(entries)
+}
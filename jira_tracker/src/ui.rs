@@ -0,0 +1,125 @@
+//! The dashboard under `/ui`, built from the assets in `ui/`. Release builds embed them with
+//! rust-embed. With the `dev` feature they are read from disk on every request instead, so that
+//! changes show up without a rebuild, and the page reloads itself once `GET /ui/version` changes.
+//! Assets are referenced with that version, so that browsers may cache them for good. The service
+//! worker `sw.js` keeps the page available offline, which makes the dashboard an installable PWA.
+
+use std::borrow::Cow;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::rounding::DisplayRounding;
+
+/// Whether the assets are read from disk
+pub const LIVE: bool = cfg!(feature = "dev");
+
+#[cfg(not(feature = "dev"))]
+#[derive(rust_embed::RustEmbed)]
+#[folder = "ui/"]
+struct Assets;
+
+#[cfg(feature = "dev")]
+const DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/ui");
+
+/// Response of `GET /ui/version`
+#[derive(Debug, Serialize)]
+pub struct UiVersion {
+    pub version: String,
+    pub live: bool,
+}
+
+#[cfg(not(feature = "dev"))]
+pub fn asset(path: &str) -> Option<Cow<'static, [u8]>> {
+    Assets::get(path).map(|file| file.data)
+}
+
+#[cfg(feature = "dev")]
+pub fn asset(path: &str) -> Option<Cow<'static, [u8]>> {
+    use std::path::{Component, Path};
+    // only plain names, no `..`, roots or prefixes that would leave the directory
+    let relative = Path::new(path);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    std::fs::read(Path::new(DIRECTORY).join(relative))
+        .ok()
+        .map(Cow::Owned)
+}
+
+/// Hash of all assets, computed once as they cannot change
+#[cfg(not(feature = "dev"))]
+pub fn version() -> String {
+    static VERSION: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    VERSION
+        .get_or_init(|| {
+            let paths: Vec<String> = Assets::iter().map(|path| path.to_string()).collect();
+            digest(paths)
+        })
+        .clone()
+}
+
+/// Hash of all assets currently on disk
+#[cfg(feature = "dev")]
+pub fn version() -> String {
+    fn collect(directory: &std::path::Path, paths: &mut Vec<String>) {
+        let Ok(entries) = std::fs::read_dir(directory) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                collect(&path, paths);
+            } else if let Ok(relative) = path.strip_prefix(DIRECTORY) {
+                paths.push(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    let mut paths = Vec::new();
+    collect(std::path::Path::new(DIRECTORY), &mut paths);
+    digest(paths)
+}
+
+fn digest(mut paths: Vec<String>) -> String {
+    paths.sort();
+    let mut hasher = Sha256::new();
+    for path in paths {
+        hasher.update(path.as_bytes());
+        hasher.update(asset(&path).unwrap_or_default());
+    }
+    hasher.finalize()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// The dashboard page, with its assets referenced by the current version and the rounding of
+/// shown durations
+pub fn index(display: DisplayRounding) -> Option<String> {
+    let html = asset("index.html")?;
+    let step = display.step.map_or(0, |step| step.as_secs());
+    Some(
+        String::from_utf8_lossy(&html)
+            .replace("{{version}}", &version())
+            .replace("{{display_step}}", &step.to_string()),
+    )
+}
+
+pub fn content_type(path: &str) -> String {
+    mime_guess::from_path(path)
+        .first_or_octet_stream()
+        .to_string()
+}
+
+/// Assets requested with their version may be cached for good unless they are live. The
+/// service worker and the icon are requested without.
+pub fn cache_control(versioned: bool) -> &'static str {
+    match (LIVE, versioned) {
+        (true, _) => "no-store",
+        (false, true) => "public, max-age=31536000, immutable",
+        (false, false) => "no-cache",
+    }
+}
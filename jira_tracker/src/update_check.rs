@@ -0,0 +1,97 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use axum::http::header::USER_AGENT;
+use serde::{Deserialize, Serialize};
+
+use crate::scheduler::Scheduler;
+
+const LATEST_RELEASE_URI: &str =
+    "https://api.github.com/repos/frankruegamer/jira_tracker/releases/latest";
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    #[serde(rename = "tag_name")]
+    pub version: String,
+    #[serde(rename = "html_url")]
+    pub url: String,
+}
+
+/// Latest release on GitHub, only ever used to notify about updates
+#[derive(Debug, Default)]
+pub struct UpdateCheck {
+    latest: RwLock<Option<Release>>,
+}
+
+/// Numeric components of a version like `v1.2.3`, ignoring anything that is not a number
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| part.parse().unwrap_or_default())
+        .collect()
+}
+
+impl UpdateCheck {
+    pub fn current_version(&self) -> &'static str {
+        CURRENT_VERSION
+    }
+
+    /// `None` if no check succeeded yet
+    pub fn update_available(&self) -> Option<bool> {
+        self.latest
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|release| version_parts(&release.version) > version_parts(CURRENT_VERSION))
+    }
+
+    pub fn latest(&self) -> Option<Release> {
+        self.latest.read().unwrap().clone()
+    }
+
+    async fn check(&self, client: &reqwest::Client) -> Result<(), reqwest::Error> {
+        let release: Release = client
+            .get(LATEST_RELEASE_URI)
+            .header(
+                USER_AGENT,
+                concat!("jira_tracker/", env!("CARGO_PKG_VERSION")),
+            )
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        *self.latest.write().unwrap() = Some(release.clone());
+        if self.update_available() == Some(true) {
+            tracing::info!(
+                "jira_tracker {} is available (running {}), see {}",
+                release.version,
+                CURRENT_VERSION,
+                release.url
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Checks for a new release now and then every `interval`, never installing anything
+pub fn schedule_update_check(
+    scheduler: &Scheduler,
+    update_check: Arc<UpdateCheck>,
+    interval: Duration,
+) {
+    let client = reqwest::Client::new();
+    scheduler.add("update_check", interval, move || {
+        let (update_check, client) = (update_check.clone(), client.clone());
+        async move {
+            update_check
+                .check(&client)
+                .await
+                .map_err(|e| e.to_string())?;
+            let latest = update_check.latest().map(|release| release.version);
+            Ok(format!("latest release is {}", latest.unwrap_or_default()))
+        }
+    });
+}
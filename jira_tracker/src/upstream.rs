@@ -0,0 +1,221 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use axum::http::header::RETRY_AFTER;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Service {
+    Jira,
+    Tempo,
+}
+
+impl Display for Service {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Service::Jira => write!(f, "Jira"),
+            Service::Tempo => write!(f, "Tempo"),
+        }
+    }
+}
+
+/// Failures of requests to Jira or Tempo, carrying the upstream error details
+#[derive(Debug)]
+pub enum UpstreamError {
+    JiraUnauthorized(String),
+    JiraNotFound(String),
+    TempoUnauthorized(String),
+    TempoValidation(String),
+    RateLimited {
+        service: Service,
+        retry_after: Option<String>,
+    },
+    Network {
+        service: Service,
+        detail: String,
+    },
+    Unexpected {
+        service: Service,
+        status: u16,
+        detail: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorBody<'a> {
+    error: &'static str,
+    service: Service,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after: Option<&'a str>,
+    detail: &'a str,
+}
+
+impl UpstreamError {
+    pub fn service(&self) -> Service {
+        match self {
+            UpstreamError::JiraUnauthorized(_) | UpstreamError::JiraNotFound(_) => Service::Jira,
+            UpstreamError::TempoUnauthorized(_) | UpstreamError::TempoValidation(_) => {
+                Service::Tempo
+            }
+            UpstreamError::RateLimited { service, .. }
+            | UpstreamError::Network { service, .. }
+            | UpstreamError::Unexpected { service, .. } => *service,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            UpstreamError::JiraUnauthorized(_) => "jira_unauthorized",
+            UpstreamError::JiraNotFound(_) => "jira_not_found",
+            UpstreamError::TempoUnauthorized(_) => "tempo_unauthorized",
+            UpstreamError::TempoValidation(_) => "tempo_validation",
+            UpstreamError::RateLimited { .. } => "rate_limited",
+            UpstreamError::Network { .. } => "network",
+            UpstreamError::Unexpected { .. } => "unexpected",
+        }
+    }
+
+    fn detail(&self) -> &str {
+        match self {
+            UpstreamError::JiraUnauthorized(detail)
+            | UpstreamError::JiraNotFound(detail)
+            | UpstreamError::TempoUnauthorized(detail)
+            | UpstreamError::TempoValidation(detail)
+            | UpstreamError::Network { detail, .. }
+            | UpstreamError::Unexpected { detail, .. } => detail,
+            UpstreamError::RateLimited { .. } => "too many requests",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            UpstreamError::JiraUnauthorized(_) | UpstreamError::TempoUnauthorized(_) => {
+                StatusCode::UNAUTHORIZED
+            }
+            UpstreamError::JiraNotFound(_) => StatusCode::NOT_FOUND,
+            UpstreamError::TempoValidation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            UpstreamError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            UpstreamError::Network { .. } | UpstreamError::Unexpected { .. } => {
+                StatusCode::BAD_GATEWAY
+            }
+        }
+    }
+
+    fn from_status(service: Service, status: StatusCode, detail: String) -> Self {
+        match (service, status) {
+            (_, StatusCode::TOO_MANY_REQUESTS) => UpstreamError::RateLimited {
+                service,
+                retry_after: None,
+            },
+            (Service::Jira, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                UpstreamError::JiraUnauthorized(detail)
+            }
+            (Service::Jira, StatusCode::NOT_FOUND) => UpstreamError::JiraNotFound(detail),
+            (Service::Tempo, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                UpstreamError::TempoUnauthorized(detail)
+            }
+            (Service::Tempo, StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY) => {
+                UpstreamError::TempoValidation(detail)
+            }
+            _ => UpstreamError::Unexpected {
+                service,
+                status: status.as_u16(),
+                detail,
+            },
+        }
+    }
+
+    fn from_reqwest(service: Service, error: reqwest::Error) -> Self {
+        UpstreamError::Network {
+            service,
+            detail: error.to_string(),
+        }
+    }
+}
+
+impl Display for UpstreamError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} request failed ({}): {}",
+            self.service(),
+            self.name(),
+            self.detail()
+        )
+    }
+}
+
+impl Error for UpstreamError {}
+
+impl IntoResponse for UpstreamError {
+    fn into_response(self) -> Response {
+        tracing::warn!("{}", self);
+        let body = ErrorBody {
+            error: self.name(),
+            service: self.service(),
+            status: match &self {
+                UpstreamError::Unexpected { status, .. } => Some(*status),
+                _ => None,
+            },
+            retry_after: match &self {
+                UpstreamError::RateLimited { retry_after, .. } => retry_after.as_deref(),
+                _ => None,
+            },
+            detail: self.detail(),
+        };
+        (self.status_code(), Json(body)).into_response()
+    }
+}
+
+/// Sends the request and turns non-success responses into an [`UpstreamError`]
+pub async fn send(
+    service: Service,
+    request: RequestBuilder,
+) -> Result<reqwest::Response, UpstreamError> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| UpstreamError::from_reqwest(service, e))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let detail = response.text().await.unwrap_or_default();
+    Err(match UpstreamError::from_status(service, status, detail) {
+        UpstreamError::RateLimited { service, .. } => UpstreamError::RateLimited {
+            service,
+            retry_after,
+        },
+        error => error,
+    })
+}
+
+/// Like [`send`], deserializing the response body
+pub async fn send_json<T: DeserializeOwned>(
+    service: Service,
+    request: RequestBuilder,
+) -> Result<T, UpstreamError> {
+    send(service, request)
+        .await?
+        .json::<T>()
+        .await
+        .map_err(|e| UpstreamError::Unexpected {
+            service,
+            status: StatusCode::OK.as_u16(),
+            detail: e.to_string(),
+        })
+}
@@ -1,107 +1,369 @@
+use std::cmp::Reverse;
+use std::convert::Infallible;
 use std::fmt::Debug;
 use std::sync::Arc;
 use std::time::Duration;
 
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
-use axum::routing::{get, post};
+use axum::body::Bytes;
+use axum::extract::ws::WebSocketUpgrade;
+use axum::extract::{Form, Path, Query, State};
+use axum::http::header::{
+    AUTHORIZATION, CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, HOST, ORIGIN, REFERER, WARNING,
+};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{get, post, put};
 use axum::{Json, Router};
+use chrono::{DateTime, Datelike, IsoWeek, Local, NaiveDate};
+use futures::future::join_all;
+use futures::{Stream, StreamExt};
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use crate::app_data::{AppData, TrackerError};
-use crate::config::LogError;
-use crate::jira_api::JiraApi;
-use crate::tempo_api::TempoApi;
+use crate::accounts;
+use crate::accounts::AccountTime;
+use crate::activity::{Activity, ActivityLog, IssueActivity};
+use crate::anomalies;
+use crate::anomalies::Anomaly;
+use crate::app_data::{
+    AppData, ArchivedTracker, Compaction, ImportMode, Submission, TrackerError, TrackerSession,
+    TrashedTracker,
+};
+use crate::audit;
+use crate::audit::{AuditEntry, AuditQuery};
+use crate::auth_guard::{AuthGuard, Rejection};
+use crate::chatops;
+use crate::chatops::{MattermostCommand, MattermostResponse, TeamsMessage, TeamsResponse};
+use crate::clients::{ClientInfo, ClientRegistry};
+use crate::clock;
+use crate::clockify;
+use crate::commands;
+use crate::commands::CommandContext;
+use crate::config::AppConfig;
+use crate::day::{ClosedDay, DaySummary, DayTotals, OpenedDay, PrunePolicy};
+use crate::descriptions;
+use crate::descriptions::{Commit, CommitFeed};
+use crate::diagnostics;
+use crate::diagnostics::{Diagnostics, StorageHealth, Versions};
+use crate::events;
+use crate::filter::{Filter, FilterError};
+use crate::goals::{Goal, GoalBody, GoalError, GoalProgress};
+use crate::html;
+use crate::html::{FormAction, TrackerForm};
+use crate::issue_cache::IssueCache;
+use crate::jira_api::{JiraApi, JiraIssue, JiraProject};
+use crate::jira_webhook;
+use crate::jira_webhook::{JiraWebhookEvent, Trigger};
+use crate::journal;
+use crate::journal::JournalError;
+use crate::lease::WriterLease;
+use crate::plans::PlanOverview;
+use crate::project_cache::ProjectCache;
+use crate::report::{week_start_of, Report};
+use crate::review;
+use crate::review::SubmitReview;
+use crate::rounding::DisplayRounding;
+use crate::schedule::Schedule;
+use crate::scheduler::{JobStatus, Scheduler};
+use crate::selection::{FieldsQuery, Selected};
+use crate::sprint::{self, SprintCapacity};
+use crate::storage::{MigratedStorage, MigrationError, StorageBackend};
+use crate::team::{Team, TeamMember};
+use crate::tempo_api::{FailedWorklog, TempoApi};
+use crate::time_import;
+use crate::time_import::{TimeImport, TimeImportError};
+use crate::toggl;
+use crate::ui;
+use crate::ui::UiVersion;
+use crate::update_check::{Release, UpdateCheck};
+use crate::upstream::UpstreamError;
+use crate::websocket;
 use crate::AppState;
-use domain::TrackerInformation;
+use domain::{AdjustTracker, Detail, Pair, PatchTracker, ReportFormat, Sum, TrackerInformation};
 
-async fn list(State(state): State<Arc<AppData>>) -> Json<Vec<TrackerInformation>> {
-    Json(state.list_trackers())
+#[derive(Debug, Deserialize)]
+struct DetailQuery {
+    #[serde(default)]
+    detail: Detail,
+}
+
+const ARCHIVE_CANDIDATES: HeaderName = HeaderName::from_static("x-archive-candidates");
+/// Number of trackers matching the filters of `GET /trackers`, regardless of the page
+const TOTAL_COUNT: HeaderName = HeaderName::from_static("x-total-count");
+/// HMAC signature of Jira webhooks with a secret
+const HUB_SIGNATURE: HeaderName = HeaderName::from_static("x-hub-signature");
+/// Issues looked up at once, e.g. by `POST /trackers`, so that a long list doesn't flood Jira
+const CONCURRENT_LOOKUPS: usize = 8;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TrackerSort {
+    Key,
+    StartTime,
+    /// Longest first
+    Duration,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListQuery {
+    running: Option<bool>,
+    /// Project key like `ABC`
+    project: Option<String>,
+    sort: Option<TrackerSort>,
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+}
+
+impl ListQuery {
+    fn is_plain(&self) -> bool {
+        self.running.is_none()
+            && self.project.is_none()
+            && self.sort.is_none()
+            && self.limit.is_none()
+            && self.offset == 0
+    }
+
+    /// Matching trackers sorted, with their number before paging
+    fn apply(&self, mut trackers: Vec<TrackerInformation>) -> (Vec<TrackerInformation>, usize) {
+        trackers.retain(|tracker| {
+            let project = tracker.key.split_once('-').map_or("", |(p, _)| p);
+            self.running
+                .is_none_or(|running| tracker.running == running)
+                && self
+                    .project
+                    .as_ref()
+                    .is_none_or(|p| p.eq_ignore_ascii_case(project))
+        });
+        match self.sort {
+            Some(TrackerSort::Key) => trackers.sort_by(|a, b| a.key.cmp(&b.key)),
+            Some(TrackerSort::StartTime) => trackers.sort_by_key(|t| t.start_time),
+            Some(TrackerSort::Duration) => trackers.sort_by_key(|t| Reverse(t.duration)),
+            None => {}
+        }
+        let total = trackers.len();
+        let page = trackers
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+        (page, total)
+    }
+}
+
+async fn list(
+    Query(query): Query<DetailQuery>,
+    Query(list): Query<ListQuery>,
+    Query(fields): Query<FieldsQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Response {
+    if !list.is_plain() {
+        let (trackers, total) = list.apply(state.list_trackers(query.detail));
+        let headers = [(TOTAL_COUNT, total.to_string())];
+        return (headers, fields.select(trackers)).into_response();
+    }
+    let trackers = state.listing(query.detail);
+    let mut headers = HeaderMap::new();
+    if let Some(limit) = config.tracker_soft_limit.filter(|l| trackers.len() > *l) {
+        let warning = format!(
+            "299 jira_tracker \"{} trackers exceed the soft limit of {}\"",
+            trackers.len(),
+            limit
+        );
+        tracing::warn!("{}", warning);
+        headers.insert(WARNING, warning.parse().unwrap());
+        let candidates = state.archive_candidates(limit).join(",");
+        if let Ok(value) = candidates.parse() {
+            headers.insert(ARCHIVE_CANDIDATES, value);
+        }
+    }
+    (headers, fields.select(trackers)).into_response()
 }
 
 async fn get_tracker(
     Path(key): Path<String>,
+    Query(query): Query<DetailQuery>,
+    Query(fields): Query<FieldsQuery>,
     State(state): State<Arc<AppData>>,
-) -> Result<Json<TrackerInformation>, TrackerError> {
-    state.get_tracker(&key).map(Json)
+) -> Result<Selected<TrackerInformation>, TrackerError> {
+    let tracker = state.get_tracker(&key, query.detail)?;
+    Ok(fields.select(tracker))
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateQuery {
+    #[serde(default)]
+    external: bool,
 }
 
 async fn create(
     Path(key): Path<String>,
+    Query(query): Query<CreateQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(cache): State<Arc<IssueCache>>,
     State(jira): State<Arc<JiraApi>>,
+    State(projects): State<Arc<ProjectCache>>,
     State(state): State<Arc<AppData>>,
 ) -> Result<Json<TrackerInformation>, TrackerError> {
-    let issue = jira
-        .get_issue_info(&key)
-        .await
-        .map_err(|_| TrackerError::NotFoundError)?;
-    state.create_tracker(&key, &issue.id)?;
-    let tracker = state.start(&key)?;
+    let key = config.resolve_alias(&key);
+    create_paused(&cache, &jira, &projects, &state, key, query.external).await?;
+    let tracker = state.start(key)?;
     Ok(Json(tracker))
 }
 
+/// Creates the tracker without starting it, looking up the issue unless it is external
+async fn create_paused(
+    cache: &IssueCache,
+    jira: &JiraApi,
+    projects: &ProjectCache,
+    state: &AppData,
+    key: &str,
+    external: bool,
+) -> Result<TrackerInformation, TrackerError> {
+    if external {
+        return state.create_external_tracker(key);
+    }
+    let issue = lookup_issue(cache, jira, projects, key).await?;
+    state.create_tracker(key, &issue.id, Some((&issue).into()))
+}
+
+async fn lookup_issue(
+    cache: &IssueCache,
+    jira: &JiraApi,
+    projects: &ProjectCache,
+    key: &str,
+) -> Result<JiraIssue, TrackerError> {
+    // e.g. keys of external trackers, which belong to no project and are never looked up
+    let Some((project, _)) = key.split_once('-') else {
+        return Err(TrackerError::KeyFormatError);
+    };
+    if cache.get(key).is_none() && !projects.contains_project(jira, project).await {
+        return Err(TrackerError::UnknownProjectError(project.to_string()));
+    }
+    cache
+        .get_or_fetch(jira, key)
+        .await
+        .map_err(TrackerError::issue_lookup)
+}
+
+#[derive(Debug, Serialize)]
+struct BulkCreated {
+    key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tracker: Option<TrackerInformation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Creates paused trackers for many issues at once, e.g. for the sprint, like `POST /trackers/:key`
+/// for each key. Failing keys do not prevent the others from being created.
+async fn create_many(
+    Query(query): Query<CreateQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(cache): State<Arc<IssueCache>>,
+    State(jira): State<Arc<JiraApi>>,
+    State(projects): State<Arc<ProjectCache>>,
+    State(state): State<Arc<AppData>>,
+    Json(keys): Json<Vec<String>>,
+) -> Json<Vec<BulkCreated>> {
+    let keys: Vec<_> = keys.iter().map(|key| config.resolve_alias(key)).collect();
+    let mut created = Vec::with_capacity(keys.len());
+    for chunk in keys.chunks(CONCURRENT_LOOKUPS) {
+        let results = join_all(
+            chunk
+                .iter()
+                .map(|key| create_paused(&cache, &jira, &projects, &state, key, query.external)),
+        )
+        .await;
+        created.extend(chunk.iter().zip(results).map(|(key, result)| match result {
+            Ok(tracker) => BulkCreated {
+                key: key.to_string(),
+                tracker: Some(tracker),
+                error: None,
+            },
+            Err(e) => BulkCreated {
+                key: key.to_string(),
+                tracker: None,
+                error: Some(commands::describe_error(key, e)),
+            },
+        }));
+    }
+    Json(created)
+}
+
+#[derive(Debug, Deserialize)]
+struct LinkBody {
+    key: String,
+}
+
+async fn link(
+    Path(key): Path<String>,
+    State(config): State<Arc<AppConfig>>,
+    State(cache): State<Arc<IssueCache>>,
+    State(jira): State<Arc<JiraApi>>,
+    State(state): State<Arc<AppData>>,
+    Json(body): Json<LinkBody>,
+) -> Result<Json<TrackerInformation>, TrackerError> {
+    let new_key = config.resolve_alias(&body.key);
+    let issue = cache
+        .get_or_fetch(&jira, new_key)
+        .await
+        .map_err(TrackerError::issue_lookup)?;
+    state
+        .link(&key, new_key, &issue.id, Some((&issue).into()))
+        .map(Json)
+}
+
+/// Marks the tracker as paired with a colleague, `null` removes the pairing
+async fn pair(
+    Path(key): Path<String>,
+    State(state): State<Arc<AppData>>,
+    Json(body): Json<Pair>,
+) -> Result<Json<TrackerInformation>, TrackerError> {
+    state.set_paired_with(&key, body.account_id).map(Json)
+}
+
 async fn start(
     Path(key): Path<String>,
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Result<Json<TrackerInformation>, TrackerError> {
+    state.start(config.resolve_alias(&key)).map(Json)
+}
+
+async fn toggle(
+    Path(key): Path<String>,
+    State(config): State<Arc<AppConfig>>,
     State(state): State<Arc<AppData>>,
 ) -> Result<Json<TrackerInformation>, TrackerError> {
-    state.start(&key).map(Json)
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(untagged, deny_unknown_fields)]
-enum AdjustTrackerBody {
-    SetDescription {
-        description: Option<String>,
-    },
-    PositiveDuration {
-        #[serde(
-            rename = "plus",
-            alias = "add",
-            alias = "increase",
-            with = "humantime_serde"
-        )]
-        duration: Duration,
-        #[serde(alias = "from")]
-        using: Option<String>,
-    },
-    NegativeDuration {
-        #[serde(
-            rename = "minus",
-            alias = "sub",
-            alias = "subtract",
-            alias = "decrease",
-            with = "humantime_serde"
-        )]
-        duration: Duration,
-        #[serde(alias = "to")]
-        using: Option<String>,
-    },
+    state.toggle(config.resolve_alias(&key)).map(Json)
 }
 
 async fn adjust(
     Path(key): Path<String>,
     State(state): State<Arc<AppData>>,
-    Json(body): Json<AdjustTrackerBody>,
+    Json(body): Json<AdjustTracker>,
 ) -> Result<Json<TrackerInformation>, TrackerError> {
-    let tracker = match body {
-        AdjustTrackerBody::SetDescription { description } => {
-            state.set_description(&key, description)?
-        }
-        AdjustTrackerBody::PositiveDuration { duration, using } => {
-            if let Some(other_key) = using {
-                state.adjust_negative_duration(&other_key, duration)?;
-            }
-            state.adjust_positive_duration(&key, duration)?
-        }
-        AdjustTrackerBody::NegativeDuration { duration, using } => {
-            let tracker = state.adjust_negative_duration(&key, duration)?;
-            if let Some(other_key) = using {
-                state.adjust_positive_duration(&other_key, duration)?;
-            }
-            tracker
-        }
-    };
-    Ok(Json(tracker))
+    state.adjust(&key, body).map(Json)
+}
+
+/// Adjusts several trackers like `PUT /trackers/:key` at once, all or none of them
+async fn adjust_all(
+    State(state): State<Arc<AppData>>,
+    Json(body): Json<IndexMap<String, AdjustTracker>>,
+) -> Result<Json<Vec<TrackerInformation>>, TrackerError> {
+    state.adjust_all(body).map(Json)
+}
+
+async fn patch(
+    Path(key): Path<String>,
+    State(state): State<Arc<AppData>>,
+    Json(body): Json<PatchTracker>,
+) -> Result<Json<TrackerInformation>, TrackerError> {
+    state.patch_fields(&key, body.fields).map(Json)
 }
 
 async fn delete(
@@ -111,58 +373,1321 @@ async fn delete(
     state.remove(&key).map(|_| StatusCode::NO_CONTENT)
 }
 
-async fn clear(State(state): State<Arc<AppData>>) -> StatusCode {
+async fn tracker_sessions(
+    Path(key): Path<String>,
+    State(state): State<Arc<AppData>>,
+) -> Result<Json<Vec<TrackerSession>>, TrackerError> {
+    state.tracker_sessions(&key).map(Json)
+}
+
+#[derive(Debug, Deserialize)]
+struct SplitBody {
+    /// Where to split, e.g. `2025-02-14T12:30:00+01:00`
+    at: Option<DateTime<Local>>,
+    /// Where to split, as time after the start of the session
+    #[serde(default, with = "humantime_serde")]
+    after: Option<Duration>,
+    /// Tracker the second part is moved to
+    to: Option<String>,
+}
+
+/// Splits a finished session of `GET /trackers/:key/sessions` in two
+async fn split_session(
+    Path((key, id)): Path<(String, usize)>,
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+    Json(body): Json<SplitBody>,
+) -> Result<Json<Vec<TrackerInformation>>, TrackerError> {
+    let at = match (body.at, body.after) {
+        (Some(at), None) => at,
+        (None, Some(after)) => {
+            let sessions = state.tracker_sessions(&key)?;
+            let start = sessions.get(id).ok_or(TrackerError::NotFoundError)?.start();
+            chrono::Duration::from_std(after)
+                .ok()
+                .and_then(|after| start.checked_add_signed(after))
+                .ok_or(TrackerError::DurationAdjustmentError)?
+        }
+        _ => return Err(TrackerError::DurationAdjustmentError),
+    };
+    let to = body.to.as_deref().map(|to| config.resolve_alias(to));
+    state.split_session(&key, id, at, to).map(Json)
+}
+
+/// Restores a deleted tracker within `PURGE_DELAY`
+async fn restore(
+    Path(key): Path<String>,
+    State(state): State<Arc<AppData>>,
+) -> Result<Json<TrackerInformation>, TrackerError> {
+    state.undelete(&key).map(Json)
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfirmQuery {
+    /// Today's date like `2025-02-14`, required with `CONFIRM_DESTRUCTIVE`
+    confirm: Option<String>,
+}
+
+/// Rejection of a destructive request that was not confirmed with today's date
+fn unconfirmed(config: &AppConfig, confirm: Option<&str>) -> Option<Response> {
+    let today = clock::local_now()
+        .date_naive()
+        .format("%Y-%m-%d")
+        .to_string();
+    if !config.confirm_destructive || confirm == Some(today.as_str()) {
+        return None;
+    }
+    let message = format!("confirm with ?confirm={}", today);
+    Some((StatusCode::PRECONDITION_REQUIRED, message).into_response())
+}
+
+async fn clear(
+    Query(query): Query<ConfirmQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Response {
+    if let Some(rejection) = unconfirmed(&config, query.confirm.as_deref()) {
+        return rejection;
+    }
     state.remove_all();
-    StatusCode::NO_CONTENT
+    StatusCode::NO_CONTENT.into_response()
 }
 
 async fn current(
+    Query(fields): Query<FieldsQuery>,
+    State(state): State<Arc<AppData>>,
+) -> Result<Selected<TrackerInformation>, TrackerError> {
+    Ok(fields.select(state.current()?))
+}
+
+async fn resume_last(
     State(state): State<Arc<AppData>>,
 ) -> Result<Json<TrackerInformation>, TrackerError> {
-    state.current().map(Json)
+    state.resume_last().map(Json)
+}
+
+async fn start_unknown(
+    State(state): State<Arc<AppData>>,
+) -> Result<Json<TrackerInformation>, TrackerError> {
+    state.start_unknown().map(Json)
+}
+
+/// Maps tracker keys to the duration moved onto them, e.g. `{"ABC-1": "30m"}`
+#[derive(Debug, Deserialize)]
+struct TriageBody(#[serde(with = "humantime_map")] Vec<(String, Duration)>);
+
+mod humantime_map {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer};
+
+    #[derive(Deserialize)]
+    struct HumanDuration(#[serde(with = "humantime_serde")] Duration);
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<(String, Duration)>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let map = BTreeMap::<String, HumanDuration>::deserialize(deserializer)?;
+        Ok(map
+            .into_iter()
+            .map(|(k, HumanDuration(d))| (k, d))
+            .collect())
+    }
+}
+
+async fn triage(
+    State(state): State<Arc<AppData>>,
+    Json(TriageBody(assignments)): Json<TriageBody>,
+) -> Result<Json<Vec<TrackerInformation>>, TrackerError> {
+    state.triage(&assignments).map(Json)
 }
 
 async fn pause(State(state): State<Arc<AppData>>) {
     state.pause()
 }
 
+async fn suspend(State(state): State<Arc<AppData>>) -> StatusCode {
+    state.suspend();
+    StatusCode::NO_CONTENT
+}
+
+async fn resume(
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Result<Json<TrackerInformation>, StatusCode> {
+    state
+        .resume(config.resume_policy, config.resume_within)
+        .map(Json)
+        .ok_or(StatusCode::NO_CONTENT)
+}
+
+async fn undo(
+    State(state): State<Arc<AppData>>,
+) -> Result<Json<Vec<TrackerInformation>>, StatusCode> {
+    state.undo().map(Json).ok_or(StatusCode::CONFLICT)
+}
+
+async fn redo(
+    State(state): State<Arc<AppData>>,
+) -> Result<Json<Vec<TrackerInformation>>, StatusCode> {
+    state.redo().map(Json).ok_or(StatusCode::CONFLICT)
+}
+
+/// Slash command of Mattermost, authenticated by its token
+async fn mattermost_command(
+    rejection: Rejection,
+    State(config): State<Arc<AppConfig>>,
+    State(cache): State<Arc<IssueCache>>,
+    State(jira): State<Arc<JiraApi>>,
+    State(state): State<Arc<AppData>>,
+    Form(command): Form<MattermostCommand>,
+) -> Result<Json<MattermostResponse>, StatusCode> {
+    let token = config
+        .mattermost_token
+        .as_ref()
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if !command.verify(token) {
+        return Err(rejection.unauthorized());
+    }
+    let context = CommandContext {
+        data: &state,
+        jira: &jira,
+        cache: &cache,
+        aliases: &config.aliases,
+        display: config.display_rounding(),
+    };
+    let reply = context.apply(&command.text).await;
+    Ok(Json(MattermostResponse::ephemeral(reply)))
+}
+
+/// Outgoing webhook of Teams, authenticated by the HMAC signature of the body
+async fn teams_command(
+    rejection: Rejection,
+    headers: HeaderMap,
+    State(config): State<Arc<AppConfig>>,
+    State(cache): State<Arc<IssueCache>>,
+    State(jira): State<Arc<JiraApi>>,
+    State(state): State<Arc<AppData>>,
+    body: Bytes,
+) -> Result<Json<TeamsResponse>, StatusCode> {
+    let secret = config.teams_secret.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    let authorization = headers.get(AUTHORIZATION).and_then(|h| h.to_str().ok());
+    if !chatops::verify_teams_signature(secret, authorization, &body) {
+        return Err(rejection.unauthorized());
+    }
+    let message: TeamsMessage =
+        serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let context = CommandContext {
+        data: &state,
+        jira: &jira,
+        cache: &cache,
+        aliases: &config.aliases,
+        display: config.display_rounding(),
+    };
+    let reply = context.apply(&message.command()).await;
+    Ok(Json(TeamsResponse::message(reply)))
+}
+
 #[derive(Debug, Serialize)]
-struct SumResponse {
-    #[serde(with = "humantime_serde")]
-    duration: Duration,
+struct JiraWebhookOutcome {
+    /// Missing if the event did not pick up an issue
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trigger: Option<Trigger>,
+    /// Missing if there was a tracker for the issue already
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created: Option<TrackerInformation>,
+}
+
+/// Webhook of Jira, authenticated by the HMAC signature of the body
+async fn jira_webhook(
+    rejection: Rejection,
+    headers: HeaderMap,
+    State(config): State<Arc<AppConfig>>,
+    State(tempo): State<Arc<TempoApi>>,
+    State(state): State<Arc<AppData>>,
+    body: Bytes,
+) -> Result<Json<JiraWebhookOutcome>, StatusCode> {
+    let secret = config
+        .jira_webhook_secret
+        .as_ref()
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let signature = headers.get(HUB_SIGNATURE).and_then(|h| h.to_str().ok());
+    if !jira_webhook::verify_signature(secret, signature, &body) {
+        return Err(rejection.unauthorized());
+    }
+    let event: JiraWebhookEvent =
+        serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let Some((issue, trigger)) = event.trigger(tempo.account_id()) else {
+        return Ok(Json(JiraWebhookOutcome {
+            trigger: None,
+            created: None,
+        }));
+    };
+    let created = state
+        .create_tracker(&issue.key, &issue.id, Some(issue.into()))
+        .ok();
+    if created.is_some() {
+        tracing::info!("created tracker {} for issue picked up in Jira", issue.key);
+    }
+    Ok(Json(JiraWebhookOutcome {
+        trigger: Some(trigger),
+        created,
+    }))
+}
+
+async fn trash(State(state): State<Arc<AppData>>) -> Json<Vec<TrashedTracker>> {
+    Json(state.trash())
+}
+
+async fn sum(State(state): State<Arc<AppData>>) -> Json<Sum> {
+    Json(state.sum())
+}
+
+async fn day_totals(
+    Path(date): Path<NaiveDate>,
+    State(config): State<Arc<AppConfig>>,
+    State(tempo): State<Arc<TempoApi>>,
+    State(state): State<Arc<AppData>>,
+) -> Json<DayTotals> {
+    let schedule = Schedule::fetch(&tempo, date, date, config.working_hours).await;
+    Json(
+        state
+            .day_totals(date)
+            .with_required(schedule.required(date)),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct DeltaQuery {
+    /// e.g. `2025-02-14T12:30:00+01:00`
+    since: DateTime<Local>,
+}
+
+/// Time tracked since a moment, e.g. the last break
+async fn sum_delta(
+    Query(query): Query<DeltaQuery>,
+    State(state): State<Arc<AppData>>,
+) -> Json<Sum> {
+    Json(state.sum_since(query.since.into()))
+}
+
+#[derive(Debug, Deserialize)]
+struct ReportQuery {
+    #[serde(default)]
+    format: ReportFormat,
+    filter: Option<String>,
+}
+
+impl ReportQuery {
+    /// Trackers matching the filter, with full details as the filter may refer to them
+    fn trackers(&self, state: &AppData) -> Result<Vec<TrackerInformation>, FilterError> {
+        let Some(filter) = &self.filter else {
+            return Ok(state.list_trackers(Detail::Summary));
+        };
+        let filter: Filter = filter.parse()?;
+        Ok(state
+            .list_trackers(Detail::Full)
+            .into_iter()
+            .filter(|tracker| filter.matches(tracker))
+            .collect())
+    }
+}
+
+async fn day_report(
+    Query(query): Query<ReportQuery>,
+    Query(fields): Query<FieldsQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Result<Response, FilterError> {
+    let trackers = query.trackers(&state)?;
+    let report = Report::day(config.locale, clock::local_now().date_naive(), &trackers)
+        .with_rounding(config.display_rounding());
+    Ok(render_report(&report, query.format, &fields))
+}
+
+async fn week_report(
+    Query(query): Query<ReportQuery>,
+    Query(fields): Query<FieldsQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Result<Response, FilterError> {
+    let trackers = query.trackers(&state)?;
+    let today = clock::local_now().date_naive();
+    let goals = state.goal_progress(week_start_of(today, config.week_start()));
+    let report = Report::week(config.locale, config.week_start(), today, &trackers)
+        .with_goals(goals)
+        .with_rounding(config.display_rounding());
+    Ok(render_report(&report, query.format, &fields))
+}
+
+/// The trackers of JSON reports are trimmed to `?fields=`
+fn render_report(report: &Report, format: ReportFormat, fields: &FieldsQuery) -> Response {
+    match format {
+        ReportFormat::Json => fields.select(report.trackers()).into_response(),
+        format => (
+            [(CONTENT_TYPE, format.content_type())],
+            report.render(format),
+        )
+            .into_response(),
+    }
+}
+
+/// Goals with their progress this week
+async fn goals(
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Json<Vec<GoalProgress>> {
+    let today = clock::local_now().date_naive();
+    Json(state.goal_progress(week_start_of(today, config.week_start())))
+}
+
+async fn set_goal(
+    Path(name): Path<String>,
+    State(state): State<Arc<AppData>>,
+    Json(body): Json<GoalBody>,
+) -> Result<Json<Goal>, GoalError> {
+    let goal = Goal::new(&name, body)?;
+    state.set_goal(goal.clone());
+    Ok(Json(goal))
+}
+
+async fn remove_goal(
+    Path(name): Path<String>,
+    State(state): State<Arc<AppData>>,
+) -> Result<Json<Goal>, StatusCode> {
+    state
+        .remove_goal(&name)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountQuery {
+    /// First day, the start of the current quarter if missing
+    from: Option<NaiveDate>,
+    /// Last day, today if missing
+    to: Option<NaiveDate>,
+    /// Only `csv` is supported, JSON otherwise
+    format: Option<ReportFormat>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArchiveQuery {
+    /// First day of submission
+    from: Option<NaiveDate>,
+    /// Last day of submission
+    to: Option<NaiveDate>,
+}
+
+async fn archive(
+    Query(query): Query<ArchiveQuery>,
+    State(state): State<Arc<AppData>>,
+) -> Json<Vec<ArchivedTracker>> {
+    Json(state.archived(query.from, query.to))
+}
+
+async fn import_toggl(
+    State(config): State<Arc<AppConfig>>,
+    State(cache): State<Arc<IssueCache>>,
+    State(jira): State<Arc<JiraApi>>,
+    State(state): State<Arc<AppData>>,
+    body: String,
+) -> Result<Json<TimeImport>, TimeImportError> {
+    let first = week_start_of(clock::local_now().date_naive(), config.week_start());
+    let entries = toggl::parse(&body, first)?;
+    time_import::import(&state, &cache, &jira, entries)
+        .await
+        .map(Json)
+}
+
+#[derive(Debug, Deserialize)]
+struct MigrateStorageQuery {
+    to: StorageBackend,
+}
+
+async fn migrate_storage(
+    Query(query): Query<MigrateStorageQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Result<Json<MigratedStorage>, MigrationError> {
+    state.migrate_storage(&config, query.to).map(Json)
+}
+
+async fn import_clockify(
+    State(cache): State<Arc<IssueCache>>,
+    State(jira): State<Arc<JiraApi>>,
+    State(state): State<Arc<AppData>>,
+    body: String,
+) -> Result<Json<TimeImport>, TimeImportError> {
+    let entries = clockify::parse(&body)?;
+    time_import::import(&state, &cache, &jira, entries)
+        .await
+        .map(Json)
+}
+
+async fn compact(State(state): State<Arc<AppData>>) -> Json<Compaction> {
+    Json(state.compact())
+}
+
+#[derive(Debug, Deserialize)]
+struct ReloadQuery {
+    /// Replaces the state in memory instead of merging
+    #[serde(default)]
+    force: bool,
+}
+
+/// Applies changes of the stored state made outside, returning the conflicting changes
+async fn reload(
+    Query(query): Query<ReloadQuery>,
+    State(state): State<Arc<AppData>>,
+) -> Result<Json<Vec<String>>, (StatusCode, String)> {
+    state
+        .reload(query.force)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+async fn export_state(State(state): State<Arc<AppData>>) -> Json<Value> {
+    Json(state.export())
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportQuery {
+    #[serde(default)]
+    mode: ImportMode,
+    /// Today's date, required with `CONFIRM_DESTRUCTIVE` to replace the state
+    confirm: Option<String>,
+}
+
+async fn import_state(
+    Query(query): Query<ImportQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+    Json(body): Json<Value>,
+) -> Response {
+    if let ImportMode::Replace = query.mode {
+        if let Some(rejection) = unconfirmed(&config, query.confirm.as_deref()) {
+            return rejection;
+        }
+    }
+    state.import(body, query.mode).map(Json).into_response()
 }
 
-async fn sum(State(state): State<Arc<AppData>>) -> Json<SumResponse> {
-    Json(SumResponse {
-        duration: state.sum(),
+async fn account_report(
+    Query(query): Query<AccountQuery>,
+    Query(fields): Query<FieldsQuery>,
+    State(state): State<Arc<AppData>>,
+    State(api): State<Arc<TempoApi>>,
+) -> Result<Response, UpstreamError> {
+    let today = clock::local_now().date_naive();
+    let from = query
+        .from
+        .unwrap_or_else(|| accounts::quarter_start_of(today));
+    let to = query.to.unwrap_or(today);
+    let report: Vec<AccountTime> = accounts::report(&api, &state.submissions(), from, to).await?;
+    Ok(match query.format {
+        Some(ReportFormat::Csv) => (
+            [(CONTENT_TYPE, ReportFormat::Csv.content_type())],
+            accounts::render_csv(&report),
+        )
+            .into_response(),
+        _ => fields.select(report).into_response(),
     })
 }
 
+async fn anomalies(
+    Query(fields): Query<FieldsQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Selected<Vec<Anomaly>> {
+    let trackers = state.list_trackers(Detail::Summary);
+    let sessions: Vec<_> = trackers
+        .iter()
+        .filter_map(|tracker| {
+            let sessions = state.tracker_sessions(&tracker.key).ok()?;
+            Some((tracker.key.clone(), sessions))
+        })
+        .collect();
+    fields.select(anomalies::detect(
+        &trackers,
+        &sessions,
+        config.anomaly_thresholds(),
+    ))
+}
+
+async fn jira_projects(
+    State(jira): State<Arc<JiraApi>>,
+    State(projects): State<Arc<ProjectCache>>,
+) -> Result<Json<Vec<JiraProject>>, UpstreamError> {
+    Ok(Json(projects.get(&jira).await?))
+}
+
+async fn plans_today(
+    State(state): State<Arc<AppData>>,
+    State(api): State<Arc<TempoApi>>,
+) -> Result<Json<PlanOverview>, UpstreamError> {
+    let today = clock::local_now().date_naive();
+    let plans = api.get_plans(today, today).await?;
+    let trackers = state.day_trackers(today);
+    let overview = PlanOverview::new(&plans, &trackers);
+    for key in overview.unplanned() {
+        tracing::warn!("tracking time on {} which is outside of today's plan", key);
+    }
+    Ok(Json(overview))
+}
+
+async fn sprint_capacity(
+    State(config): State<Arc<AppConfig>>,
+    State(jira): State<Arc<JiraApi>>,
+    State(tempo): State<Arc<TempoApi>>,
+    State(state): State<Arc<AppData>>,
+) -> Result<Json<SprintCapacity>, UpstreamError> {
+    let issues = jira.get_sprint_issues(&config.sprint_field).await?;
+    let today = clock::local_now().date_naive();
+    let trackers: Vec<_> = state
+        .list_trackers(Detail::Summary)
+        .into_iter()
+        .filter(|t| t.start_time.date_naive() == today)
+        .collect();
+    let end = sprint::active_sprint_end(&issues, &config.sprint_field).unwrap_or(today);
+    let schedule = Schedule::fetch(&tempo, today, end, config.working_hours).await;
+    let capacity = SprintCapacity::new(&issues, &config.sprint_field, &schedule, today, &trackers);
+    Ok(Json(capacity))
+}
+
+/// Checks the trackers against their freshly looked up Jira issues and against Tempo
+async fn review_trackers(
+    jira: &JiraApi,
+    tempo: &TempoApi,
+    state: &AppData,
+    display: DisplayRounding,
+) -> SubmitReview {
+    let trackers = state.list_trackers(Detail::Summary);
+    let mut issues = Vec::with_capacity(trackers.len());
+    for chunk in trackers.chunks(CONCURRENT_LOOKUPS) {
+        issues.extend(
+            join_all(chunk.iter().map(|tracker| async {
+                if tracker.external {
+                    None
+                } else {
+                    jira.get_issue_info(&tracker.key).await.ok()
+                }
+            }))
+            .await,
+        );
+    }
+    let tempo = review::check_tempo(tempo, &trackers).await;
+    let reviewed: Vec<_> = trackers.into_iter().zip(issues).collect();
+    SubmitReview::new(&reviewed, tempo, display)
+}
+
+async fn submit_review(
+    State(config): State<Arc<AppConfig>>,
+    State(jira): State<Arc<JiraApi>>,
+    State(state): State<Arc<AppData>>,
+    State(api): State<Arc<TempoApi>>,
+) -> Json<SubmitReview> {
+    Json(review_trackers(&jira, &api, &state, config.display_rounding()).await)
+}
+
+#[derive(Debug, Deserialize)]
+struct SubmitQuery {
+    on_behalf_of: Option<String>,
+    /// Only returns the worklogs that would be created
+    #[serde(default)]
+    dry_run: bool,
+    /// Today's date, required with `CONFIRM_DESTRUCTIVE` as submitting removes the trackers
+    confirm: Option<String>,
+}
+
+/// Fills in missing descriptions of Jira trackers, returning the keys of the changed trackers
+async fn generate_descriptions(
+    trackers: &mut [TrackerInformation],
+    feed: &CommitFeed,
+    jira: &JiraApi,
+    account_id: &str,
+) -> Vec<String> {
+    let mut generated = Vec::new();
+    for tracker in trackers
+        .iter_mut()
+        .filter(|t| !t.external && t.description.is_none())
+    {
+        tracker.description = descriptions::generate(feed, jira, account_id, &tracker.key).await;
+        if tracker.description.is_some() {
+            generated.push(tracker.key.clone());
+        }
+    }
+    generated
+}
+
+/// Trackers as worklogs per `SUBMISSION_GROUPING`, with generated descriptions and their keys
+async fn worklogs(
+    config: &AppConfig,
+    feed: &CommitFeed,
+    jira: &JiraApi,
+    state: &AppData,
+    api: &TempoApi,
+    on_behalf_of: Option<&str>,
+) -> (Vec<TrackerInformation>, Vec<String>) {
+    let mut trackers = state.worklogs(config.submission_grouping);
+    let generated = if config.auto_description {
+        let account_id = on_behalf_of.unwrap_or(api.account_id());
+        generate_descriptions(&mut trackers, feed, jira, account_id).await
+    } else {
+        Vec::new()
+    };
+    (trackers, generated)
+}
+
+/// Submits all submittable trackers, generating missing descriptions with `AUTO_DESCRIPTION`
+async fn submit_trackers(
+    config: &AppConfig,
+    feed: &CommitFeed,
+    jira: &JiraApi,
+    state: &AppData,
+    api: &TempoApi,
+    on_behalf_of: Option<&str>,
+) -> Result<Submission, UpstreamError> {
+    let (trackers, generated) = worklogs(config, feed, jira, state, api, on_behalf_of).await;
+    let skipped = api.skipped(&trackers);
+    let submitted: Vec<String> = trackers.iter().map(|t| t.key.clone()).collect();
+    let (mut receipts, mut errors) = (Vec::new(), Vec::new());
+    for (key, result) in api.submit_all(trackers, on_behalf_of).await {
+        match result {
+            Ok(mut receipt) => {
+                receipt.generated_description = generated.contains(&receipt.key);
+                receipts.push(receipt);
+            }
+            Err(error) => errors.push((key, error)),
+        }
+    }
+    // with no worklog created, nothing is archived and the trackers keep their time
+    if receipts.is_empty() && !errors.is_empty() {
+        return Err(errors.swap_remove(0).1);
+    }
+    // with some worklogs created, the submission tells which ones failed
+    let failed = errors
+        .iter()
+        .map(|(key, error)| FailedWorklog {
+            key: key.clone(),
+            error: error.to_string(),
+        })
+        .collect();
+    Ok(state.record_submission(&submitted, receipts, failed, skipped))
+}
+
+async fn submissions(State(state): State<Arc<AppData>>) -> Json<Vec<Submission>> {
+    Json(state.submissions())
+}
+
 async fn submit(
+    Query(query): Query<SubmitQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(feed): State<Arc<CommitFeed>>,
+    State(jira): State<Arc<JiraApi>>,
     State(state): State<Arc<AppData>>,
     State(api): State<Arc<TempoApi>>,
-) -> Result<(), LogError> {
-    api.submit_all(state.list_trackers()).await?;
-    state.remove_all();
-    Ok(())
+) -> Result<Response, UpstreamError> {
+    if query.on_behalf_of.is_some() && !config.allow_delegation {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    let on_behalf_of = query.on_behalf_of.as_deref();
+    if query.dry_run {
+        let (trackers, _) = worklogs(&config, &feed, &jira, &state, &api, on_behalf_of).await;
+        return Ok(Json(api.preview(trackers, on_behalf_of)).into_response());
+    }
+    if let Some(rejection) = unconfirmed(&config, query.confirm.as_deref()) {
+        return Ok(rejection);
+    }
+    let submission = submit_trackers(&config, &feed, &jira, &state, &api, on_behalf_of).await?;
+    Ok(Json(submission).into_response())
+}
+
+/// Prunes stale trackers, creates the recurring ones, warms the issue cache and reports time of
+/// previous days that was not submitted yet
+async fn open_day(
+    State(config): State<Arc<AppConfig>>,
+    State(cache): State<Arc<IssueCache>>,
+    State(jira): State<Arc<JiraApi>>,
+    State(state): State<Arc<AppData>>,
+) -> Json<OpenedDay> {
+    let today = clock::local_now().date_naive();
+    let pruned = match config.prune_policy {
+        PrunePolicy::None => Vec::new(),
+        PrunePolicy::Empty | PrunePolicy::Archive => state.prune_empty(today),
+    };
+    let archived = match config.prune_policy {
+        PrunePolicy::Archive => state.archive_external(today),
+        _ => Vec::new(),
+    };
+    let mut created = Vec::new();
+    for key in &config.recurring_trackers {
+        let key = config.resolve_alias(key);
+        if state.get_tracker(key, Detail::Summary).is_ok() {
+            continue;
+        }
+        match cache.get_or_fetch(&jira, key).await {
+            Ok(issue) => {
+                if state
+                    .create_tracker(key, &issue.id, Some((&issue).into()))
+                    .is_ok()
+                {
+                    created.push(key.to_string());
+                }
+            }
+            Err(e) => tracing::warn!("could not create recurring tracker {}: {}", key, e),
+        }
+    }
+    let prefetched = cache
+        .prefetch(&jira, &config.prefetch_keys(), config.prefetch_delay)
+        .await;
+    let unsubmitted = state
+        .list_trackers(Detail::Summary)
+        .into_iter()
+        .filter(|t| !t.external && !t.duration.is_zero() && t.start_time.date_naive() < today)
+        .collect();
+    Json(OpenedDay {
+        pruned,
+        archived,
+        created,
+        prefetched,
+        unsubmitted,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct CloseDayQuery {
+    /// Submits the trackers if the review found nothing blocking
+    #[serde(default)]
+    submit: bool,
+    /// Today's date, required with `CONFIRM_DESTRUCTIVE` when submitting
+    confirm: Option<String>,
+}
+
+/// Pauses and rounds the trackers, reviews and optionally submits them, and keeps a summary of the
+/// day for the standup
+async fn close_day(
+    Query(query): Query<CloseDayQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(feed): State<Arc<CommitFeed>>,
+    State(jira): State<Arc<JiraApi>>,
+    State(state): State<Arc<AppData>>,
+    State(api): State<Arc<TempoApi>>,
+) -> Result<Response, UpstreamError> {
+    if query.submit {
+        if let Some(rejection) = unconfirmed(&config, query.confirm.as_deref()) {
+            return Ok(rejection);
+        }
+    }
+    state.pause();
+    let rounded = match config.rounding_step {
+        Some(step) => state.round_durations(step, config.rounding_policy),
+        None => Vec::new(),
+    };
+    let trackers = state.list_trackers(Detail::Full);
+    let review = review_trackers(&jira, &api, &state, config.display_rounding()).await;
+    let submission = if query.submit && review.ready() {
+        Some(submit_trackers(&config, &feed, &jira, &state, &api, None).await?)
+    } else {
+        None
+    };
+    let summary = DaySummary::new(&trackers, submission.is_some());
+    state.record_day(summary.clone());
+    Ok(Json(ClosedDay {
+        review,
+        rounded,
+        submission,
+        standup: summary.standup(config.display_rounding()),
+        summary,
+    })
+    .into_response())
+}
+
+#[derive(Debug, Serialize)]
+struct Health {
+    status: &'static str,
+    version: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    update_available: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latest_release: Option<Release>,
+    /// Detected loops of reloading the state file, a hint at another writer
+    reload_loops: u64,
+    /// The state was corrupt on startup and recovered like this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state_recovered: Option<String>,
+    /// Failed authentications since the start, see `auth_guard.rs`
+    auth_failures: u64,
+    /// Jira and Tempo are faked, see `SANDBOX`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    sandbox: bool,
+}
+
+async fn health(
+    State(state): State<Arc<AppData>>,
+    State(config): State<Arc<AppConfig>>,
+    State(auth_guard): State<Arc<AuthGuard>>,
+    State(update_check): State<Arc<UpdateCheck>>,
+) -> Json<Health> {
+    let state_recovered = state.recovery().map(str::to_string);
+    Json(Health {
+        status: if state_recovered.is_some() {
+            "degraded"
+        } else {
+            "ok"
+        },
+        version: update_check.current_version(),
+        update_available: update_check.update_available(),
+        latest_release: update_check.latest(),
+        reload_loops: state.reload_loops(),
+        state_recovered,
+        auth_failures: auth_guard.failures(),
+        sandbox: config.sandbox,
+    })
+}
+
+/// Offered as download, to be attached to bug reports
+async fn diagnostics(
+    State(state): State<Arc<AppData>>,
+    State(config): State<Arc<AppConfig>>,
+    State(auth_guard): State<Arc<AuthGuard>>,
+    State(update_check): State<Arc<UpdateCheck>>,
+    State(scheduler): State<Arc<Scheduler>>,
+    State(lease): State<Arc<WriterLease>>,
+) -> Response {
+    let generated_at = clock::local_now();
+    let disposition = format!(
+        "attachment; filename=\"jira-tracker-diagnostics-{}.json\"",
+        generated_at.format("%Y%m%d-%H%M%S")
+    );
+    let bundle = Diagnostics {
+        generated_at,
+        versions: Versions::new(update_check.current_version()),
+        config: &config,
+        state: state.summary(),
+        storage: StorageHealth::new(&config, &state, lease.held(), lease.is_read_only()),
+        jobs: scheduler.list(),
+        auth_failures: auth_guard.failures(),
+        recent_errors: diagnostics::recent_errors(),
+    };
+    ([(CONTENT_DISPOSITION, disposition)], Json(bundle)).into_response()
+}
+
+/// Readiness to take requests, only of the instance holding the writer lease
+async fn ready(State(lease): State<Arc<WriterLease>>) -> StatusCode {
+    if lease.held() || lease.is_read_only() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+async fn team(State(team): State<Arc<Team>>) -> Json<Vec<TeamMember>> {
+    Json(team.members().await)
+}
+
+#[derive(Debug, Serialize)]
+struct HeartbeatResponse {
+    matched: usize,
+}
+
+async fn heartbeat_commits(
+    State(feed): State<Arc<CommitFeed>>,
+    Json(commits): Json<Vec<Commit>>,
+) -> Json<HeartbeatResponse> {
+    Json(HeartbeatResponse {
+        matched: feed.record(&commits),
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct ActivityResponse {
+    recorded: bool,
+}
+
+/// Attributes the activity to the running tracker, if activity is recorded at all
+async fn heartbeat_activity(
+    State(activity): State<Arc<ActivityLog>>,
+    State(state): State<Arc<AppData>>,
+    Json(body): Json<Activity>,
+) -> Json<ActivityResponse> {
+    let key = state.current().ok().map(|tracker| tracker.key);
+    Json(ActivityResponse {
+        recorded: activity.record(key, body),
+    })
+}
+
+async fn activity_report(
+    Query(fields): Query<FieldsQuery>,
+    State(activity): State<Arc<ActivityLog>>,
+) -> Result<Selected<Vec<IssueActivity>>, StatusCode> {
+    if !activity.enabled() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(fields.select(activity.report()))
+}
+
+#[derive(Debug, Deserialize)]
+struct JournalQuery {
+    /// ISO week like `2025-W07`, the current week if missing
+    week: Option<String>,
+}
+
+impl JournalQuery {
+    fn week(&self) -> Result<IsoWeek, JournalError> {
+        match &self.week {
+            Some(week) => journal::parse_week(week),
+            None => Ok(clock::local_now().date_naive().iso_week()),
+        }
+    }
+}
+
+async fn export_journal(
+    Query(query): Query<JournalQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Result<String, JournalError> {
+    let path = journal::export(&state, &config.journal_directory(), query.week()?)?;
+    Ok(path.display().to_string())
+}
+
+async fn import_journal(
+    Query(query): Query<JournalQuery>,
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Result<Json<Vec<String>>, JournalError> {
+    journal::import(&state, &config.journal_directory(), query.week()?).map(Json)
+}
+
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+    /// Interval of `tick` events, e.g. `5s`, none if not given
+    #[serde(default, with = "humantime_serde")]
+    tick: Option<Duration>,
+}
+
+/// Tracker events as server-sent events
+async fn tracker_events(
+    Query(query): Query<EventsQuery>,
+    State(state): State<Arc<AppData>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let changes =
+        events::stream(state.subscribe()).map(|event| Event::default().json_data(event).unwrap());
+    let ticks = futures::stream::iter(query.tick)
+        .flat_map(move |interval| events::ticks(state.clone(), interval))
+        .map(|tick| Event::default().json_data(tick).unwrap());
+    let stream = futures::stream::select(changes, ticks).map(Ok);
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn live_socket(
+    upgrade: WebSocketUpgrade,
+    headers: HeaderMap,
+    Query(query): Query<EventsQuery>,
+    State(state): State<Arc<AppData>>,
+    State(config): State<Arc<AppConfig>>,
+    State(lease): State<Arc<WriterLease>>,
+) -> Response {
+    if !same_origin(&headers) {
+        return (StatusCode::FORBIDDEN, "cross-origin WebSocket").into_response();
+    }
+    let tick = query.tick.unwrap_or(websocket::DEFAULT_TICK);
+    upgrade.on_upgrade(move |socket| websocket::serve(socket, state, config, lease, tick))
+}
+
+async fn audit_log(
+    Query(query): Query<AuditQuery>,
+    State(config): State<Arc<AppConfig>>,
+) -> Result<Json<Vec<AuditEntry>>, StatusCode> {
+    if !config.audit_log {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    audit::read(config.audit_file(), &query)
+        .map(Json)
+        .map_err(|e| {
+            tracing::warn!("could not read audit log: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+async fn jobs(State(scheduler): State<Arc<Scheduler>>) -> Json<Vec<JobStatus>> {
+    Json(scheduler.list())
+}
+
+/// Runs the job now, answers `409 Conflict` with its status if it is still running
+async fn run_job(
+    Path(id): Path<String>,
+    State(scheduler): State<Arc<Scheduler>>,
+) -> Result<Json<JobStatus>, Response> {
+    let status = scheduler
+        .run(&id)
+        .await
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?;
+    if status.running() {
+        return Err((StatusCode::CONFLICT, Json(status)).into_response());
+    }
+    Ok(Json(status))
+}
+
+async fn clients(State(clients): State<Arc<ClientRegistry>>) -> Json<Vec<ClientInfo>> {
+    Json(clients.list())
+}
+
+async fn html_trackers(
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Html<String> {
+    let trackers = state.list_trackers(Detail::Summary);
+    Html(html::trackers_page(
+        config.locale,
+        config.display_rounding(),
+        &trackers,
+        None,
+    ))
+}
+
+/// Whether the `Origin`, or else the `Referer`, of a browser request is this server, so that other
+/// web pages cannot open the socket or post the forms in the browser of the user. Requests without
+/// either, e.g. from text browsers and scripts, are allowed.
+fn same_origin(headers: &HeaderMap) -> bool {
+    let origin = headers.get(ORIGIN).or_else(|| headers.get(REFERER));
+    let Some(origin) = origin else {
+        return true;
+    };
+    let host = headers.get(HOST).and_then(|host| host.to_str().ok());
+    let authority = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.split_once("://"))
+        .map(|(_, rest)| rest.split('/').next().unwrap_or(rest));
+    host.is_some_and(|host| authority == Some(host))
+}
+
+/// Applies a form of `GET /html/trackers` and redirects back, or shows the list with the error
+async fn html_tracker_form(
+    headers: HeaderMap,
+    State(config): State<Arc<AppConfig>>,
+    State(cache): State<Arc<IssueCache>>,
+    State(jira): State<Arc<JiraApi>>,
+    State(state): State<Arc<AppData>>,
+    Form(form): Form<TrackerForm>,
+) -> Response {
+    if !same_origin(&headers) {
+        return (StatusCode::FORBIDDEN, "cross-origin form").into_response();
+    }
+    let mut key = config.resolve_alias(form.key.trim()).to_string();
+    let result = match form.action {
+        FormAction::Track => {
+            // typed issue keys, existing trackers are started and adjusted as listed
+            key = key.to_uppercase();
+            let context = CommandContext {
+                data: &state,
+                jira: &jira,
+                cache: &cache,
+                aliases: &config.aliases,
+                display: config.display_rounding(),
+            };
+            context.track(&key, None, None).await.map(drop)
+        }
+        FormAction::Start => state.start(&key).map(drop),
+        FormAction::Pause => {
+            state.pause();
+            Ok(())
+        }
+        FormAction::Adjust => match form.adjustment() {
+            Some((duration, false)) => state.adjust_positive_duration(&key, duration).map(drop),
+            Some((duration, true)) => state.adjust_negative_duration(&key, duration).map(drop),
+            None => Err(TrackerError::DurationAdjustmentError),
+        },
+    };
+    match result {
+        Ok(()) => Redirect::to("trackers").into_response(),
+        Err(error) => {
+            let error = commands::describe_error(&key, error);
+            let trackers = state.list_trackers(Detail::Summary);
+            let page = html::trackers_page(
+                config.locale,
+                config.display_rounding(),
+                &trackers,
+                Some(&error),
+            );
+            (StatusCode::BAD_REQUEST, Html(page)).into_response()
+        }
+    }
+}
+
+async fn html_sum(
+    State(config): State<Arc<AppConfig>>,
+    State(state): State<Arc<AppData>>,
+) -> Html<String> {
+    Html(html::sum_page(
+        config.locale,
+        config.display_rounding(),
+        &state.sum(),
+    ))
+}
+
+async fn ui_index(State(config): State<Arc<AppConfig>>) -> Response {
+    match ui::index(config.display_rounding()) {
+        Some(html) => ([(CACHE_CONTROL, "no-cache")], Html(html)).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn ui_version() -> Json<UiVersion> {
+    Json(UiVersion {
+        version: ui::version(),
+        live: ui::LIVE,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct AssetQuery {
+    /// Version of the assets the page references them with
+    v: Option<String>,
+}
+
+async fn ui_asset(Path(path): Path<String>, Query(query): Query<AssetQuery>) -> Response {
+    let Some(data) = ui::asset(&path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let mut response = (
+        [
+            (CONTENT_TYPE, ui::content_type(&path)),
+            (
+                CACHE_CONTROL,
+                ui::cache_control(query.v.is_some()).to_string(),
+            ),
+        ],
+        data,
+    )
+        .into_response();
+    if path == "sw.js" {
+        // lets the worker control `/ui` itself, not only the paths below
+        response
+            .headers_mut()
+            .insert("Service-Worker-Allowed", HeaderValue::from_static("/ui"));
+    }
+    response
+}
+
+#[cfg(feature = "dev")]
+#[derive(Debug, Deserialize)]
+struct AdvanceClockQuery {
+    #[serde(with = "humantime_serde")]
+    by: Duration,
+}
+
+#[cfg(feature = "dev")]
+async fn advance_clock(
+    Query(query): Query<AdvanceClockQuery>,
+) -> Json<chrono::DateTime<chrono::Local>> {
+    clock::advance(query.by);
+    Json(clock::local_now())
+}
+
+/// Routes authenticated by their own token or signature, guarded by `auth_guard.rs`
+pub fn token_routes() -> Router<AppState> {
+    Router::new()
+        .route("/chat/mattermost", post(mattermost_command))
+        .route("/chat/teams", post(teams_command))
+        .route("/webhooks/jira", post(jira_webhook))
 }
 
 pub fn router() -> Router<AppState> {
     let trackers_routes = Router::new()
-        .route("/", get(list).delete(clear))
+        .route(
+            "/",
+            get(list).post(create_many).put(adjust_all).delete(clear),
+        )
         .route(
             "/:key",
-            get(get_tracker).post(create).put(adjust).delete(delete),
+            get(get_tracker)
+                .post(create)
+                .put(adjust)
+                .patch(patch)
+                .delete(delete),
         )
-        .route("/:key/start", post(start));
+        .route("/:key/start", post(start))
+        .route("/:key/toggle", post(toggle))
+        .route("/:key/link", post(link))
+        .route("/:key/pair", post(pair))
+        .route("/:key/sessions", get(tracker_sessions))
+        .route("/:key/sessions/:id/split", post(split_session))
+        .route("/:key/restore", post(restore))
+        .route("/:key/undelete", post(restore));
 
     let tracker_routes = Router::new()
         .route("/", get(current))
-        .route("/pause", post(pause));
+        .route("/pause", post(pause))
+        .route("/resume-last", post(resume_last))
+        .route("/unknown", post(start_unknown))
+        .route("/unknown/triage", post(triage));
 
-    Router::new()
+    let report_routes = Router::new()
+        .route("/day", get(day_report))
+        .route("/week", get(week_report))
+        .route("/anomalies", get(anomalies))
+        .route("/activity", get(activity_report))
+        .route("/accounts", get(account_report));
+
+    let system_routes = Router::new()
+        .route("/suspend", post(suspend))
+        .route("/resume", post(resume));
+
+    let router = Router::new()
         .nest("/trackers", trackers_routes)
         .nest("/tracker", tracker_routes)
+        .nest("/report", report_routes)
+        .nest("/system", system_routes)
+        .route("/plans/today", get(plans_today))
+        .route("/sprint/capacity", get(sprint_capacity))
+        .route("/jira/projects", get(jira_projects))
         .route("/sum", get(sum))
+        .route("/sum/delta", get(sum_delta))
         .route("/submit", post(submit))
+        .route("/submit/review", get(submit_review))
+        .route("/submissions", get(submissions))
+        .route("/day/open", post(open_day))
+        .route("/day/close", post(close_day))
+        .route("/days/:date", get(day_totals))
+        .route("/goals", get(goals))
+        .route("/goals/:name", put(set_goal).delete(remove_goal))
+        .route("/clients", get(clients))
+        .route("/html/trackers", get(html_trackers).post(html_tracker_form))
+        .route("/html/sum", get(html_sum))
+        .route("/ui", get(ui_index))
+        .route("/ui/version", get(ui_version))
+        .route("/ui/*path", get(ui_asset))
+        .route("/health", get(health))
+        .route("/ready", get(ready))
+        .route("/diagnostics", get(diagnostics))
+        .route("/team", get(team))
+        .route("/heartbeat/commits", post(heartbeat_commits))
+        .route("/heartbeat/activity", post(heartbeat_activity))
+        .route("/journal/export", post(export_journal))
+        .route("/journal/import", post(import_journal))
+        .route("/trash", get(trash))
+        .route("/undo", post(undo))
+        .route("/redo", post(redo))
+        .route("/archive", get(archive))
+        .route("/export", get(export_state))
+        .route("/reload", post(reload))
+        .route("/admin/compact", post(compact))
+        .route("/maintenance/migrate-storage", post(migrate_storage))
+        .route("/import", post(import_state))
+        .route("/import/toggl", post(import_toggl))
+        .route("/import/clockify", post(import_clockify))
+        .route("/audit", get(audit_log))
+        .route("/events", get(tracker_events))
+        .route("/ws", get(live_socket))
+        .route("/jobs", get(jobs))
+        .route("/jobs/:id/run-now", post(run_job));
+
+    #[cfg(feature = "dev")]
+    let router = router.route("/debug/advance-clock", post(advance_clock));
+
+    router
 }
@@ -0,0 +1,120 @@
+//! `GET /ws` for live dashboards: the current tracker on connect and after each command, the
+//! changes of `GET /events` and `tick` events, and `start`/`pause` commands from the client, e.g.
+//! `{"command": "start", "key": "ABC-1"}`.
+
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use domain::TrackerInformation;
+
+use crate::app_data::AppData;
+use crate::commands;
+use crate::config::AppConfig;
+use crate::events;
+use crate::freeze;
+use crate::lease::WriterLease;
+
+/// Interval of `tick` events unless given with `?tick=`
+pub const DEFAULT_TICK: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+enum ClientCommand {
+    Start { key: String },
+    Pause,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Reply {
+    /// `None` while no tracker is running
+    Current {
+        tracker: Option<Box<TrackerInformation>>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl Reply {
+    fn current(data: &AppData) -> Self {
+        Reply::Current {
+            tracker: data.current().ok().map(Box::new),
+        }
+    }
+
+    fn text(&self) -> Message {
+        Message::Text(serde_json::to_string(self).unwrap())
+    }
+}
+
+/// Commands are rejected while the freeze window is open or another instance is the writer, like
+/// mutating requests
+fn apply(data: &AppData, config: &AppConfig, lease: &WriterLease, text: &str) -> Reply {
+    let command = match serde_json::from_str(text) {
+        Ok(command) => command,
+        Err(e) => {
+            return Reply::Error {
+                message: format!("invalid command: {}", e),
+            }
+        }
+    };
+    if let Some((_, refusal)) = lease.refusal() {
+        return Reply::Error {
+            message: refusal.to_string(),
+        };
+    }
+    if let Some(unlock_at) = freeze::frozen_until(config) {
+        return Reply::Error {
+            message: format!("frozen until {}", unlock_at.format("%H:%M")),
+        };
+    }
+    match command {
+        ClientCommand::Start { key } => {
+            let key = config.resolve_alias(&key);
+            if let Err(e) = data.start(key) {
+                return Reply::Error {
+                    message: commands::describe_error(key, e),
+                };
+            }
+        }
+        ClientCommand::Pause => data.pause(),
+    }
+    Reply::current(data)
+}
+
+/// Runs until the client disconnects
+pub async fn serve(
+    socket: WebSocket,
+    data: Arc<AppData>,
+    config: Arc<AppConfig>,
+    lease: Arc<WriterLease>,
+    tick: Duration,
+) {
+    let (mut sender, mut receiver) = socket.split();
+    let changes = events::stream(data.subscribe()).map(|event| serde_json::to_string(&event));
+    let ticks = events::ticks(data.clone(), tick).map(|tick| serde_json::to_string(&tick));
+    let mut outgoing = pin!(futures::stream::select(changes, ticks));
+    if sender.send(Reply::current(&data).text()).await.is_err() {
+        return;
+    }
+    loop {
+        let message = tokio::select! {
+            Some(json) = outgoing.next() => Message::Text(json.unwrap()),
+            received = receiver.next() => match received {
+                Some(Ok(Message::Text(text))) => apply(&data, &config, &lease, &text).text(),
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                // pings are answered by the socket itself
+                Some(Ok(_)) => continue,
+            },
+        };
+        if sender.send(message).await.is_err() {
+            break;
+        }
+    }
+}
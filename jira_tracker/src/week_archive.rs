@@ -0,0 +1,133 @@
+//! CSV backup of the billing-relevant data of each ISO week with `WEEK_ARCHIVE`: the sessions
+//! started in it and the worklogs submitted in it, as `2025-W07-sessions.csv` and
+//! `2025-W07-worklogs.csv` in a directory or a WebDAV collection. Profiles, which share the
+//! target, prefix the names with theirs, e.g. `sideproject-2025-W07-sessions.csv`.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Days, Local};
+use reqwest::Url;
+use serde::Serialize;
+
+use crate::app_data::AppData;
+use crate::clock;
+use crate::files;
+use crate::journal::week_name;
+use crate::scheduler::Scheduler;
+
+#[derive(Debug, Serialize)]
+pub struct SessionRow {
+    pub key: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub seconds: u64,
+    /// Empty while the tracker is not submitted yet
+    pub submitted_at: Option<DateTime<Local>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorklogRow {
+    pub submitted_at: DateTime<Local>,
+    pub key: String,
+    pub seconds: u64,
+    pub tempo_worklog_id: u64,
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum WeekArchive {
+    Directory(PathBuf),
+    /// Collection the files are put into, credentials are taken from the URL
+    WebDav(Url),
+}
+
+impl WeekArchive {
+    /// `http(s)://` URLs are WebDAV collections, anything else is a directory
+    pub fn parse(target: &str) -> Result<Self, String> {
+        if !target.starts_with("http://") && !target.starts_with("https://") {
+            let path = shellexpand::tilde(target).into_owned();
+            return Ok(WeekArchive::Directory(path.into()));
+        }
+        let mut url = Url::parse(target).map_err(|e| format!("invalid WEEK_ARCHIVE: {}", e))?;
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+        Ok(WeekArchive::WebDav(url))
+    }
+
+    async fn write(
+        &self,
+        client: &reqwest::Client,
+        name: &str,
+        bytes: Vec<u8>,
+    ) -> Result<(), String> {
+        match self {
+            WeekArchive::Directory(directory) => files::write_bytes(directory.join(name), &bytes)
+                .map_err(|e| format!("could not write {}: {:?}", name, e)),
+            WeekArchive::WebDav(collection) => {
+                let mut url = collection.join(name).map_err(|e| e.to_string())?;
+                let username = url.username().to_string();
+                let password = url.password().map(str::to_string);
+                let _ = url.set_username("");
+                let _ = url.set_password(None);
+                let mut request = client.put(url).body(bytes);
+                if !username.is_empty() {
+                    request = request.basic_auth(username, password);
+                }
+                request
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map(|_| ())
+                    .map_err(|e| format!("could not upload {}: {}", name, e))
+            }
+        }
+    }
+}
+
+fn to_csv<T: Serialize>(rows: &[T]) -> Result<Vec<u8>, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.serialize(row).map_err(|e| e.to_string())?;
+    }
+    writer.into_inner().map_err(|e| e.to_string())
+}
+
+/// Writes the archive of the current and the previous week every `interval`, so the archive of a
+/// week is complete once the next one has passed. The files of a profile start with `profile`.
+pub fn schedule_week_archive(
+    scheduler: &Scheduler,
+    data: Arc<AppData>,
+    archive: WeekArchive,
+    profile: Option<String>,
+    interval: Duration,
+) {
+    let client = reqwest::Client::new();
+    scheduler.add("week_archive", interval, move || {
+        let (data, archive, client) = (data.clone(), archive.clone(), client.clone());
+        let profile = profile.clone();
+        async move {
+            let today = clock::local_now().date_naive();
+            let weeks = [today - Days::new(7), today].map(|date| date.iso_week());
+            for week in weeks {
+                let (sessions, worklogs) = data.week_archive(week);
+                let name = match &profile {
+                    Some(profile) => format!("{}-{}", profile, week_name(week)),
+                    None => week_name(week),
+                };
+                let sessions = to_csv(&sessions)?;
+                archive
+                    .write(&client, &format!("{}-sessions.csv", name), sessions)
+                    .await?;
+                let worklogs = to_csv(&worklogs)?;
+                archive
+                    .write(&client, &format!("{}-worklogs.csv", name), worklogs)
+                    .await?;
+            }
+            let names: Vec<_> = weeks.into_iter().map(week_name).collect();
+            Ok(format!("archived {}", names.join(", ")))
+        }
+    });
+}
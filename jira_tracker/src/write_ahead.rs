@@ -0,0 +1,146 @@
+//! Write-ahead log of changes in `state.wal` next to the state file, see `STATE_JOURNAL`. Each
+//! change is appended and synced before it is answered, as the state itself is only written in the
+//! background. Entries are dropped once a save covers them, those left on startup are replayed.
+
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::files;
+use crate::files::{FileError, FileKey};
+
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    /// Version of the state after the change, see [`WriteAheadLog::saved`]
+    seq: u64,
+    change: T,
+}
+
+/// Lines not covered by a save yet with their versions, and the file they are appended to
+#[derive(Default)]
+struct Pending {
+    lines: Vec<(u64, String)>,
+    file: Option<File>,
+}
+
+pub struct WriteAheadLog {
+    path: PathBuf,
+    /// Encrypts each line like the state file if given
+    key: Option<FileKey>,
+    pending: Mutex<Pending>,
+}
+
+impl WriteAheadLog {
+    pub fn new<P: Into<PathBuf>>(path: P, key: Option<FileKey>) -> Self {
+        Self {
+            path: path.into(),
+            key,
+            pending: Mutex::new(Pending::default()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Changes left by a previous run, oldest first. A line cut off by a crash while appending
+    /// ends the log.
+    pub fn replay<T: DeserializeOwned>(&self) -> Result<Vec<T>, FileError> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(FileError::IO(e)),
+        };
+        let mut changes = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(FileError::IO)?;
+            match self.decode(&line) {
+                Ok(entry) => changes.push(entry.change),
+                Err(e) => {
+                    tracing::warn!("ignoring the rest of {}: {:?}", self.path.display(), e);
+                    break;
+                }
+            }
+        }
+        Ok(changes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, line: &str) -> Result<Entry<T>, FileError> {
+        let bytes = match &self.key {
+            Some(_) => STANDARD
+                .decode(line)
+                .map_err(|_| FileError::Encryption)
+                .and_then(|bytes| files::decrypt(bytes, self.key.as_ref()))?,
+            None => line.as_bytes().to_vec(),
+        };
+        serde_json::from_slice(&bytes).map_err(FileError::Serde)
+    }
+
+    fn encode<T: Serialize>(&self, entry: &Entry<T>) -> String {
+        let json = serde_json::to_vec(entry).expect("changes are serializable");
+        match &self.key {
+            Some(key) => STANDARD.encode(key.encrypt(&json)),
+            None => String::from_utf8(json).expect("JSON is UTF-8"),
+        }
+    }
+
+    /// Appends the change resulting in version `seq` and waits until it is on disk
+    pub fn append<T: Serialize>(&self, seq: u64, change: T) -> Result<(), FileError> {
+        let mut line = self.encode(&Entry { seq, change });
+        line.push('\n');
+        let mut pending = self.pending.lock().unwrap();
+        let Pending { lines, file } = &mut *pending;
+        if file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent).map_err(FileError::IO)?;
+            }
+            let opened = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .map_err(FileError::IO)?;
+            *file = Some(opened);
+        }
+        let appended = file.as_mut().unwrap();
+        appended
+            .write_all(line.as_bytes())
+            .and_then(|_| appended.sync_data())
+            .map_err(FileError::IO)?;
+        lines.push((seq, line));
+        Ok(())
+    }
+
+    /// Drops the changes up to version `seq`, which a save covers now
+    pub fn saved(&self, seq: u64) -> Result<(), FileError> {
+        let mut pending = self.pending.lock().unwrap();
+        let Pending { lines, file } = &mut *pending;
+        let before = lines.len();
+        lines.retain(|(version, _)| *version > seq);
+        match (lines.len() == before, lines.is_empty(), file.as_ref()) {
+            (true, _, _) => Ok(()),
+            (false, true, Some(file)) => file.set_len(0).map_err(FileError::IO),
+            _ => {
+                let rest: String = lines.iter().map(|(_, line)| line.as_str()).collect();
+                files::write_bytes(&self.path, rest.as_bytes())?;
+                // the file was replaced, later changes are appended to the new one
+                *file = None;
+                Ok(())
+            }
+        }
+    }
+
+    /// Removes the changes of a previous run once they are saved
+    pub fn clear(&self) -> Result<(), FileError> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(FileError::IO(e)),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+// Refreshes the trackers every few seconds. With the `dev` feature the page also reloads itself
+// when the assets on disk change, see `GET /ui/version`.
+// Changes made while the server cannot be reached are queued in the browser and replayed once it
+// answers again: new trackers in one `POST /trackers`, the rest in order. Every request keeps its
+// `Idempotency-Key` across retries, so that a request that got through before is not applied twice.
+
+const version = document.body.dataset.version;
+// `DISPLAY_ROUNDING_STEP` in seconds, 0 if not set
+const displayStep = Number(document.body.dataset.displayStep);
+const units = {days: 86400, day: 86400, h: 3600, m: 60, s: 1};
+const QUEUE = "jira-tracker-queue";
+
+// Rounds a duration like `1h 2m 3s` as `DisplayRounding::shown` does, e.g. to `1h 00m`
+function shown(duration) {
+    let seconds = 0;
+    for (const [, amount, unit] of duration.matchAll(/(\d+)([a-z]+)/g)) {
+        seconds += Number(amount) * (units[unit] ?? 0);
+    }
+    if (displayStep > 0) {
+        seconds = Math.round(seconds / displayStep) * displayStep;
+    }
+    const minutes = Math.floor(seconds / 60);
+    return `${Math.floor(minutes / 60)}h ${String(minutes % 60).padStart(2, "0")}m`;
+}
+
+async function fetchJson(path) {
+    try {
+        const response = await fetch(path, {headers: {Accept: "application/json"}});
+        return response.ok ? response.json() : null;
+    } catch {
+        return null;
+    }
+}
+
+// `{create: {id, keys, sent} | null, actions: [{id, path, body}]}`
+function loadQueue() {
+    return JSON.parse(localStorage.getItem(QUEUE) ?? "null") ?? {create: null, actions: []};
+}
+
+function saveQueue(queue) {
+    localStorage.setItem(QUEUE, JSON.stringify(queue));
+    const pending = (queue.create?.keys.length ?? 0) + queue.actions.length;
+    document.getElementById("queued").textContent = pending ? `${pending} changes queued` : "";
+}
+
+// Resolves to false if the server could not be reached, the request is to be retried then
+async function send(path, id, body) {
+    try {
+        const response = await fetch(path, {
+            method: "POST",
+            headers: {"Idempotency-Key": id, "Content-Type": "application/json"},
+            body: body === undefined ? undefined : JSON.stringify(body),
+        });
+        return response.status < 500;
+    } catch {
+        return false;
+    }
+}
+
+// Removes a change from the queue once the server answered it
+function done(id) {
+    const queue = loadQueue();
+    if (queue.create?.id === id) {
+        queue.create = null;
+    }
+    queue.actions = queue.actions.filter(action => action.id !== id);
+    saveQueue(queue);
+}
+
+// Sends the queued changes in order, stopping at the first one that does not get through. The
+// queue is read again after each request, as changes may be queued meanwhile.
+async function sendQueued() {
+    for (;;) {
+        const queue = loadQueue();
+        let next = queue.actions[0];
+        if (queue.create) {
+            queue.create.sent = true;
+            saveQueue(queue);
+            next = {id: queue.create.id, path: "/trackers", body: queue.create.keys};
+        }
+        if (!next) {
+            return true;
+        }
+        if (!await send(next.path, next.id, next.body)) {
+            return false;
+        }
+        done(next.id);
+    }
+}
+
+let replaying = null;
+
+function replay() {
+    replaying ??= sendQueued().finally(() => replaying = null);
+    return replaying;
+}
+
+function createTrackers(keys) {
+    const queue = loadQueue();
+    // a batch that was sent already must keep its body, as its key may have been used
+    if (!queue.create || queue.create.sent) {
+        if (queue.create) {
+            const {id, keys} = queue.create;
+            queue.actions.unshift({id, path: "/trackers", body: keys});
+        }
+        queue.create = {id: crypto.randomUUID(), keys: [], sent: false};
+    }
+    queue.create.keys.push(...keys);
+    saveQueue(queue);
+    replay().then(refresh);
+}
+
+function queueAction(path) {
+    const queue = loadQueue();
+    queue.actions.push({id: crypto.randomUUID(), path});
+    saveQueue(queue);
+    replay().then(refresh);
+}
+
+function cell(row, text) {
+    const td = document.createElement("td");
+    td.textContent = text ?? "";
+    row.appendChild(td);
+    return td;
+}
+
+async function refresh() {
+    if (!await replay()) {
+        return;
+    }
+    const [trackers, sum] = await Promise.all([fetchJson("/trackers"), fetchJson("/sum")]);
+    if (trackers === null) {
+        return;
+    }
+    const body = document.getElementById("trackers");
+    body.replaceChildren();
+    let running = null;
+    for (const tracker of trackers) {
+        const row = document.createElement("tr");
+        row.classList.toggle("running", tracker.running);
+        cell(row, tracker.key);
+        cell(row, tracker.description ?? tracker.details?.summary);
+        cell(row, shown(tracker.duration));
+        const toggle = document.createElement("button");
+        toggle.textContent = tracker.running ? "Pause" : "Start";
+        toggle.onclick = () => queueAction(`/trackers/${encodeURIComponent(tracker.key)}/toggle`);
+        cell(row).appendChild(toggle);
+        body.appendChild(row);
+        if (tracker.running) {
+            running = tracker;
+        }
+    }
+    document.getElementById("running").textContent = running ? `Tracking ${running.key}` : "";
+    document.getElementById("sum").textContent = sum ? `Total ${shown(sum.duration)}` : "";
+}
+
+async function watchAssets() {
+    const current = await fetchJson("/ui/version");
+    if (current?.live) {
+        if (current.version !== version) {
+            location.reload();
+        }
+        setTimeout(watchAssets, 1000);
+    }
+}
+
+document.getElementById("add").onsubmit = event => {
+    event.preventDefault();
+    const input = document.getElementById("keys");
+    const keys = input.value.split(/[\s,]+/).filter(key => key);
+    if (keys.length) {
+        createTrackers(keys);
+    }
+    input.value = "";
+};
+
+if ("serviceWorker" in navigator) {
+    navigator.serviceWorker.register("/ui/sw.js", {scope: "/ui"});
+}
+
+saveQueue(loadQueue());
+window.addEventListener("online", refresh);
+refresh();
+setInterval(refresh, 5000);
+watchAssets();
@@ -0,0 +1,32 @@
+// Keeps the dashboard available without the server: the page and its assets are answered from the
+// cache if the server cannot be reached. Requests of the API are left to the page, which queues
+// changes itself, see `app.js`.
+
+const CACHE = "jira-tracker-ui";
+
+self.addEventListener("install", () => self.skipWaiting());
+self.addEventListener("activate", event => event.waitUntil(self.clients.claim()));
+
+self.addEventListener("fetch", event => {
+    const url = new URL(event.request.url);
+    if (event.request.method !== "GET" || !url.pathname.startsWith("/ui") ||
+        url.pathname === "/ui/version") {
+        return;
+    }
+    event.respondWith((async () => {
+        const cache = await caches.open(CACHE);
+        try {
+            const response = await fetch(event.request);
+            if (response.ok) {
+                await cache.put(event.request, response.clone());
+            }
+            return response;
+        } catch (error) {
+            const cached = await cache.match(event.request);
+            if (cached) {
+                return cached;
+            }
+            throw error;
+        }
+    })());
+});